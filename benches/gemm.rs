@@ -0,0 +1,67 @@
+//! Simulation speed on the 8x8 GEMM kernel, run with `cargo bench`.
+//! A std-only harness: the kernel is simulated to the end a number of times and the mean is reported,
+//! on a freshly loaded grid (cold) and on a grid restored to its first cycle (warm, as in a sweep).
+
+use std::time::{Duration, Instant};
+
+use pace_sim::sim::{
+    grid::{Grid, SimulationError},
+    topology::MemoryTopology,
+};
+
+const RUNS: u32 = 500;
+
+/// Simulate the grid until the AGUs stop it, return the number of simulated cycles
+fn run_to_end(grid: &mut Grid) -> usize {
+    let mut cycles = 0;
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return cycles,
+            Err(e) => panic!("Unexpected simulation error at cycle {}: {:?}", cycles, e),
+        }
+        grid.next_cycle();
+        cycles += 1;
+    }
+}
+
+fn report(name: &str, cycles: usize, total: Duration) {
+    let per_run = total / RUNS;
+    println!(
+        "gemm 8x8 {}: {} cycles, {:?} per run, {:.0} cycles/s ({} runs)",
+        name,
+        cycles,
+        per_run,
+        cycles as f64 / per_run.as_secs_f64(),
+        RUNS
+    );
+}
+
+fn main() {
+    let folder = format!("{}/tests/gemm", env!("CARGO_MANIFEST_DIR"));
+    let load = || {
+        Grid::from_folder(&folder, MemoryTopology::double_sided()).expect("Failed to load the grid")
+    };
+    // warm up
+    let cycles = run_to_end(&mut load());
+
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let mut grid = load();
+        let start = Instant::now();
+        run_to_end(&mut grid);
+        total += start.elapsed();
+    }
+    report("cold", cycles, total);
+
+    let mut grid = load();
+    let start_state = grid.checkpoint();
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        grid.restore(&start_state).unwrap();
+        let start = Instant::now();
+        run_to_end(&mut grid);
+        total += start.elapsed();
+    }
+    report("warm", cycles, total);
+}
@@ -4,6 +4,7 @@ use nom::{
     IResult, Parser,
     bytes::complete::tag,
     character::complete::{digit1, multispace0},
+    combinator::map_res,
     multi::separated_list0,
 };
 
@@ -103,7 +104,8 @@ impl Display for AGU {
 }
 
 impl AGU {
-    fn parse_mnemonics(s: &str) -> IResult<&str, Self> {
+    /// Parse the AGU mnemonics, the content is not validated, see AGU::validate
+    pub fn parse_mnemonics(s: &str) -> IResult<&str, Self> {
         let (input, _) = multispace0(s)?;
         let (input, _) = tag("CM:").parse(input)?;
         let (input, _) = multispace0.parse(input)?;
@@ -111,25 +113,13 @@ impl AGU {
         let (input, _) = multispace0.parse(input)?;
         let (input, _) = tag("ARF:").parse(input)?;
         let (input, _) = multispace0.parse(input)?;
-        let (input, arf) = separated_list0(multispace0, digit1).parse(input)?;
+        let (input, arf) =
+            separated_list0(multispace0, map_res(digit1, str::parse::<u16>)).parse(input)?;
         let (input, _) = multispace0.parse(input)?;
         let (input, _) = tag("MAX COUNT:").parse(input)?;
         let (input, _) = multispace0.parse(input)?;
-        let (input, max_count) = digit1.parse(input)?;
-        let max_count = max_count.parse::<u32>().unwrap();
-        let arf: Vec<u16> = arf.iter().map(|s| s.parse::<u16>().unwrap()).collect();
+        let (input, max_count) = map_res(digit1, str::parse::<u32>).parse(input)?;
         let (input, _) = multispace0.parse(input)?;
-        assert!(
-            arf.len() == cm.len(),
-            "ARF and CM must have the same length"
-        );
-        if arf.len() == 0 {
-            assert!(max_count == 0, "max count must be 0 if the AGU is not used");
-        } else {
-            assert!(cm.len() > 0, "CM must have at least one instruction");
-            assert!(arf.len() > 0, "ARF must have at least one address");
-            assert!(max_count > 0, "max count must be greater than 0");
-        }
         Ok((
             input,
             Self {
@@ -142,9 +132,31 @@ impl AGU {
         ))
     }
 
+    /// Check the consistency of the CM, ARF and max count
+    pub fn validate(&self) -> Result<(), String> {
+        if self.arf.len() != self.cm.len() {
+            return Err(format!(
+                "ARF and CM must have the same length, got {} instructions and {} addresses",
+                self.cm.len(),
+                self.arf.len()
+            ));
+        }
+        if self.cm.is_empty() {
+            if self.max_count != 0 {
+                return Err("max count must be 0 if the AGU is not used".to_string());
+            }
+        } else if self.max_count == 0 {
+            return Err("max count must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
     pub fn from_mnemonics(s: &str) -> Result<Self, String> {
         let (input, state) = Self::parse_mnemonics(s).map_err(|e| e.to_string())?;
-        assert!(input.is_empty());
+        if !input.is_empty() {
+            return Err(format!("Unexpected input after MAX COUNT: {}", input));
+        }
+        state.validate()?;
         Ok(state)
     }
 
@@ -4,12 +4,14 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{digit1, multispace0},
+    combinator::map_res,
     sequence::delimited,
 };
+use serde::{Deserialize, Serialize};
 use std::{fmt::Display, str::FromStr};
 use strum_macros::{Display, EnumString};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Instruction {
     pub inst_type: InstType,
     pub inst_mode: InstMode,
@@ -17,19 +19,19 @@ pub struct Instruction {
     pub stride: u8, // can only be used as 4b integer
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
 pub enum InstType {
     LOAD = 0,
     STORE = 1,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
 pub enum InstMode {
     STRIDED = 0,
     CONST = 1,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
 pub enum DataWidth {
     B8 = 0,
     B16 = 1,
@@ -44,9 +46,8 @@ impl Instruction {
         let (input, _) = delimited(multispace0, tag(","), multispace0).parse(input)?;
         let (input, data_width) = alt((tag("B8"), tag("B16"), tag("B64"))).parse(input)?;
         let (input, _) = delimited(multispace0, tag(","), multispace0).parse(input)?;
-        let (input, stride) = digit1.parse(input)?;
+        let (input, stride) = map_res(digit1, str::parse::<u8>).parse(input)?;
         let inst_mode: InstMode = inst_mode.parse().unwrap();
-        let stride = stride.parse::<u8>().unwrap();
         if inst_mode == InstMode::CONST && stride != 0 {
             warn!(
                 "Warning when loading AGU configuration: you are in CONST mode but you specified stride in the instruction"
@@ -0,0 +1,100 @@
+use clap::Parser;
+use log::error;
+use pace_sim::sim::{
+    arch::Architecture,
+    dmem::MemoryFaultPolicy,
+    fault_injection::Campaign,
+    golden::{AddressRange, Golden},
+    grid::Grid,
+    topology::TopologyPreset,
+};
+
+/// Run a fault-injection campaign on a grid of PEs
+#[derive(Parser, Debug)]
+#[command(about = "Fault-injection campaign", long_about = None)]
+struct Args {
+    /// The folder path of the grid to simulate.
+    #[clap(long)]
+    folder_path: String,
+    /// The campaign description (TOML, or JSON by the extension), see src/sim/fault_injection.rs.
+    #[clap(long, value_name = "FILE")]
+    campaign: String,
+    /// The golden DMs, a folder of dmN files as for simulation --expect.
+    /// The DMs of the fault-free run by default.
+    #[clap(long, value_name = "FOLDER")]
+    expect: Option<String>,
+    /// Only compare a byte range of an expected DM, can be repeated, e.g. dm1:0x0..0x20.
+    #[clap(long, value_name = "RANGE", requires = "expect")]
+    expect_range: Vec<AddressRange>,
+    /// Write the injections and the outcome of every run to the file, one run per line.
+    #[clap(long, value_name = "FILE")]
+    runs: Option<String>,
+    /// The memory layout of the grid.
+    #[clap(short, long, default_value = "double-sided")]
+    topology: TopologyPreset,
+    /// The architecture descriptor (TOML, or JSON by the extension), replaces --topology.
+    #[clap(long, value_name = "FILE", conflicts_with = "topology")]
+    arch: Option<String>,
+    /// What to do with a DM access out of range or misaligned, see simulation --help.
+    /// A memory fault crashes the run with the default.
+    #[clap(long, default_value = "fault")]
+    mem_fault: MemoryFaultPolicy,
+}
+
+fn main() {
+    env_logger::init();
+    let args = Args::parse();
+    let loaded = match &args.arch {
+        Some(arch) => Architecture::load(std::path::Path::new(arch))
+            .and_then(|arch| Grid::from_folder_with_arch(&args.folder_path, &arch)),
+        None => Grid::from_folder(&args.folder_path, args.topology.into()),
+    };
+    let golden = args
+        .expect
+        .as_ref()
+        .map(|folder| Golden::load(std::path::Path::new(folder)))
+        .transpose();
+    let campaign = Campaign::load(std::path::Path::new(&args.campaign));
+    let (mut grid, mut golden, campaign) = match (loaded, golden, campaign) {
+        (Ok(grid), Ok(golden), Ok(campaign)) => (grid, golden, campaign),
+        (grid, golden, campaign) => {
+            for e in [grid.err(), golden.err(), campaign.err()]
+                .into_iter()
+                .flatten()
+            {
+                for e in e.errors() {
+                    error!("{}", e);
+                }
+            }
+            std::process::exit(1);
+        }
+    };
+    grid.mem_fault_policy = args.mem_fault;
+    if let Some(golden) = golden.as_mut() {
+        for range in args.expect_range.iter() {
+            golden.restrict(range.clone());
+        }
+    }
+    // the runs which panic are reported as crashed
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|info| log::debug!("{}", info)));
+    let report = campaign.run(&grid, golden.as_ref());
+    std::panic::set_hook(hook);
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Cannot run the campaign: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = &args.runs {
+        let lines: Vec<String> = report
+            .runs
+            .iter()
+            .enumerate()
+            .map(|(i, run)| format!("run {}: {}\n", i, run))
+            .collect();
+        std::fs::write(path, lines.concat()).unwrap();
+    }
+    print!("{}", report);
+}
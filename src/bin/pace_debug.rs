@@ -0,0 +1,288 @@
+use std::io::{BufRead, Write};
+
+use clap::Parser;
+use log::error;
+use pace_sim::{
+    isa::pe::PERegister,
+    sim::{
+        arch::Architecture,
+        breakpoint::Breakpoint,
+        dmem::MemoryFaultPolicy,
+        grid::{Grid, PEIdx, SimulationError},
+        route_check::MAX_PC_COMBINATIONS,
+        topology::TopologyPreset,
+    },
+};
+
+/// Step through the simulation of a grid of PEs
+#[derive(Parser, Debug)]
+#[command(about = "Interactive simulation debugger", long_about = None)]
+struct Args {
+    /// The folder path of the grid to simulate.
+    #[clap(long)]
+    folder_path: String,
+    /// The memory layout of the grid.
+    #[clap(short, long, default_value = "double-sided")]
+    topology: TopologyPreset,
+    /// The architecture descriptor (TOML, or JSON by the extension), replaces --topology.
+    /// The folder is checked against it and its DMs padded to the DM size.
+    #[clap(long, value_name = "FILE", conflicts_with = "topology")]
+    arch: Option<String>,
+    /// Breakpoints to start with, can be repeated. Same syntax as the `break` command.
+    #[clap(long = "break", value_name = "CONDITION")]
+    breakpoints: Vec<Breakpoint>,
+    /// What to do with a DM access out of range or misaligned, see simulation --help.
+    #[clap(long, default_value = "fault")]
+    mem_fault: MemoryFaultPolicy,
+}
+
+const HELP: &str = "\
+Commands (numbers are decimal or 0x hexadecimal):
+  step [n]                    simulate n cycles (default 1), stops early on a breakpoint
+  continue                    simulate until a breakpoint or the end of the simulation
+  print pe Y X                state of the PE, as in the PE-YyXx.state snapshots
+  print dm N addr len         len bytes of the data memory N from addr
+  print agu N                 state of the AGU N
+  route Y X                   router configuration of the PE
+  set reg Y X <reg> <value>   write a register of the PE, e.g. set reg 0 1 reg_op1 0x10
+  break                       list the breakpoints
+  break <condition>           add a breakpoint, e.g. break PE-Y0X1 pc 3, see simulation --help
+  break clear                 remove all the breakpoints
+  help                        show this message
+  quit                        exit";
+
+/// The state shown is the one after the last simulated cycle, as in the cycle_N snapshots
+/// of the simulation binary: the next_cycle() of a cycle is delayed to the next step.
+struct Debugger {
+    grid: Grid,
+    breakpoints: Vec<Breakpoint>,
+    /// The number of simulated cycles
+    cycle: usize,
+    /// Whether the grid has to move to the next cycle before simulating
+    pending_next_cycle: bool,
+    /// Set when the simulation cannot continue
+    finished: bool,
+}
+
+impl Debugger {
+    /// Simulate one cycle, returns false if the execution stopped (breakpoint, end of simulation or error)
+    fn step(&mut self) -> bool {
+        if self.finished {
+            println!("The simulation has finished");
+            return false;
+        }
+        if self.pending_next_cycle {
+            self.grid.next_cycle();
+        }
+        let result = self.grid.simulate_cycle_with_breakpoints(&self.breakpoints);
+        self.pending_next_cycle = true;
+        let cycle = self.cycle;
+        self.cycle += 1;
+        match result {
+            Ok(()) => true,
+            Err(SimulationError::Breakpoint(reason)) => {
+                println!("Cycle {}: {}", cycle, reason);
+                false
+            }
+            Err(SimulationError::SimulationEnd) => {
+                println!("Cycle {}: simulation finished by AGU signal", cycle);
+                self.finished = true;
+                false
+            }
+            Err(SimulationError::CombinationalLoop(router_loop)) => {
+                println!(
+                    "Cycle {}: combinational loop in the router: {}",
+                    cycle, router_loop
+                );
+                self.finished = true;
+                false
+            }
+            Err(SimulationError::MemoryFault(fault)) => {
+                println!("Memory fault at {}", fault);
+                self.finished = true;
+                false
+            }
+            Err(SimulationError::PEUpdateError(pe_idx, e)) => {
+                println!(
+                    "Cycle {}: PEUpdateError at PE-Y{}X{}: {}",
+                    cycle, pe_idx.y, pe_idx.x, e
+                );
+                self.finished = true;
+                false
+            }
+        }
+    }
+
+    /// Execute a command line, returns false to quit
+    fn execute(&mut self, line: &str) -> Result<bool, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["step" | "s"] => {
+                self.step();
+                println!("{} cycles simulated", self.cycle);
+            }
+            ["step" | "s", n] => {
+                for _ in 0..parse_number(n)? {
+                    if !self.step() {
+                        break;
+                    }
+                }
+                println!("{} cycles simulated", self.cycle);
+            }
+            ["continue" | "c"] => {
+                while self.step() {}
+                println!("{} cycles simulated", self.cycle);
+            }
+            ["print" | "p", "pe", y, x] => {
+                let pe_idx = self.pe_idx(y, x)?;
+                println!("{}", self.grid.pes[pe_idx.y][pe_idx.x].snapshot());
+            }
+            ["print" | "p", "dm", n, addr, len] => {
+                let dm = self
+                    .grid
+                    .dmems
+                    .get(parse_number(n)? as usize)
+                    .ok_or(format!("dm{} does not exist", n))?;
+                let addr = parse_number(addr)? as usize;
+                if addr >= dm.capacity() {
+                    return Err(format!(
+                        "Address {} is out of the data memory of {} bytes",
+                        addr,
+                        dm.capacity()
+                    ));
+                }
+                println!("{}", dm.dump_range(addr, parse_number(len)? as usize));
+            }
+            ["print" | "p", "agu", n] => {
+                let agu = self
+                    .grid
+                    .agus
+                    .get(parse_number(n)? as usize)
+                    .ok_or(format!("agu{} does not exist", n))?;
+                print!("{}", agu);
+            }
+            ["route", y, x] => {
+                let pe_idx = self.pe_idx(y, x)?;
+                let pe = &self.grid.pes[pe_idx.y][pe_idx.x];
+                println!("PC: {}", pe.pc);
+                println!("{}", pe.current_conf().router_config.to_mnemonics());
+            }
+            ["set", "reg", y, x, reg, value] => {
+                let pe_idx = self.pe_idx(y, x)?;
+                let reg: PERegister = reg
+                    .parse()
+                    .map_err(|_| format!("Unknown register: {}", reg))?;
+                let value = parse_number(value)?;
+                self.grid.pes[pe_idx.y][pe_idx.x].regs.set(reg, value);
+            }
+            ["break" | "b"] => {
+                for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+                    println!("{}: {}", i, breakpoint);
+                }
+            }
+            ["break" | "b", "clear"] => self.breakpoints.clear(),
+            ["break" | "b", ..] => {
+                let breakpoint: Breakpoint = line.trim()[words[0].len()..].parse()?;
+                breakpoint.validate(&self.grid)?;
+                self.breakpoints.push(breakpoint);
+            }
+            ["help" | "h"] => println!("{}", HELP),
+            ["quit" | "q" | "exit"] => return Ok(false),
+            _ => return Err(format!("Unknown command: {}, see help", line.trim())),
+        }
+        Ok(true)
+    }
+
+    /// Parse and check the coordinates of a PE
+    fn pe_idx(&self, y: &str, x: &str) -> Result<PEIdx, String> {
+        let pe_idx = PEIdx {
+            x: parse_number(x)? as usize,
+            y: parse_number(y)? as usize,
+        };
+        if pe_idx.x >= self.grid.shape.x || pe_idx.y >= self.grid.shape.y {
+            return Err(format!(
+                "PE (y={}, x={}) is out of the {} cols x {} rows grid",
+                pe_idx.y, pe_idx.x, self.grid.shape.x, self.grid.shape.y
+            ));
+        }
+        Ok(pe_idx)
+    }
+}
+
+fn parse_number(s: &str) -> Result<u64, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("Invalid number: {}", s))
+}
+
+fn main() {
+    env_logger::init();
+    let args = Args::parse();
+    let loaded = match &args.arch {
+        Some(arch) => Architecture::load(std::path::Path::new(arch))
+            .and_then(|arch| Grid::from_folder_with_arch(&args.folder_path, &arch)),
+        None => Grid::from_folder(&args.folder_path, args.topology.into()),
+    };
+    let mut grid = match loaded {
+        Ok(grid) => grid,
+        Err(e) => {
+            for e in e.errors() {
+                error!("{}", e);
+            }
+            std::process::exit(1);
+        }
+    };
+    grid.mem_fault_policy = args.mem_fault;
+    for breakpoint in args.breakpoints.iter() {
+        if let Err(e) = breakpoint.validate(&grid) {
+            error!("Invalid breakpoint `{}`: {}", breakpoint, e);
+            std::process::exit(1);
+        }
+    }
+    // the routing mistakes are reported but the grid can still be debugged
+    for diagnostic in grid.check_routing() {
+        println!("Routing error: {}", diagnostic);
+    }
+    let router_loops = grid.check_router_loops();
+    for (cycle, router_loop) in router_loops.loops.iter() {
+        println!(
+            "Combinational loop in the router at cycle {}: {}",
+            cycle, router_loop
+        );
+    }
+    if router_loops.truncated {
+        println!(
+            "The router loop check stopped after {} combinations of PCs, the others are not checked",
+            MAX_PC_COMBINATIONS
+        );
+    }
+    println!(
+        "Loaded a {} cols x {} rows grid from {}, type `help` for the commands",
+        grid.shape.x, grid.shape.y, args.folder_path
+    );
+    let mut debugger = Debugger {
+        grid,
+        breakpoints: args.breakpoints,
+        cycle: 0,
+        pending_next_cycle: false,
+        finished: false,
+    };
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(pace-debug) ");
+        std::io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        match debugger.execute(&line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
@@ -1,6 +1,17 @@
 use clap::{Parser, ValueEnum};
-use log::{LevelFilter, error, info};
-use pace_sim::sim::grid::{DoubleSidedMemoryGrid, SimulationError};
+use log::{LevelFilter, error, info, warn};
+use pace_sim::sim::{
+    arch::Architecture,
+    breakpoint::Breakpoint,
+    checkpoint::Checkpoint,
+    dmem::MemoryFaultPolicy,
+    golden::{AddressRange, Golden},
+    grid::{Grid, SimulationError},
+    route_check::MAX_PC_COMBINATIONS,
+    rtl_trace::RtlTrace,
+    topology::TopologyPreset,
+    vcd::{PERange, VcdWriter},
+};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 enum LogLevel {
@@ -36,6 +47,48 @@ struct Args {
     /// Dump the snapshot for every cycle.
     #[clap(long)]
     full_trace: bool,
+    /// Stop the simulation when the condition is met, can be repeated.
+    /// e.g. "PE-Y1X0 pc 3", "PE-Y1X0 reg_res", "PE-Y1X0 reg_res == 0x10", "dm0 read 16", "dm0 write 16", "agu2 count 4".
+    /// A snapshot is saved to <folder_path>/breakpoint/cycle_N when a breakpoint is hit.
+    #[clap(long = "break", value_name = "CONDITION")]
+    breakpoints: Vec<Breakpoint>,
+    /// Save a checkpoint every N cycles to <folder_path>/checkpoint/cycle_N.json.
+    #[clap(long, value_name = "N")]
+    checkpoint_every: Option<usize>,
+    /// Resume the simulation from a checkpoint of the same folder.
+    #[clap(long, value_name = "FILE")]
+    resume: Option<String>,
+    /// Write the waveform of the simulation to a VCD file.
+    #[clap(long, value_name = "FILE")]
+    vcd: Option<String>,
+    /// Only dump the PEs in the range to the VCD file (and the DM ports and AGUs of the memory PEs in it), e.g. PE-Y0X0:PE-Y3X7.
+    #[clap(long, value_name = "RANGE", requires = "vcd")]
+    vcd_pes: Option<PERange>,
+    /// Skip the static check of the routing and simulate even if the routing has mistakes.
+    #[clap(long)]
+    no_route_check: bool,
+    /// The memory layout of the grid.
+    #[clap(short, long, default_value = "double-sided")]
+    topology: TopologyPreset,
+    /// The architecture descriptor (TOML, or JSON by the extension), replaces --topology.
+    /// The folder is checked against it and its DMs padded to the DM size.
+    #[clap(long, value_name = "FILE", conflicts_with = "topology")]
+    arch: Option<String>,
+    /// What to do with a DM access out of range or misaligned: stop the simulation (fault),
+    /// wrap the address modulo the DM size (wrap), or log it and skip the access (log).
+    #[clap(long, default_value = "fault")]
+    mem_fault: MemoryFaultPolicy,
+    /// Compare the final DMs with the dmN files of the folder, where `x` bits are not compared,
+    /// and exit with an error on a mismatch.
+    #[clap(long, value_name = "FOLDER")]
+    expect: Option<String>,
+    /// Only compare a byte range of an expected DM, can be repeated, e.g. dm1:0x0..0x20.
+    #[clap(long, value_name = "RANGE", requires = "expect")]
+    expect_range: Vec<AddressRange>,
+    /// Run in lock-step with the trace of the RTL simulation and stop at the first difference
+    /// of a PC, a register or a DM write, see src/sim/rtl_trace.rs for the format.
+    #[clap(long, value_name = "FILE")]
+    rtl_trace: Option<String>,
     /// Set the log level.
     #[clap(short, long, default_value = "Info")]
     log_level: LogLevel,
@@ -44,15 +97,113 @@ struct Args {
 fn main() {
     env_logger::init();
     let args = Args::parse();
-    let mut grid = DoubleSidedMemoryGrid::from_folder(&args.folder_path);
+    let loaded = match &args.arch {
+        Some(arch) => Architecture::load(std::path::Path::new(arch))
+            .and_then(|arch| Grid::from_folder_with_arch(&args.folder_path, &arch)),
+        None => Grid::from_folder(&args.folder_path, args.topology.into()),
+    };
+    let mut grid = match loaded {
+        Ok(grid) => grid,
+        Err(e) => {
+            // report every problem found in the folder
+            for e in e.errors() {
+                error!("{}", e);
+            }
+            std::process::exit(1);
+        }
+    };
+    grid.mem_fault_policy = args.mem_fault;
+    let golden = args.expect.as_ref().map(|folder| {
+        let mut golden = match Golden::load(std::path::Path::new(folder)) {
+            Ok(golden) => golden,
+            Err(e) => {
+                for e in e.errors() {
+                    error!("{}", e);
+                }
+                std::process::exit(1);
+            }
+        };
+        for range in args.expect_range.iter() {
+            golden.restrict(range.clone());
+        }
+        golden
+    });
+    let rtl_trace = args.rtl_trace.as_ref().map(|path| {
+        let trace = match RtlTrace::load(std::path::Path::new(path)) {
+            Ok(trace) => trace,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = trace.validate(&grid) {
+            error!("Invalid RTL trace {}: {}", path, e);
+            std::process::exit(1);
+        }
+        trace
+    });
+    let mut diverged = false;
+    for breakpoint in args.breakpoints.iter() {
+        if let Err(e) = breakpoint.validate(&grid) {
+            error!("Invalid breakpoint `{}`: {}", breakpoint, e);
+            std::process::exit(1);
+        }
+    }
+    if !args.no_route_check {
+        let diagnostics = grid.check_routing();
+        for diagnostic in diagnostics.iter() {
+            error!("Routing error: {}", diagnostic);
+        }
+        let router_loops = grid.check_router_loops();
+        for (cycle, router_loop) in router_loops.loops.iter() {
+            error!(
+                "Combinational loop in the router at cycle {}: {}",
+                cycle, router_loop
+            );
+        }
+        if router_loops.truncated {
+            warn!(
+                "The router loop check stopped after {} combinations of PCs, the others are not checked",
+                MAX_PC_COMBINATIONS
+            );
+        }
+        if !diagnostics.is_empty() || !router_loops.loops.is_empty() {
+            std::process::exit(1);
+        }
+    }
     let mut cycle = 0;
+    if let Some(resume) = &args.resume {
+        let checkpoint = match Checkpoint::load(std::path::Path::new(resume)) {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        cycle = match grid.restore(&checkpoint) {
+            Ok(cycle) => cycle,
+            Err(e) => {
+                error!("Cannot resume from {}: {}", resume, e);
+                std::process::exit(1);
+            }
+        };
+        info!("Resuming from cycle {}", cycle);
+    }
+    let mut vcd = args.vcd.as_ref().map(|path| {
+        let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+        VcdWriter::new(file, &grid, args.vcd_pes).unwrap()
+    });
     loop {
         if let Some(cycles) = args.cycles {
             if cycle >= cycles {
                 break;
             }
         }
-        if let Err(e) = grid.simulate_cycle() {
+        let result = grid.simulate_cycle_with_breakpoints(&args.breakpoints);
+        if let Some(vcd) = vcd.as_mut() {
+            vcd.sample(&grid, cycle).unwrap();
+        }
+        if let Err(e) = result {
             match e {
                 SimulationError::PEUpdateError(pe_idx, e) => {
                     error!("PEUpdateError at PE({},{}): {}", pe_idx.x, pe_idx.y, e);
@@ -69,8 +220,37 @@ fn main() {
                     info!("Simulation finished prematurely");
                     break;
                 }
+                SimulationError::CombinationalLoop(router_loop) => {
+                    error!("Combinational loop in the router: {}", router_loop);
+                    break;
+                }
+                SimulationError::MemoryFault(fault) => {
+                    error!("Memory fault at {}", fault);
+                    let snapshot_folder = format!("{}/debug/cycle_{}", args.folder_path, cycle);
+                    error!("Saving snapshot for debugging at {}", snapshot_folder);
+                    grid.snapshot(snapshot_folder.as_str());
+                    break;
+                }
+                SimulationError::Breakpoint(reason) => {
+                    info!("Cycle {}: {}", cycle, reason);
+                    let snapshot_folder =
+                        format!("{}/breakpoint/cycle_{}", args.folder_path, cycle);
+                    info!("Saving snapshot at {}", snapshot_folder);
+                    grid.snapshot(snapshot_folder.as_str());
+                    break;
+                }
             }
         }
+        if let Some(trace) = &rtl_trace
+            && let Err(divergence) = trace.check(&grid)
+        {
+            error!("Divergence from the RTL trace at {}", divergence);
+            let snapshot_folder = format!("{}/debug/cycle_{}", args.folder_path, cycle);
+            error!("Saving snapshot for debugging at {}", snapshot_folder);
+            grid.snapshot(snapshot_folder.as_str());
+            diverged = true;
+            break;
+        }
         if args.full_trace {
             let snapshot_folder = format!("{}/cycle_{}", args.folder_path, cycle);
             println!(
@@ -83,5 +263,44 @@ fn main() {
         }
         grid.next_cycle();
         cycle += 1;
+        if let Some(every) = args.checkpoint_every
+            && cycle.is_multiple_of(every)
+        {
+            let checkpoint_path = format!("{}/checkpoint/cycle_{}.json", args.folder_path, cycle);
+            info!("Saving checkpoint at {}", checkpoint_path);
+            grid.checkpoint()
+                .save(std::path::Path::new(&checkpoint_path))
+                .unwrap();
+        }
+    }
+    if let Some(vcd) = vcd.as_mut() {
+        vcd.flush().unwrap();
+    }
+    println!("{}", grid.stats);
+    if let Some(last) = rtl_trace.as_ref().and_then(|trace| trace.last_cycle())
+        && !diverged
+        && last >= cycle
+    {
+        warn!(
+            "The simulation stopped at cycle {}, the RTL trace goes on to cycle {}",
+            cycle, last
+        );
+    }
+    if let Some(golden) = golden {
+        match golden.compare(&grid.dmems) {
+            Ok(comparison) => {
+                println!("{}", comparison);
+                if !comparison.is_match() {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                error!("Cannot compare the data memories: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if diverged {
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,259 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// bfloat16: 1 sign bit, 8 exponent bits (bias 127), 7 mantissa bits, the high half of an f32.
+/// Used by the scalar ALU operations in floating-point mode with the BF16 format.
+/// The operations are computed in f64 and rounded once to nearest even: f64 is wide enough for
+/// the double rounding of an addition, a subtraction, a multiplication or a division to give
+/// the correctly rounded result, and its exponent range covers the subnormals of a BF16.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct BF16(u16);
+
+impl Debug for BF16 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let float_val: f32 = (*self).into();
+        write!(f, "0x{:04x}({})", self.0, float_val)
+    }
+}
+
+impl BF16 {
+    const EXP_MASK: u16 = 0x7F80;
+    const MANT_MASK: u16 = 0x007F;
+    const SIGN_MASK: u16 = 0x8000;
+    const MANT_BITS: i32 = 7;
+    /// The exponent of the smallest normal number, shared by the subnormals
+    const MIN_EXP: i32 = -126;
+    /// The quiet NaN returned by the operations
+    const NAN: u16 = 0x7FC0;
+
+    pub fn is_nan(&self) -> bool {
+        self.0 & Self::EXP_MASK == Self::EXP_MASK && self.0 & Self::MANT_MASK != 0
+    }
+}
+
+impl From<u16> for BF16 {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BF16> for u16 {
+    fn from(value: BF16) -> Self {
+        value.0
+    }
+}
+
+/// Exact conversion, the BF16 is the high half of the f32
+impl From<BF16> for f32 {
+    fn from(value: BF16) -> Self {
+        f32::from_bits((value.0 as u32) << 16)
+    }
+}
+
+impl From<BF16> for f64 {
+    fn from(value: BF16) -> Self {
+        f32::from(value) as f64
+    }
+}
+
+/// Round to nearest even, the values too large for a BF16 become infinities
+impl From<f64> for BF16 {
+    fn from(value: f64) -> Self {
+        let sign = if value.is_sign_negative() {
+            Self::SIGN_MASK
+        } else {
+            0
+        };
+        if value.is_nan() {
+            return BF16(sign | Self::NAN);
+        }
+        if value.is_infinite() {
+            return BF16(sign | Self::EXP_MASK);
+        }
+        let value = value.abs();
+        // the exponent of the value, the subnormals share the one of the smallest normal number
+        let exp = (((value.to_bits() >> 52) & 0x7FF) as i32 - 1023).max(Self::MIN_EXP);
+        // the value in units of the last place of its exponent, exact with a power of two
+        let units = value / 2f64.powi(exp - Self::MANT_BITS);
+        let mut significand = units.floor();
+        let rem = units - significand;
+        if rem > 0.5 || (rem == 0.5 && significand % 2.0 == 1.0) {
+            significand += 1.0;
+        }
+        // a carry out of the mantissa goes to the exponent, up to the infinity
+        let code = (((exp - Self::MIN_EXP) as u64) << Self::MANT_BITS) + significand as u64;
+        if code >= Self::EXP_MASK as u64 {
+            return BF16(sign | Self::EXP_MASK);
+        }
+        BF16(sign | code as u16)
+    }
+}
+
+impl From<f32> for BF16 {
+    fn from(value: f32) -> Self {
+        BF16::from(value as f64)
+    }
+}
+
+impl Add for BF16 {
+    type Output = BF16;
+
+    fn add(self, other: BF16) -> BF16 {
+        BF16::from(f64::from(self) + f64::from(other))
+    }
+}
+
+impl Sub for BF16 {
+    type Output = BF16;
+
+    fn sub(self, other: BF16) -> BF16 {
+        BF16::from(f64::from(self) - f64::from(other))
+    }
+}
+
+impl Mul for BF16 {
+    type Output = BF16;
+
+    fn mul(self, other: BF16) -> BF16 {
+        BF16::from(f64::from(self) * f64::from(other))
+    }
+}
+
+impl Div for BF16 {
+    type Output = BF16;
+
+    fn div(self, other: BF16) -> BF16 {
+        BF16::from(f64::from(self) / f64::from(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_f32() {
+        for bits in 0u16..=u16::MAX {
+            let value = BF16::from(bits);
+            let back: u16 = BF16::from(f32::from(value)).into();
+            if value.is_nan() {
+                assert!(BF16::from(back).is_nan());
+            } else {
+                assert_eq!(back, bits, "{:?}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        let bf16 = |v: f64| -> u16 { BF16::from(v).into() };
+        assert_eq!(bf16(1.0), 0x3F80);
+        assert_eq!(bf16(-2.0), 0xC000);
+        // the largest finite value, then ties to even up to the infinity
+        assert_eq!(bf16(f32::MAX as f64), 0x7F80);
+        assert_eq!(bf16(255.0 * 2f64.powi(120)), 0x7F7F);
+        assert_eq!(bf16(255.5 * 2f64.powi(120)), 0x7F80);
+        assert_eq!(bf16(1.0 + 2f64.powi(-8)), 0x3F80);
+        assert_eq!(bf16(1.0 + 3.0 * 2f64.powi(-8)), 0x3F82);
+        // subnormals
+        assert_eq!(bf16(2f64.powi(-133)), 0x0001);
+        assert_eq!(bf16(2f64.powi(-134)), 0x0000);
+        assert_eq!(bf16(1.5 * 2f64.powi(-134)), 0x0001);
+        assert_eq!(bf16(-127.0 * 2f64.powi(-133)), 0x807F);
+        // the largest subnormal rounds up to the smallest normal
+        assert_eq!(bf16(2f64.powi(-126) - 2f64.powi(-135)), 0x0080);
+    }
+
+    /// The positive finite values of a BF16 in increasing order, with their patterns
+    fn table() -> Vec<(f64, u16)> {
+        (0..BF16::EXP_MASK)
+            .map(|bits| (f64::from(BF16::from(bits)), bits))
+            .collect()
+    }
+
+    /// The nearest pattern by searching the table, ties to the even pattern
+    fn reference(table: &[(f64, u16)], value: f64) -> u16 {
+        let sign = if value.is_sign_negative() { 0x8000 } else { 0 };
+        let magnitude = value.abs();
+        // the next value after the largest one as if the exponent range went on
+        let last = table[table.len() - 1].0 + 2f64.powi(127 - 7);
+        let nearest = match table.partition_point(|(v, _)| *v < magnitude) {
+            0 => 0,
+            i if i == table.len() => {
+                let below = table[i - 1].0;
+                if magnitude - below < last - magnitude {
+                    table[i - 1].1
+                } else {
+                    BF16::EXP_MASK
+                }
+            }
+            i => {
+                let ((below, low), (above, high)) = (table[i - 1], table[i]);
+                if magnitude - below < above - magnitude {
+                    low
+                } else if magnitude - below > above - magnitude {
+                    high
+                } else if low % 2 == 0 {
+                    low
+                } else {
+                    high
+                }
+            }
+        };
+        sign | nearest
+    }
+
+    /// The operations against the nearest value of their exact result, for all the first
+    /// operands and a stride of second operands
+    #[test]
+    fn test_operations_against_reference() {
+        let table = table();
+        for a in 0..=u16::MAX {
+            for b in (0..=u16::MAX)
+                .step_by(2003)
+                .chain([0x0001, 0x3F80, 0x7F7F, 0x8080])
+            {
+                let (x, y) = (BF16::from(a), BF16::from(b));
+                if x.is_nan() || y.is_nan() {
+                    assert!((x + y).is_nan() && (x * y).is_nan());
+                    continue;
+                }
+                let (fx, fy) = (f64::from(x), f64::from(y));
+                // the products are exact in f64, rounding the sums to f64 first does not change
+                // their nearest BF16
+                let results = [(x + y, fx + fy), (x - y, fx - fy), (x * y, fx * fy)];
+                for (result, exact) in results {
+                    if exact.is_nan() {
+                        assert!(result.is_nan());
+                    } else if exact.is_infinite() {
+                        assert_eq!(f64::from(result), exact);
+                    } else {
+                        assert_eq!(
+                            u16::from(result),
+                            reference(&table, exact),
+                            "{:?} {:?} exact {}",
+                            x,
+                            y,
+                            exact
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_operations() {
+        let (one, two, three) = (BF16::from(0x3F80), BF16::from(0x4000), BF16::from(0x4040));
+        assert_eq!(u16::from(one + two), 0x4040);
+        assert_eq!(u16::from(one - three), 0xC000);
+        assert_eq!(u16::from(two * three), 0x40C0);
+        assert_eq!(u16::from(three / two), 0x3FC0);
+        assert_eq!(u16::from(one / BF16::from(0)), 0x7F80);
+        assert!((BF16::from(0) / BF16::from(0)).is_nan());
+        // ties to even: 1 + 2^-8 rounds down to 1, (1 + 2^-7) + 2^-8 rounds up to 1 + 2^-6
+        let (next, half_ulp) = (BF16::from(0x3F81), BF16::from(2f64.powi(-8)));
+        assert_eq!(u16::from(one + half_ulp), 0x3F80);
+        assert_eq!(u16::from(next + half_ulp), 0x3F82);
+    }
+}
@@ -74,7 +74,7 @@ pub trait BinaryStringIO {
     where
         Self: Sized,
     {
-        let file = std::fs::read_to_string(path).unwrap();
+        let file = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
         let file = file.replace(" ", "").replace("\n", "");
         Self::from_binary_str(&file)
     }
@@ -1,6 +1,8 @@
 use crate::isa::{
     binary::binary::BinaryIO,
     configuration::{Configuration, Program},
+    fp8::FP8Format,
+    fp16::FloatFormat,
     operation::Operation,
     router::RouterConfig,
 };
@@ -14,6 +16,9 @@ pub enum ConfigField {
     UseFloatBit,
     AluBypassBit,
     AguTrigger,
+    FP8FormatBit,
+    FloatFormatBit,
+    Function,
     Immediate,
     LoopEnd,
     LoopStart,
@@ -38,6 +43,9 @@ impl ConfigField {
             ConfigField::UseFloatBit => (61, 62),       // 1 bit: bit 61
             ConfigField::AluBypassBit => (60, 61),      // 1 bit: bit 60
             ConfigField::AguTrigger => (59, 60),        // 1 bit: bit 59
+            ConfigField::FP8FormatBit => (51, 52),      // 1 bit: bit 51
+            ConfigField::FloatFormatBit => (57, 58),    // 1 bit: bit 57
+            ConfigField::Function => (52, 57),          // 5 bits: bits 52-56
             ConfigField::Immediate => (35, 51),         // 16 bits: bits 35-50
             ConfigField::LoopEnd => (40, 45),           // 5 bits: bits 40-44
             ConfigField::LoopStart => (35, 40),         // 5 bits: bits 35-39
@@ -103,18 +111,49 @@ impl BinaryIO for Configuration {
         let operation: u64 = self.operation.to_u64();
         let mut code = router_config | operation;
         code.set_bool_field(ConfigField::AguTrigger, self.agu_trigger);
+        code.set_bool_field(ConfigField::PredicateBit, self.predicated);
+        code.set_bool_field(ConfigField::UseFloatBit, self.use_float);
+        code.set_bool_field(ConfigField::AluBypassBit, self.alu_bypass);
+        code.set_bool_field(
+            ConfigField::FP8FormatBit,
+            self.fp8_format == FP8Format::E5M2,
+        );
+        code.set_bool_field(
+            ConfigField::FloatFormatBit,
+            self.float_format == FloatFormat::BF16,
+        );
         code.to_le_bytes().to_vec()
     }
 
     fn from_binary(code: &Vec<u8>) -> Result<Self, String> {
         let code = u64::from_binary(code)?;
+        RouterConfig::check_u64(code)?;
+        Operation::check_u64(code)?;
         let router_config = RouterConfig::from_u64(code);
         let operation = Operation::from_u64(code);
         let agu_trigger = code.get_bool_field(ConfigField::AguTrigger);
+        let predicated = code.get_bool_field(ConfigField::PredicateBit);
+        let use_float = code.get_bool_field(ConfigField::UseFloatBit);
+        let alu_bypass = code.get_bool_field(ConfigField::AluBypassBit);
+        let fp8_format = if code.get_bool_field(ConfigField::FP8FormatBit) {
+            FP8Format::E5M2
+        } else {
+            FP8Format::E4M3
+        };
+        let float_format = if code.get_bool_field(ConfigField::FloatFormatBit) {
+            FloatFormat::BF16
+        } else {
+            FloatFormat::FP16
+        };
         Ok(Self {
             router_config,
             operation,
             agu_trigger,
+            predicated,
+            use_float,
+            alu_bypass,
+            fp8_format,
+            float_format,
         })
     }
 }
@@ -130,16 +169,18 @@ impl BinaryIO for Program {
 
     fn from_binary(code: &Vec<u8>) -> Result<Self, String> {
         // make sure the length is a multiple of 8
-        assert_eq!(
-            code.len() % 8,
-            0,
-            "Invalid binary length, not multiple of 8"
-        );
+        if !code.len().is_multiple_of(8) {
+            return Err("Invalid binary length, not multiple of 8".to_string());
+        }
         // convert code into chunks of 8 bytes
         let chunks = code.chunks(8);
         // for each chunk, convert to u64 with little endian encoding
         let configurations = chunks
-            .map(|c| Configuration::from_binary(&c.to_vec()))
+            .enumerate()
+            .map(|(i, c)| {
+                Configuration::from_binary(&c.to_vec())
+                    .map_err(|e| format!("Configuration {}: {}", i, e))
+            })
             .collect::<Result<Vec<Configuration>, String>>()?;
         Ok(Program { configurations })
     }
@@ -152,6 +193,17 @@ impl Configuration {
         let operation: u64 = self.operation.to_u64();
         let mut code = router_config | operation;
         code.set_bool_field(ConfigField::AguTrigger, self.agu_trigger);
+        code.set_bool_field(ConfigField::PredicateBit, self.predicated);
+        code.set_bool_field(ConfigField::UseFloatBit, self.use_float);
+        code.set_bool_field(ConfigField::AluBypassBit, self.alu_bypass);
+        code.set_bool_field(
+            ConfigField::FP8FormatBit,
+            self.fp8_format == FP8Format::E5M2,
+        );
+        code.set_bool_field(
+            ConfigField::FloatFormatBit,
+            self.float_format == FloatFormat::BF16,
+        );
         code
     }
 }
@@ -159,7 +211,10 @@ impl Configuration {
 #[cfg(test)]
 mod tests {
     use crate::isa::binary::binary::BinaryStringIO;
+    use crate::isa::fp8::FP8Format;
+    use crate::isa::fp16::FloatFormat;
     use crate::isa::operation::*;
+    use crate::isa::router::RouterInDir;
     use std::path::Path;
 
     use super::*;
@@ -216,6 +271,124 @@ input_register_write: {};
         assert_eq!(configuration, configuration_from_binary);
     }
 
+    #[test]
+    fn test_predicated_binary_conversions() {
+        let configuration = Configuration::from_mnemonics(
+            r"operation: (p) ADD! 15
+            switch_config: {
+            ALUOut -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            Open -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};",
+        )
+        .unwrap();
+        assert!(configuration.predicated);
+        // the predicated execution is bit 63
+        assert_eq!(configuration.to_u64() >> 63, 1);
+        let binary = configuration.to_binary();
+        let configuration_from_binary = Configuration::from_binary(&binary).unwrap();
+        assert_eq!(configuration, configuration_from_binary);
+        assert_eq!(
+            configuration_from_binary
+                .router_config
+                .switch_config
+                .predicate,
+            RouterInDir::ALUOut
+        );
+    }
+
+    #[test]
+    fn test_float_bypass_binary_conversions() {
+        let configuration = Configuration::from_mnemonics(
+            r"operation: (f) (b) MULT! 15360
+            switch_config: {
+            Open -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            Open -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};",
+        )
+        .unwrap();
+        assert!(configuration.use_float);
+        assert!(configuration.alu_bypass);
+        // UseFloatBit is bit 61, AluBypassBit is bit 60
+        assert_eq!((configuration.to_u64() >> 60) & 0b11, 0b11);
+        let binary = configuration.to_binary();
+        assert_eq!(Configuration::from_binary(&binary).unwrap(), configuration);
+
+        for (bit, use_float, alu_bypass) in [(61, true, false), (60, false, true)] {
+            let code = Configuration::from_mnemonics(
+                "operation: NOP\nswitch_config: { Open -> predicate, };\ninput_register_used: {};\ninput_register_write: {};",
+            )
+            .unwrap()
+            .to_u64()
+                | 1 << bit;
+            let configuration = Configuration::from_binary(&code.to_le_bytes().to_vec()).unwrap();
+            assert_eq!(configuration.use_float, use_float);
+            assert_eq!(configuration.alu_bypass, alu_bypass);
+            assert_eq!(configuration.to_u64(), code);
+        }
+    }
+
+    #[test]
+    fn test_fp8_format_binary_conversions() {
+        let configuration = Configuration::from_mnemonics(
+            r"operation: (e5m2) VMUL!
+            switch_config: {
+            Open -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            ALURes -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};",
+        )
+        .unwrap();
+        assert_eq!(configuration.fp8_format, FP8Format::E5M2);
+        // FP8FormatBit is bit 51
+        assert_eq!((configuration.to_u64() >> 51) & 1, 1);
+        let binary = configuration.to_binary();
+        assert_eq!(Configuration::from_binary(&binary).unwrap(), configuration);
+    }
+
+    #[test]
+    fn test_float_format_binary_conversions() {
+        let configuration = Configuration::from_mnemonics(
+            r"operation: (f) (bf16) ADD! 16256
+            switch_config: {
+            Open -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            ALURes -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};",
+        )
+        .unwrap();
+        assert_eq!(configuration.float_format, FloatFormat::BF16);
+        // FloatFormatBit is bit 57, the Function field of ADD (bits 52-56) is 0
+        assert_eq!((configuration.to_u64() >> 52) & 0b11_1111, 0b10_0000);
+        let binary = configuration.to_binary();
+        assert_eq!(Configuration::from_binary(&binary).unwrap(), configuration);
+    }
+
     #[test]
     fn test_program_binary_conversions() {
         // Converting from binprog to prog, then back to binprog
@@ -1,8 +1,32 @@
 use crate::isa::{
     binary::configuration::{ConfigField, ConfigurationField},
-    operation::{OpCode, Operation},
+    operation::{
+        ADD_OPCODES, FP8_MAC_OPCODES, INT_SIMD_OPCODES, MULT_OPCODES, OpCode, Operation,
+        SUB_OPCODES,
+    },
 };
 
+/// The opcode of MULT and its fixed-point variants
+const MULT_CODE: u8 = 3;
+/// The Function field of MULT.Q is MULQ_FUNCTION | shift
+const MULQ_FUNCTION: u32 = 0b1_0000;
+/// The opcode of all the integer SIMD operations, which one is given by the Function field
+const INT_SIMD_CODE: u8 = 25;
+/// The opcode of VMAC and VDOT, which one is given by the Function field
+const FP8_MAC_CODE: u8 = 28;
+
+/// The operations sharing an opcode, indexed by their Function field
+fn shared_functions(code: u8) -> Option<&'static [OpCode]> {
+    match code {
+        1 => Some(&ADD_OPCODES),
+        2 => Some(&SUB_OPCODES),
+        MULT_CODE => Some(&MULT_OPCODES),
+        INT_SIMD_CODE => Some(&INT_SIMD_OPCODES),
+        FP8_MAC_CODE => Some(&FP8_MAC_OPCODES),
+        _ => None,
+    }
+}
+
 impl Operation {
     /// Convert the operation to a 64-bit binary code, MSB first, LSB last
     pub fn to_u64(&self) -> u64 {
@@ -26,12 +50,30 @@ impl Operation {
             }
             code.set_field(ConfigField::OpCode, self.op_code.to_binary() as u32);
             code.set_field(ConfigField::AluUpdateResBit, self.update_res as u32);
+            if let Some(function) = self.op_code.function() {
+                code.set_field(ConfigField::Function, function);
+            }
         }
         code
     }
 
+    /// Check that the operation fields of a 64-bit configuration can be decoded
+    pub fn check_u64(code: u64) -> Result<(), String> {
+        let op = OpCode::try_from_u64(code)?;
+        if op == OpCode::JUMP && code.get_field(ConfigField::JumpDst) >= 16 {
+            return Err("Jump destination out of bounds".to_string());
+        }
+        if op == OpCode::BR
+            && (code.get_field(ConfigField::MsbBit) == 0
+                || code.get_field(ConfigField::Immediate) >= 16)
+        {
+            return Err("Branch destination must be set within 4 bits".to_string());
+        }
+        Ok(())
+    }
+
     pub fn from_u64(code: u64) -> Self {
-        let op = OpCode::from_binary(code.get_field(ConfigField::OpCode) as u8);
+        let op = OpCode::try_from_u64(code).unwrap_or_else(|e| panic!("{}", e));
 
         if op == OpCode::JUMP {
             let loop_start = code.get_field(ConfigField::LoopStart) as u8;
@@ -67,13 +109,38 @@ impl OpCode {
     fn to_binary(&self) -> u8 {
         match self {
             OpCode::NOP => 0,
-            OpCode::ADD => 1,
-            OpCode::SUB => 2,
-            OpCode::MULT => 3,
+            OpCode::ADD | OpCode::ADDSAT => 1,
+            OpCode::SUB | OpCode::SUBSAT => 2,
+            OpCode::MULT | OpCode::MULQ(_) | OpCode::MAC | OpCode::MACSAT => MULT_CODE,
             OpCode::SEXT => 4,
             OpCode::DIV => 5,
             OpCode::VADD => 6,
             OpCode::VMUL => 7,
+            OpCode::VMAC | OpCode::VDOT => FP8_MAC_CODE,
+            OpCode::VADDB
+            | OpCode::VADDH
+            | OpCode::VADDSB
+            | OpCode::VADDSH
+            | OpCode::VSUBB
+            | OpCode::VSUBH
+            | OpCode::VSUBSB
+            | OpCode::VSUBSH
+            | OpCode::VMULB
+            | OpCode::VMULH
+            | OpCode::VMULSB
+            | OpCode::VMULSH
+            | OpCode::VMINB
+            | OpCode::VMINH
+            | OpCode::VMINSB
+            | OpCode::VMINSH
+            | OpCode::VMAXB
+            | OpCode::VMAXH
+            | OpCode::VMAXSB
+            | OpCode::VMAXSH
+            | OpCode::VSHLB
+            | OpCode::VSHLH
+            | OpCode::VSHLSB
+            | OpCode::VSHLSH => INT_SIMD_CODE,
             OpCode::LS => 8,
             OpCode::RS => 9,
             OpCode::ASR => 10,
@@ -98,8 +165,39 @@ impl OpCode {
         }
     }
 
-    fn from_binary(code: u8) -> Self {
-        match code {
+    /// The Function field of an opcode shared by several operations
+    fn function(&self) -> Option<u32> {
+        if let OpCode::MULQ(shift) = self {
+            assert!(*shift < 16, "MULT.Q shift out of bounds");
+            return Some(MULQ_FUNCTION | *shift as u32);
+        }
+        shared_functions(self.to_binary())?
+            .iter()
+            .position(|op| op == self)
+            .map(|function| function as u32)
+    }
+
+    /// The opcode of a 64-bit configuration, with the Function field for the opcodes
+    /// shared by several operations
+    fn try_from_u64(code: u64) -> Result<Self, String> {
+        let op_code = code.get_field(ConfigField::OpCode) as u8;
+        if let Some(ops) = shared_functions(op_code) {
+            let function = code.get_field(ConfigField::Function);
+            if op_code == MULT_CODE && function & MULQ_FUNCTION != 0 {
+                return Ok(OpCode::MULQ((function & !MULQ_FUNCTION) as u8));
+            }
+            return ops.get(function as usize).copied().ok_or_else(|| {
+                format!(
+                    "Invalid function {} of operation code {}",
+                    function, op_code
+                )
+            });
+        }
+        Self::try_from_binary(op_code).ok_or_else(|| format!("Invalid operation code: {}", op_code))
+    }
+
+    fn try_from_binary(code: u8) -> Option<Self> {
+        let op_code = match code {
             0 => OpCode::NOP,
             1 => OpCode::ADD,
             2 => OpCode::SUB,
@@ -129,13 +227,15 @@ impl OpCode {
             27 => OpCode::STORE,
             26 => OpCode::LOADB,
             29 => OpCode::STOREB,
-            _ => panic!("Invalid operation code: {}", code),
-        }
+            _ => return None,
+        };
+        Some(op_code)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::isa::binary::configuration::{ConfigField, ConfigurationField};
     use crate::isa::operation::*;
 
     #[test]
@@ -171,5 +271,103 @@ mod tests {
         let binary = jump.to_u64();
         let jump_from_binary = Operation::from_u64(binary);
         assert_eq!(jump, jump_from_binary);
+
+        for op_code in [OpCode::SEXT, OpCode::BR, OpCode::MOVC, OpCode::MOVCL] {
+            let operation = Operation {
+                op_code,
+                immediate: Some(8),
+                update_res: op_code != OpCode::BR,
+                loop_start: None,
+                loop_end: None,
+            };
+            let binary = operation.to_u64();
+            assert!(Operation::check_u64(binary).is_ok());
+            assert_eq!(operation, Operation::from_u64(binary));
+        }
+    }
+
+    #[test]
+    fn test_simd_function_binary_conversions() {
+        for (function, op_code) in INT_SIMD_OPCODES.iter().enumerate() {
+            let operation = Operation {
+                op_code: *op_code,
+                immediate: None,
+                update_res: UPDATE_RES,
+                loop_start: None,
+                loop_end: None,
+            };
+            let binary = operation.to_u64();
+            assert_eq!(binary.get_field(ConfigField::OpCode), 25);
+            assert_eq!(binary.get_field(ConfigField::Function), function as u32);
+            assert!(Operation::check_u64(binary).is_ok());
+            assert_eq!(operation, Operation::from_u64(binary));
+        }
+        let mut binary = Operation::from_u64(0).to_u64();
+        binary.set_field(ConfigField::OpCode, 25);
+        binary.set_field(ConfigField::Function, 24);
+        assert!(Operation::check_u64(binary).is_err());
+
+        for (function, op_code) in [OpCode::VMAC, OpCode::VDOT].into_iter().enumerate() {
+            let operation = Operation {
+                op_code,
+                immediate: None,
+                update_res: UPDATE_RES,
+                loop_start: None,
+                loop_end: None,
+            };
+            let binary = operation.to_u64();
+            assert_eq!(binary.get_field(ConfigField::OpCode), 28);
+            assert_eq!(binary.get_field(ConfigField::Function), function as u32);
+            assert_eq!(operation, Operation::from_u64(binary));
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_binary_conversions() {
+        let mut op_codes = vec![
+            (OpCode::ADD, 1, 0),
+            (OpCode::ADDSAT, 1, 1),
+            (OpCode::SUB, 2, 0),
+            (OpCode::SUBSAT, 2, 1),
+            (OpCode::MULT, 3, 0),
+            (OpCode::MAC, 3, 1),
+            (OpCode::MACSAT, 3, 2),
+        ];
+        op_codes.extend((0..16).map(|shift| (OpCode::MULQ(shift), 3, 16 + shift as u32)));
+        for (op_code, code, function) in op_codes {
+            let operation = Operation {
+                op_code,
+                immediate: Some(0x7FFF),
+                update_res: UPDATE_RES,
+                loop_start: None,
+                loop_end: None,
+            };
+            let binary = operation.to_u64();
+            assert_eq!(binary.get_field(ConfigField::OpCode), code);
+            assert_eq!(binary.get_field(ConfigField::Function), function);
+            assert!(Operation::check_u64(binary).is_ok());
+            assert_eq!(operation, Operation::from_u64(binary));
+        }
+        let mut binary = Operation::from_u64(0).to_u64();
+        binary.set_field(ConfigField::OpCode, 1);
+        binary.set_field(ConfigField::Function, 2);
+        assert!(Operation::check_u64(binary).is_err());
+        binary.set_field(ConfigField::OpCode, 3);
+        binary.set_field(ConfigField::Function, 3);
+        assert!(Operation::check_u64(binary).is_err());
+    }
+
+    #[test]
+    fn test_check_branch_destination() {
+        let branch = |immediate| Operation {
+            op_code: OpCode::BR,
+            immediate,
+            update_res: false,
+            loop_start: None,
+            loop_end: None,
+        };
+        assert!(Operation::check_u64(branch(Some(15)).to_u64()).is_ok());
+        assert!(Operation::check_u64(branch(Some(16)).to_u64()).is_err());
+        assert!(Operation::check_u64(branch(None).to_u64()).is_err());
     }
 }
@@ -125,6 +125,29 @@ impl RouterSwitchConfig {
 }
 
 impl RouterConfig {
+    /// Check that the router fields of a 64-bit configuration can be decoded
+    pub fn check_u64(code: u64) -> Result<(), String> {
+        let switch_config = code.get_field(ConfigField::RouterSwitchConfig);
+        for (field, shift) in [
+            ("east_out", 0),
+            ("south_out", 3),
+            ("west_out", 6),
+            ("north_out", 9),
+            ("alu_op1", 12),
+            ("alu_op2", 15),
+            ("predicate", 18),
+        ] {
+            let dir_code = (switch_config >> shift) & 0b111;
+            if dir_code == 6 {
+                return Err(format!(
+                    "Invalid router direction code {} for {}",
+                    dir_code, field
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn from_u64(code: u64) -> Self {
         let switch_config =
             RouterSwitchConfig::from_u32(code.get_field(ConfigField::RouterSwitchConfig) as u32);
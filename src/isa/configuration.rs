@@ -1,10 +1,35 @@
-use crate::isa::{operation::Operation, router::RouterConfig};
+use crate::isa::{
+    fp8::FP8Format, fp16::FloatFormat, operation::Operation, router::RouterConfig, value::LaneView,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub struct Configuration {
     pub operation: Operation,
     pub router_config: RouterConfig,
     pub agu_trigger: bool,
+    /// Predicated execution (bit 63): with reg_predicate false, the result register and the
+    /// stores are not updated
+    pub predicated: bool,
+    /// Floating-point mode (bit 61): the scalar arithmetic and comparisons are on 16-bit
+    /// floating-point operands, in the float format
+    pub use_float: bool,
+    /// ALU bypass (bit 60): op1 is forwarded to the ALU output instead of the operation result
+    pub alu_bypass: bool,
+    /// The format of the FP8 lanes of VADD and VMUL (bit 51): E4M3 if clear, E5M2 if set
+    pub fp8_format: FP8Format,
+    /// The format of the operands in floating-point mode (bit 57): FP16 if clear, BF16 if set
+    pub float_format: FloatFormat,
+}
+
+impl Configuration {
+    /// How the lanes of the values are shown while the configuration runs: the integer lanes of
+    /// an integer SIMD operation, the FP8 lanes in the format of the configuration otherwise
+    pub fn lane_view(&self) -> LaneView {
+        match self.operation.op_code.int_simd() {
+            Some((_, lanes, _)) => LaneView::Int(lanes),
+            None => LaneView::FP8(self.fp8_format),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
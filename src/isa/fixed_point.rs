@@ -0,0 +1,226 @@
+//! Signed 16-bit fixed-point arithmetic of the scalar ALU: the operands are the two's
+//! complement i16 of the low 16 bits, the results are computed exactly then either keep their
+//! low 16 bits (wrapping) or are clamped to the range of an i16 (saturating).
+//! The Q15 value of an i16 v is v × 2^-15, in [-1, 1).
+
+fn signed(value: u16) -> i64 {
+    value as i16 as i64
+}
+
+/// Clamp an exact result to the range of an i16, as a 16-bit pattern
+pub fn saturate(value: i64) -> u16 {
+    value.clamp(i16::MIN as i64, i16::MAX as i64) as i16 as u16
+}
+
+pub fn add_sat(a: u16, b: u16) -> u16 {
+    saturate(signed(a) + signed(b))
+}
+
+pub fn sub_sat(a: u16, b: u16) -> u16 {
+    saturate(signed(a) - signed(b))
+}
+
+/// The product shifted right by `shift` bits, rounded to nearest with the ties up, then
+/// saturated, e.g. the product of two Q15 values as a Q15 with a shift of 15.
+/// -1.0 × -1.0 saturates to the largest Q15 value.
+pub fn mul_q(a: u16, b: u16, shift: u8) -> u16 {
+    let rounding = if shift == 0 { 0 } else { 1 << (shift - 1) };
+    saturate((signed(a) * signed(b) + rounding) >> shift)
+}
+
+/// acc + a × b with a single wrapping or saturation of the exact result
+pub fn mac(acc: u16, a: u16, b: u16, saturating: bool) -> u16 {
+    if saturating {
+        saturate(signed(acc) + signed(a) * signed(b))
+    } else {
+        acc.wrapping_add(a.wrapping_mul(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The boundaries of an i16: 0, ±2^k and their neighbours, the bounds of the range
+    fn boundaries() -> Vec<i16> {
+        let mut values = vec![0, i16::MIN, i16::MIN + 1, i16::MAX - 1, i16::MAX];
+        for k in 0..15 {
+            let power = 1i16 << k;
+            for value in [power - 1, power, power + 1] {
+                values.extend([value, -value]);
+            }
+        }
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    /// The values of an i16 next to the given exact values, where the results start saturating
+    fn around(values: impl IntoIterator<Item = i64>) -> impl Iterator<Item = i16> {
+        values
+            .into_iter()
+            .flat_map(|value| [value - 1, value, value + 1])
+            .filter_map(|value| i16::try_from(value).ok())
+    }
+
+    /// The i8 values scaled to the low and high bytes of an i16, all their pairs are checked
+    fn i8_scaled() -> impl Iterator<Item = i16> {
+        [1, 256]
+            .into_iter()
+            .flat_map(|scale| (i8::MIN..=i8::MAX).map(move |v| v as i16 * scale))
+    }
+
+    const MIN: i64 = i16::MIN as i64;
+    const MAX: i64 = i16::MAX as i64;
+
+    fn check_add_sub_sat(x: i16, y: i16) {
+        let (a, b) = (x as u16, y as u16);
+        assert_eq!(add_sat(a, b) as i16, x.saturating_add(y), "{} + {}", x, y);
+        assert_eq!(sub_sat(a, b) as i16, x.saturating_sub(y), "{} - {}", x, y);
+        assert_eq!(sub_sat(b, a) as i16, y.saturating_sub(x), "{} - {}", y, x);
+    }
+
+    /// ADD.sat and SUB.sat against the operations of Rust on i16, for all the first operands
+    /// against the boundaries and the values where the sum and the differences saturate
+    #[test]
+    fn test_add_sub_sat_all_first_operands() {
+        let boundaries = boundaries();
+        for x in i16::MIN..=i16::MAX {
+            let v = x as i64;
+            let saturating = around([MAX - v, MIN - v, v - MAX, v - MIN, MAX + v, MIN + v]);
+            for y in boundaries.iter().copied().chain(saturating) {
+                check_add_sub_sat(x, y);
+            }
+        }
+        for x in i8_scaled() {
+            for y in i8_scaled() {
+                check_add_sub_sat(x, y);
+            }
+        }
+    }
+
+    /// The nearest integer of the exact quotient, ties up, clamped to the range of an i16
+    fn mul_q_reference(x: i16, y: i16, shift: u8) -> i16 {
+        let exact = x as f64 * y as f64 / 2f64.powi(shift as i32);
+        (exact + 0.5)
+            .floor()
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+    }
+
+    fn check_mul_q(x: i16, y: i16, shift: u8) {
+        assert_eq!(
+            mul_q(x as u16, y as u16, shift) as i16,
+            mul_q_reference(x, y, shift),
+            "{} * {} >> {}",
+            x,
+            y,
+            shift
+        );
+    }
+
+    /// MULT.Q for every shift and all the first operands, against the boundaries and the
+    /// values where the rounded product saturates
+    #[test]
+    fn test_mul_q_all_shifts() {
+        let boundaries = boundaries();
+        for shift in 0..=15u8 {
+            for x in i16::MIN..=i16::MAX {
+                let v = x as i64;
+                // the product saturates from (MAX + 1/2) × 2^shift up, below (MIN - 1/2) × 2^shift
+                let saturating = (v != 0).then(|| {
+                    around([
+                        ((2 * MAX + 1) << shift) / (2 * v),
+                        ((2 * MIN - 1) << shift) / (2 * v),
+                    ])
+                });
+                for y in boundaries
+                    .iter()
+                    .copied()
+                    .chain(saturating.into_iter().flatten())
+                {
+                    check_mul_q(x, y, shift);
+                }
+            }
+            for x in i8_scaled() {
+                for y in i8_scaled() {
+                    check_mul_q(x, y, shift);
+                }
+            }
+        }
+    }
+
+    fn check_mac(acc: i16, x: i16, y: i16) {
+        let (a, b) = (x as u16, y as u16);
+        let wrapped = acc.wrapping_add(x.wrapping_mul(y));
+        assert_eq!(
+            mac(acc as u16, a, b, false) as i16,
+            wrapped,
+            "{} + {} * {}",
+            acc,
+            x,
+            y
+        );
+        let exact = acc as i64 + x as i64 * y as i64;
+        let saturated = exact.clamp(MIN, MAX) as i16;
+        assert_eq!(
+            mac(acc as u16, a, b, true) as i16,
+            saturated,
+            "{} + {} * {}",
+            acc,
+            x,
+            y
+        );
+    }
+
+    /// MAC, wrapping and saturating, for all the accumulators against the boundary products,
+    /// and for all the first operands against the boundaries with the accumulators where the
+    /// exact result saturates
+    #[test]
+    fn test_mac_all_operands() {
+        let products = [i16::MIN, i16::MIN + 1, -256, -1, 0, 1, 255, 256, i16::MAX];
+        for acc in i16::MIN..=i16::MAX {
+            for x in products {
+                for y in products {
+                    check_mac(acc, x, y);
+                }
+            }
+        }
+        let boundaries = boundaries();
+        for x in i16::MIN..=i16::MAX {
+            for y in boundaries.iter().copied() {
+                let product = x as i64 * y as i64;
+                for acc in around([MAX - product, MIN - product]).chain([i16::MIN, 0, i16::MAX]) {
+                    check_mac(acc, x, y);
+                }
+            }
+        }
+        for x in i8_scaled() {
+            for y in i8_scaled() {
+                for acc in [i16::MIN, -1, 0, 1, i16::MAX] {
+                    check_mac(acc, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_q15() {
+        // 0.5 * 0.5 = 0.25, -0.5 * 0.5 = -0.25
+        assert_eq!(mul_q(0x4000, 0x4000, 15), 0x2000);
+        assert_eq!(mul_q(0xC000, 0x4000, 15), 0xE000);
+        // -1.0 * -1.0 saturates, -1.0 * 0.5 is exact
+        assert_eq!(mul_q(0x8000, 0x8000, 15), 0x7FFF);
+        assert_eq!(mul_q(0x8000, 0x4000, 15), 0xC000);
+        // 0.5 * 2^-15 is a tie, rounded up to 2^-15, its opposite to 0
+        assert_eq!(mul_q(0x4000, 0x0001, 15), 0x0001);
+        assert_eq!(mul_q(0xC000, 0x0001, 15), 0x0000);
+        // 0.75 + 0.5 saturates to the largest value, -0.75 - 0.5 to -1.0
+        assert_eq!(add_sat(0x6000, 0x4000), 0x7FFF);
+        assert_eq!(sub_sat(0xA000, 0x4000), 0x8000);
+        // the exact sum is saturated once: -32768 + 256 * 128 = 0 although the product
+        // overflows, 4096 + 32768 saturates and wraps around
+        assert_eq!(mac(0x8000, 0x0100, 0x0080, true), 0x0000);
+        assert_eq!(mac(0x1000, 0x0100, 0x0080, true), 0x7FFF);
+        assert_eq!(mac(0x1000, 0x0100, 0x0080, false), 0x9000);
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+use strum_macros::{Display, EnumString};
+
+use super::bf16::BF16;
+
+/// The 16-bit floating-point formats of the scalar ALU operations in floating-point mode
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Display, EnumString, Serialize, Deserialize,
+)]
+pub enum FloatFormat {
+    /// IEEE 754 half precision, see FP16
+    #[default]
+    FP16,
+    /// bfloat16, the high half of an f32, see BF16
+    BF16,
+}
+
+impl FloatFormat {
+    /// The exact value of the pattern, both formats are subsets of f32
+    pub fn decode(&self, bits: u16) -> f32 {
+        match self {
+            FloatFormat::FP16 => FP16::from(bits).into(),
+            FloatFormat::BF16 => BF16::from(bits).into(),
+        }
+    }
+
+    /// The name of the format and the value of the pattern, e.g. FP16(1.5)
+    pub fn describe(&self, bits: u16) -> String {
+        format!("{}({})", self, self.decode(bits))
+    }
+}
+
+/// IEEE 754 half precision (binary16): 1 sign bit, 5 exponent bits (bias 15), 10 mantissa bits.
+/// Used by the scalar ALU operations when the UseFloatBit of the configuration is set.
+/// The operations are computed in f32 and rounded once to nearest even: f32 is wide enough for
+/// the double rounding of an addition, a subtraction, a multiplication or a division to give
+/// the correctly rounded result (24 ≥ 2 × 11 + 2 bits of significand).
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct FP16(u16);
+
+impl Debug for FP16 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let float_val: f32 = (*self).into();
+        write!(f, "0x{:04x}({})", self.0, float_val)
+    }
+}
+
+impl FP16 {
+    const EXP_MASK: u16 = 0x7C00;
+    const MANT_MASK: u16 = 0x03FF;
+    const SIGN_MASK: u16 = 0x8000;
+    const EXP_BIAS: i32 = 15;
+    const MANT_BITS: u32 = 10;
+    /// The quiet NaN returned by the operations
+    const NAN: u16 = 0x7E00;
+
+    pub fn is_nan(&self) -> bool {
+        self.0 & Self::EXP_MASK == Self::EXP_MASK && self.0 & Self::MANT_MASK != 0
+    }
+}
+
+impl From<u16> for FP16 {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+
+impl From<FP16> for u16 {
+    fn from(value: FP16) -> Self {
+        value.0
+    }
+}
+
+/// Exact conversion, subnormals are value = sign × M × 2^-24
+impl From<FP16> for f32 {
+    fn from(value: FP16) -> Self {
+        let bits = value.0;
+        let sign = if bits & FP16::SIGN_MASK != 0 {
+            -1.0
+        } else {
+            1.0
+        };
+        let exp = ((bits & FP16::EXP_MASK) >> FP16::MANT_BITS) as i32;
+        let man = (bits & FP16::MANT_MASK) as f32;
+        match exp {
+            0 => sign * man * 2f32.powi(-24),
+            31 => {
+                if man == 0.0 {
+                    sign * f32::INFINITY
+                } else {
+                    f32::NAN
+                }
+            }
+            e => sign * (1.0 + man / 1024.0) * 2f32.powi(e - FP16::EXP_BIAS),
+        }
+    }
+}
+
+/// Round to nearest even, the values too large for a half become infinities
+impl From<f32> for FP16 {
+    fn from(value: f32) -> Self {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) as u16) & Self::SIGN_MASK;
+        if value.is_nan() {
+            return FP16(sign | Self::NAN);
+        }
+        let exp = ((bits >> 23) & 0xFF) as i32 - 127;
+        // the significand with its implicit bit, value = man × 2^(exp - 23)
+        let man = (bits & 0x7F_FFFF) | 0x80_0000;
+        if exp > Self::EXP_BIAS {
+            return FP16(sign | Self::EXP_MASK);
+        }
+        // below half of the smallest subnormal (2^-24), rounds to zero
+        if exp < -25 {
+            return FP16(sign);
+        }
+        // the number of bits of the f32 significand dropped by the rounding,
+        // more for a subnormal result
+        let shift = 23 - Self::MANT_BITS + (1 - Self::EXP_BIAS - exp).max(0) as u32;
+        let mut half_bits = man >> shift;
+        let rem = man & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        if rem > half || (rem == half && half_bits & 1 != 0) {
+            // a carry out of the mantissa increments the exponent, up to the infinity
+            half_bits += 1;
+        }
+        if exp >= 1 - Self::EXP_BIAS {
+            // replace the implicit bit by the biased exponent
+            let biased = ((exp + Self::EXP_BIAS) as u32) << Self::MANT_BITS;
+            FP16(sign | (biased + half_bits - (1 << Self::MANT_BITS)) as u16)
+        } else {
+            FP16(sign | half_bits as u16)
+        }
+    }
+}
+
+impl Add for FP16 {
+    type Output = FP16;
+
+    fn add(self, other: FP16) -> FP16 {
+        FP16::from(f32::from(self) + f32::from(other))
+    }
+}
+
+impl Sub for FP16 {
+    type Output = FP16;
+
+    fn sub(self, other: FP16) -> FP16 {
+        FP16::from(f32::from(self) - f32::from(other))
+    }
+}
+
+impl Mul for FP16 {
+    type Output = FP16;
+
+    fn mul(self, other: FP16) -> FP16 {
+        FP16::from(f32::from(self) * f32::from(other))
+    }
+}
+
+impl Div for FP16 {
+    type Output = FP16;
+
+    fn div(self, other: FP16) -> FP16 {
+        FP16::from(f32::from(self) / f32::from(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_f32() {
+        for bits in 0u16..=u16::MAX {
+            let value = FP16::from(bits);
+            let back: u16 = FP16::from(f32::from(value)).into();
+            if value.is_nan() {
+                assert!(FP16::from(back).is_nan());
+            } else {
+                assert_eq!(back, bits, "{:?}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        let half = |v: f32| -> u16 { FP16::from(v).into() };
+        assert_eq!(half(1.0), 0x3C00);
+        assert_eq!(half(-2.0), 0xC000);
+        assert_eq!(half(65504.0), 0x7BFF);
+        // ties to even, then to the infinity
+        assert_eq!(half(1.0 + 2f32.powi(-11)), 0x3C00);
+        assert_eq!(half(1.0 + 3.0 * 2f32.powi(-11)), 0x3C02);
+        assert_eq!(half(65520.0), 0x7C00);
+        assert_eq!(half(65519.0), 0x7BFF);
+        // subnormals
+        assert_eq!(half(2f32.powi(-24)), 0x0001);
+        assert_eq!(half(2f32.powi(-25)), 0x0000);
+        assert_eq!(half(1.5 * 2f32.powi(-25)), 0x0001);
+        assert_eq!(half(-1023.0 * 2f32.powi(-24)), 0x83FF);
+        // the largest subnormal rounds up to the smallest normal
+        assert_eq!(half(2f32.powi(-14) - 2f32.powi(-26)), 0x0400);
+    }
+
+    /// The positive finite values of a half in increasing order, with their patterns
+    fn table() -> Vec<(f64, u16)> {
+        (0..FP16::EXP_MASK)
+            .map(|bits| (f32::from(FP16::from(bits)) as f64, bits))
+            .collect()
+    }
+
+    /// The nearest pattern by searching the table, ties to the even pattern
+    fn reference(table: &[(f64, u16)], value: f64) -> u16 {
+        let sign = if value.is_sign_negative() { 0x8000 } else { 0 };
+        let magnitude = value.abs();
+        // the next value after the largest one as if the exponent range went on
+        let last = table[table.len() - 1].0 + 2f64.powi(15 - 10);
+        let nearest = match table.partition_point(|(v, _)| *v < magnitude) {
+            0 => 0,
+            i if i == table.len() => {
+                let below = table[i - 1].0;
+                if magnitude - below < last - magnitude {
+                    table[i - 1].1
+                } else {
+                    FP16::EXP_MASK
+                }
+            }
+            i => {
+                let ((below, low), (above, high)) = (table[i - 1], table[i]);
+                if magnitude - below < above - magnitude {
+                    low
+                } else if magnitude - below > above - magnitude {
+                    high
+                } else if low % 2 == 0 {
+                    low
+                } else {
+                    high
+                }
+            }
+        };
+        sign | nearest
+    }
+
+    /// The operations against the nearest value of their exact result, for all the first
+    /// operands and a stride of second operands
+    #[test]
+    fn test_operations_against_reference() {
+        let table = table();
+        let (mut subnormals, mut overflows) = (0, 0);
+        for a in 0..=u16::MAX {
+            for b in (0..=u16::MAX)
+                .step_by(2003)
+                .chain([0x0001, 0x03FF, 0x3C00, 0x7BFF, 0x8400])
+            {
+                let (x, y) = (FP16::from(a), FP16::from(b));
+                if x.is_nan() || y.is_nan() {
+                    assert!((x + y).is_nan() && (x * y).is_nan());
+                    continue;
+                }
+                let (fx, fy) = (f32::from(x) as f64, f32::from(y) as f64);
+                // the sums and the products of halves are exact in f64
+                let results = [(x + y, fx + fy), (x - y, fx - fy), (x * y, fx * fy)];
+                for (result, exact) in results {
+                    if exact.is_nan() {
+                        assert!(result.is_nan());
+                    } else if exact.is_infinite() {
+                        assert_eq!(f32::from(result) as f64, exact);
+                    } else {
+                        let expected = reference(&table, exact);
+                        assert_eq!(
+                            u16::from(result),
+                            expected,
+                            "{:?} {:?} exact {}",
+                            x,
+                            y,
+                            exact
+                        );
+                        match expected & FP16::EXP_MASK {
+                            0 if expected & FP16::MANT_MASK != 0 => subnormals += 1,
+                            FP16::EXP_MASK => overflows += 1,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        assert!(subnormals > 0 && overflows > 0);
+    }
+
+    #[test]
+    fn test_operations() {
+        let (one, two, three) = (FP16::from(0x3C00), FP16::from(0x4000), FP16::from(0x4200));
+        assert_eq!(u16::from(one + two), 0x4200);
+        assert_eq!(u16::from(one - three), 0xC000);
+        assert_eq!(u16::from(two * three), 0x4600);
+        assert_eq!(u16::from(three / two), 0x3E00);
+        assert_eq!(u16::from(one / FP16::from(0)), 0x7C00);
+        assert!((FP16::from(0) / FP16::from(0)).is_nan());
+    }
+}
@@ -7,6 +7,8 @@ use nom::{
 
 use crate::isa::{
     configuration::{Configuration, Program},
+    fp8::FP8Format,
+    fp16::FloatFormat,
     mnemonic::operation,
     operation::OpCode,
     router::RouterConfig,
@@ -58,7 +60,7 @@ fn skip_whitespace_and_comments(s: &str) -> IResult<&str, ()> {
 impl Configuration {
     fn parse_configuration(s: &str) -> IResult<&str, Configuration> {
         let (input, _) = skip_whitespace_and_comments(s)?;
-        let (input, (operation, agu_trigger)) = operation::parse_operation_with_trigger(input)?;
+        let (input, (operation, flags)) = operation::parse_operation_with_flags(input)?;
         let (input, _) = skip_whitespace_and_comments(input)?;
         let (input, router_config) = RouterConfig::parse_router_config(input)?;
         let (input, _) = skip_whitespace_and_comments(input)?;
@@ -67,7 +69,12 @@ impl Configuration {
             Configuration {
                 operation,
                 router_config,
-                agu_trigger,
+                agu_trigger: flags.agu_trigger,
+                predicated: flags.predicated,
+                use_float: flags.use_float,
+                alu_bypass: flags.alu_bypass,
+                fp8_format: flags.fp8_format,
+                float_format: flags.float_format,
             },
         ))
     }
@@ -89,10 +96,28 @@ impl Configuration {
     }
 
     /// Convert operation to mnemonics, including the ? marker for agu_trigger
+    /// and the (p), (f), (b), (e5m2) and (bf16) prefixes of the predicated execution, the
+    /// floating-point mode, the ALU bypass, the E5M2 format of the FP8 lanes and the BF16 format
+    /// of the floating-point mode
     fn operation_to_mnemonics_with_trigger(&self) -> String {
         let op = &self.operation;
         let mut result = String::new();
         result.push_str("operation: ");
+        if self.predicated {
+            result.push_str("(p) ");
+        }
+        if self.use_float {
+            result.push_str("(f) ");
+        }
+        if self.alu_bypass {
+            result.push_str("(b) ");
+        }
+        if self.fp8_format == FP8Format::E5M2 {
+            result.push_str("(e5m2) ");
+        }
+        if self.float_format == FloatFormat::BF16 {
+            result.push_str("(bf16) ");
+        }
         
         if op.op_code == OpCode::JUMP {
             result.push_str("JUMP");
@@ -216,6 +241,135 @@ mod tests {
         assert_eq!(configuration.agu_trigger, false);
     }
 
+    #[test]
+    fn test_parse_predicated_configuration() {
+        let input = r"operation: (p) ADD!? 15
+            switch_config: {
+            NorthIn -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            Open -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {north};
+        input_register_write: {north};";
+        let configuration = Configuration::from_mnemonics(input).unwrap();
+        assert!(configuration.predicated);
+        assert!(configuration.agu_trigger);
+        assert_eq!(configuration.operation.op_code, OpCode::ADD);
+        assert_eq!(
+            configuration.router_config.switch_config.predicate,
+            RouterInDir::NorthIn
+        );
+        let mnemonic = configuration.to_mnemonics();
+        assert!(mnemonic.starts_with("operation: (p) ADD!? 15"));
+        assert_eq!(
+            Configuration::from_mnemonics(&mnemonic).unwrap(),
+            configuration
+        );
+
+        // Without the (p) prefix the configuration is always executed
+        let input = input.replace("(p) ", "");
+        let configuration = Configuration::from_mnemonics(&input).unwrap();
+        assert!(!configuration.predicated);
+        assert!(!configuration.to_mnemonics().contains("(p)"));
+    }
+
+    #[test]
+    fn test_parse_float_bypass_configuration() {
+        let input = r"operation: (b) (p) (f) ADD! 15360
+            switch_config: {
+            Open -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            Open -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};";
+        let configuration = Configuration::from_mnemonics(input).unwrap();
+        assert!(configuration.predicated);
+        assert!(configuration.use_float);
+        assert!(configuration.alu_bypass);
+        assert_eq!(configuration.operation.immediate, Some(15360));
+        // the prefixes are printed in a fixed order
+        let mnemonic = configuration.to_mnemonics();
+        assert!(mnemonic.starts_with("operation: (p) (f) (b) ADD! 15360"));
+        assert_eq!(
+            Configuration::from_mnemonics(&mnemonic).unwrap(),
+            configuration
+        );
+
+        let configuration = Configuration::from_mnemonics(&input.replace("(b) (p) ", "")).unwrap();
+        assert!(configuration.use_float);
+        assert!(!configuration.alu_bypass);
+        assert!(!configuration.predicated);
+    }
+
+    #[test]
+    fn test_parse_fp8_format_configuration() {
+        let input = r"operation: (e5m2) VADD!
+            switch_config: {
+            Open -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            ALURes -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};";
+        let configuration = Configuration::from_mnemonics(input).unwrap();
+        assert_eq!(configuration.operation.op_code, OpCode::VADD);
+        assert_eq!(configuration.fp8_format, FP8Format::E5M2);
+        let mnemonic = configuration.to_mnemonics();
+        assert!(mnemonic.starts_with("operation: (e5m2) VADD!"));
+        assert_eq!(
+            Configuration::from_mnemonics(&mnemonic).unwrap(),
+            configuration
+        );
+
+        // E4M3 by default
+        let configuration = Configuration::from_mnemonics(&input.replace("(e5m2) ", "")).unwrap();
+        assert_eq!(configuration.fp8_format, FP8Format::E4M3);
+        assert!(!configuration.to_mnemonics().contains("(e5m2)"));
+    }
+
+    #[test]
+    fn test_parse_float_format_configuration() {
+        let input = r"operation: (bf16) (f) MULT! 16256
+            switch_config: {
+            Open -> predicate,
+            Open -> south_out,
+            Open -> west_out,
+            Open -> north_out,
+            Open -> east_out,
+            ALURes -> alu_op2,
+            ALURes -> alu_op1,
+        };
+        input_register_used: {};
+        input_register_write: {};";
+        let configuration = Configuration::from_mnemonics(input).unwrap();
+        assert!(configuration.use_float);
+        assert_eq!(configuration.float_format, FloatFormat::BF16);
+        let mnemonic = configuration.to_mnemonics();
+        assert!(mnemonic.starts_with("operation: (f) (bf16) MULT! 16256"));
+        assert_eq!(
+            Configuration::from_mnemonics(&mnemonic).unwrap(),
+            configuration
+        );
+
+        // FP16 by default
+        let configuration = Configuration::from_mnemonics(&input.replace("(bf16) ", "")).unwrap();
+        assert_eq!(configuration.float_format, FloatFormat::FP16);
+        assert!(!configuration.to_mnemonics().contains("(bf16)"));
+    }
+
     #[test]
     fn test_parse_configuration() {
         let input = r"operation: ADD! 15
@@ -3,11 +3,17 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{alpha1, digit1, multispace0, space0},
-    combinator::opt,
+    combinator::{map, opt, recognize},
+    multi::many0,
+    sequence::{preceded, terminated},
 };
 use std::str::FromStr;
 
-use crate::isa::operation::{OpCode, Operation};
+use crate::isa::{
+    fp8::FP8Format,
+    fp16::FloatFormat,
+    operation::{OpCode, Operation},
+};
 
 fn parse_nop(input: &str) -> IResult<&str, Operation> {
     let (input, (op, _agu_trigger)) = parse_nop_with_trigger(input)?;
@@ -40,6 +46,26 @@ fn parse_immediate(input: &str) -> IResult<&str, u16> {
     }
 }
 
+/// MULT.Q<shift>, the Q-format multiplication
+fn parse_q_mult(input: &str) -> IResult<&str, OpCode> {
+    let (input, shift) = preceded(tag("MULT.Q"), digit1).parse(input)?;
+    let shift = shift.parse::<u8>().unwrap();
+    // assert it is within u4
+    assert!(shift < 16, "MULT.Q shift must be within 4 bits");
+    Ok((input, OpCode::MULQ(shift)))
+}
+
+/// The opcode with its optional suffix: `ADD.sat`, `SUB.sat`, `MAC.sat` or `MULT.Q<shift>`
+fn parse_op_code(input: &str) -> IResult<&str, OpCode> {
+    alt((
+        parse_q_mult,
+        map(recognize((alpha1, opt(tag(".sat")))), |op_code_str| {
+            OpCode::from_str(op_code_str).unwrap()
+        }),
+    ))
+    .parse(input)
+}
+
 /// ALU operation is in the format of "OPCODE [!][?] [IMM]"
 /// - `!` marks the update_res flag (update ALU result register)
 /// - `?` marks the agu_trigger flag (trigger AGU for memory operations)
@@ -51,8 +77,7 @@ fn parse_alu_operation(input: &str) -> IResult<&str, Operation> {
 
 /// Parse ALU operation and return both Operation and agu_trigger flag
 fn parse_alu_operation_with_trigger(input: &str) -> IResult<&str, (Operation, bool)> {
-    let (input, op_code_str) = alpha1(input)?;
-    let op_code = OpCode::from_str(op_code_str).unwrap();
+    let (input, op_code) = parse_op_code(input)?;
     
     // Parse optional flags: ! (update_res) and ? (agu_trigger) in any order
     let (input, flags) = opt(alt((
@@ -200,10 +225,74 @@ pub fn parse_operation(input: &str) -> IResult<&str, Operation> {
 }
 
 /// Parse operation and return both Operation and agu_trigger flag
-/// This is used by Configuration parsing to get the agu_trigger bit
 pub fn parse_operation_with_trigger(input: &str) -> IResult<&str, (Operation, bool)> {
     let (input, _) = tag("operation:")(input)?;
     let (input, _) = multispace0(input)?;
+    parse_operation_body(input)
+}
+
+/// The configuration bits marked in the operation mnemonic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationFlags {
+    /// `?` after the opcode (bit 59)
+    pub agu_trigger: bool,
+    /// `(p)` prefix (bit 63)
+    pub predicated: bool,
+    /// `(f)` prefix (bit 61)
+    pub use_float: bool,
+    /// `(b)` prefix (bit 60)
+    pub alu_bypass: bool,
+    /// `(e5m2)` prefix (bit 51), E4M3 without it
+    pub fp8_format: FP8Format,
+    /// `(bf16)` prefix (bit 57), FP16 without it
+    pub float_format: FloatFormat,
+}
+
+/// Parse operation with the flags of the configuration
+/// Format: "operation: [(p)] [(f)] [(b)] [(e5m2)] [(bf16)] OPERATION", the prefixes in any order:
+/// - `(p)` marks the predicated execution
+/// - `(f)` marks the floating-point mode
+/// - `(b)` marks the ALU bypass
+/// - `(e5m2)` marks the E5M2 format of the FP8 lanes
+/// - `(bf16)` marks the BF16 format of the floating-point mode
+///
+/// This is used by Configuration parsing to get the agu_trigger, predicate, float, bypass, FP8
+/// format and float format bits
+pub fn parse_operation_with_flags(input: &str) -> IResult<&str, (Operation, OperationFlags)> {
+    let (input, _) = tag("operation:")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, prefixes) = many0(terminated(
+        alt((
+            tag("(p)"),
+            tag("(f)"),
+            tag("(b)"),
+            tag("(e5m2)"),
+            tag("(bf16)"),
+        )),
+        space0,
+    ))
+    .parse(input)?;
+    let (input, (operation, agu_trigger)) = parse_operation_body(input)?;
+    let flags = OperationFlags {
+        agu_trigger,
+        predicated: prefixes.contains(&"(p)"),
+        use_float: prefixes.contains(&"(f)"),
+        alu_bypass: prefixes.contains(&"(b)"),
+        fp8_format: if prefixes.contains(&"(e5m2)") {
+            FP8Format::E5M2
+        } else {
+            FP8Format::E4M3
+        },
+        float_format: if prefixes.contains(&"(bf16)") {
+            FloatFormat::BF16
+        } else {
+            FloatFormat::FP16
+        },
+    };
+    Ok((input, (operation, flags)))
+}
+
+fn parse_operation_body(input: &str) -> IResult<&str, (Operation, bool)> {
     alt((
         parse_nop_with_trigger,
         parse_jump_with_trigger,
@@ -246,6 +335,10 @@ impl Operation {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::isa::{
+        operation::INT_SIMD_OPCODES,
+        value::{IntLanes, IntSIMDOp},
+    };
 
     #[test]
     fn test_parse_operation() {
@@ -364,4 +457,86 @@ mod tests {
         assert_eq!(operation.loop_end, Some(5));
         assert_eq!(agu_trigger, true);
     }
+
+    #[test]
+    fn test_parse_sext_br_movc() {
+        // SEXT! 8 - sign extension of the lower 8 bits
+        let input = "operation: SEXT! 8";
+        let (_, operation) = parse_operation(input).unwrap();
+        assert_eq!(operation.op_code, OpCode::SEXT);
+        assert_eq!(operation.immediate, Some(8));
+        assert_eq!(operation.update_res, true);
+        assert!(operation.is_arith_logic());
+
+        // BR 3 - branch to 3, a control operation
+        let input = "operation: BR 3";
+        let (_, operation) = parse_operation(input).unwrap();
+        assert_eq!(operation.op_code, OpCode::BR);
+        assert_eq!(operation.immediate, Some(3));
+        assert!(operation.is_control());
+
+        // MOVC and MOVCL are not mistaken for each other
+        let input = "operation: MOVC! 4660";
+        let (_, operation) = parse_operation(input).unwrap();
+        assert_eq!(operation.op_code, OpCode::MOVC);
+        assert_eq!(operation.immediate, Some(4660));
+        let input = "operation: MOVCL! 22136";
+        let (_, operation) = parse_operation(input).unwrap();
+        assert_eq!(operation.op_code, OpCode::MOVCL);
+        assert_eq!(operation.immediate, Some(22136));
+
+        for input in [
+            "operation: SEXT! 8",
+            "operation: BR 3",
+            "operation: MOVC! 4660",
+            "operation: MOVCL! 22136",
+        ] {
+            let operation = Operation::from_mnemonics(input).unwrap();
+            let mnemonic = operation.to_mnemonics();
+            assert_eq!(Operation::from_mnemonics(&mnemonic).unwrap(), operation);
+        }
+    }
+
+    #[test]
+    fn test_parse_int_simd() {
+        for op_code in INT_SIMD_OPCODES {
+            let input = format!("operation: {}! 3", op_code);
+            let (_, operation) = parse_operation(&input).unwrap();
+            assert_eq!(operation.op_code, op_code);
+            assert_eq!(operation.immediate, Some(3));
+            assert!(operation.is_simd());
+            assert_eq!(
+                Operation::from_mnemonics(&operation.to_mnemonics()).unwrap(),
+                operation
+            );
+        }
+        let (_, operation) = parse_operation("operation: VMAXSH!").unwrap();
+        assert_eq!(
+            operation.op_code.int_simd(),
+            Some((IntSIMDOp::Max, IntLanes::I16, true))
+        );
+    }
+
+    #[test]
+    fn test_parse_fixed_point() {
+        for (input, op_code) in [
+            ("operation: ADD.sat! 3", OpCode::ADDSAT),
+            ("operation: SUB.sat! 3", OpCode::SUBSAT),
+            ("operation: MULT.Q15! 3", OpCode::MULQ(15)),
+            ("operation: MULT.Q0! 3", OpCode::MULQ(0)),
+            ("operation: MAC! 3", OpCode::MAC),
+            ("operation: MAC.sat! 3", OpCode::MACSAT),
+        ] {
+            let (_, operation) = parse_operation(input).unwrap();
+            assert_eq!(operation.op_code, op_code);
+            assert_eq!(operation.immediate, Some(3));
+            assert!(operation.is_arith_logic());
+            assert_eq!(operation.to_mnemonics(), input);
+        }
+        // the plain opcodes are not mistaken for their variants
+        let (_, operation) = parse_operation("operation: MULT! 3").unwrap();
+        assert_eq!(operation.op_code, OpCode::MULT);
+        let (_, operation) = parse_operation("operation: ADD 3").unwrap();
+        assert_eq!(operation.op_code, OpCode::ADD);
+    }
 }
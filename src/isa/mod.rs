@@ -1,5 +1,8 @@
+pub mod bf16;
 pub mod binary;
 pub mod configuration;
+pub mod fixed_point;
+pub mod fp16;
 pub mod fp8;
 pub mod mnemonic;
 pub mod operation;
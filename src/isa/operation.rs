@@ -1,6 +1,14 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Div, Mul, Sub};
 use strum_macros::{Display, EnumString};
 
-use super::{pe::PE, value::SIMDValue};
+use super::{
+    bf16::BF16,
+    fixed_point,
+    fp16::{FP16, FloatFormat},
+    pe::PE,
+    value::{IntLanes, IntSIMDOp, SIMDValue},
+};
 
 type Immediate = Option<u16>;
 type UpdateRes = bool;
@@ -9,7 +17,7 @@ pub const NO_UPDATE_RES: bool = false;
 pub const NO_IMMEDIATE: Immediate = None;
 
 // If ALU operation, immediate is the immediate value, but if jump operation, immediate is the jump destination (bit 45-50)
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Serialize, Deserialize)]
 pub struct Operation {
     pub op_code: OpCode,
     pub immediate: Immediate,
@@ -63,16 +71,53 @@ pub enum OperationType {
     NOP,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumString, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumString, Display, Copy, Serialize, Deserialize)]
 pub enum OpCode {
     NOP,
     ADD,
     SUB,
     MULT,
-    SEXT,
+    // fixed-point variants of ADD, SUB and MULT on the i16 of the low 16 bits, sharing their opcodes
+    #[strum(serialize = "ADD.sat")]
+    ADDSAT, // addition saturated to the range of an i16
+    #[strum(serialize = "SUB.sat")]
+    SUBSAT, // subtraction saturated to the range of an i16
+    #[strum(to_string = "MULT.Q{0}")]
+    MULQ(u8), // product shifted right by the u8 (0-15), rounded (ties up) and saturated
+    MAC, // low 16 bits of reg_res + op1 * op2, wrapping
+    #[strum(serialize = "MAC.sat")]
+    MACSAT, // low 16 bits of reg_res + op1 * op2, saturated once
+    SEXT, // sign extension of the op2 lower bits of op1
     DIV,
     VADD,
     VMUL,
+    VMAC, // lane-wise reg_res + op1 * op2, rounded once
+    VDOT, // sum of the 8 products of the lanes into the f32 in the low 32 bits of reg_res
+    // integer SIMD, B for 8 lanes of i8, H for 4 lanes of i16, S for the saturating variant
+    VADDB,
+    VADDH,
+    VADDSB,
+    VADDSH,
+    VSUBB,
+    VSUBH,
+    VSUBSB,
+    VSUBSH,
+    VMULB,
+    VMULH,
+    VMULSB,
+    VMULSH,
+    VMINB,
+    VMINH,
+    VMINSB,
+    VMINSH,
+    VMAXB,
+    VMAXH,
+    VMAXSB,
+    VMAXSH,
+    VSHLB,
+    VSHLH,
+    VSHLSB,
+    VSHLSH,
     LS,
     RS,
     ASR,
@@ -83,11 +128,11 @@ pub enum OpCode {
     CMERGE, // if msb, set to immediate, otherwise set to op1
     CMP, // compare equal, one bit result
     CLT, // signed LEQ comparison
-    BR,
+    BR,  // branch to the immediate if reg_predicate
     CGT, // signed GEQ comparison
-    MOVCL,
+    MOVCL, // constant into the high half (bits 31-16), keep the low half of op1
     JUMP,
-    MOVC,
+    MOVC, // constant into the low half (bits 15-0), keep the high half of op1
     LOADD,
     STORED,
     LOAD,
@@ -96,12 +141,59 @@ pub enum OpCode {
     STOREB,
 }
 
+/// The integer SIMD opcodes, indexed by their Function field (bits 52-56):
+/// the operation (bits 54-56), the saturation (bit 53) and the lanes (bit 52, set for 4x i16)
+pub const INT_SIMD_OPCODES: [OpCode; 24] = [
+    OpCode::VADDB,
+    OpCode::VADDH,
+    OpCode::VADDSB,
+    OpCode::VADDSH,
+    OpCode::VSUBB,
+    OpCode::VSUBH,
+    OpCode::VSUBSB,
+    OpCode::VSUBSH,
+    OpCode::VMULB,
+    OpCode::VMULH,
+    OpCode::VMULSB,
+    OpCode::VMULSH,
+    OpCode::VMINB,
+    OpCode::VMINH,
+    OpCode::VMINSB,
+    OpCode::VMINSH,
+    OpCode::VMAXB,
+    OpCode::VMAXH,
+    OpCode::VMAXSB,
+    OpCode::VMAXSH,
+    OpCode::VSHLB,
+    OpCode::VSHLH,
+    OpCode::VSHLSB,
+    OpCode::VSHLSH,
+];
+
+/// The operations sharing the opcode of ADD, indexed by their Function field (bits 52-56)
+pub const ADD_OPCODES: [OpCode; 2] = [OpCode::ADD, OpCode::ADDSAT];
+
+/// The operations sharing the opcode of SUB, indexed by their Function field
+pub const SUB_OPCODES: [OpCode; 2] = [OpCode::SUB, OpCode::SUBSAT];
+
+/// The operations sharing the opcode of MULT, indexed by their Function field.
+/// MULT.Q also shares it, with the Function field 16 + shift
+pub const MULT_OPCODES: [OpCode; 3] = [OpCode::MULT, OpCode::MAC, OpCode::MACSAT];
+
+/// The FP8 multiply-accumulate opcodes, indexed by their Function field
+pub const FP8_MAC_OPCODES: [OpCode; 2] = [OpCode::VMAC, OpCode::VDOT];
+
 impl OpCode {
     pub fn get_type(&self) -> OperationType {
         match self {
             OpCode::ADD
             | OpCode::SUB
             | OpCode::MULT
+            | OpCode::ADDSAT
+            | OpCode::SUBSAT
+            | OpCode::MULQ(_)
+            | OpCode::MAC
+            | OpCode::MACSAT
             | OpCode::DIV
             | OpCode::LS
             | OpCode::RS
@@ -113,19 +205,93 @@ impl OpCode {
             | OpCode::CMERGE
             | OpCode::CMP
             | OpCode::CLT
-            | OpCode::CGT => OperationType::ArithLogic,
+            | OpCode::CGT
+            | OpCode::SEXT
+            | OpCode::MOVC
+            | OpCode::MOVCL => OperationType::ArithLogic,
             OpCode::NOP => OperationType::NOP,
-            OpCode::VADD | OpCode::VMUL => OperationType::SIMD,
+            OpCode::VADD
+            | OpCode::VMUL
+            | OpCode::VMAC
+            | OpCode::VDOT
+            | OpCode::VADDB
+            | OpCode::VADDH
+            | OpCode::VADDSB
+            | OpCode::VADDSH
+            | OpCode::VSUBB
+            | OpCode::VSUBH
+            | OpCode::VSUBSB
+            | OpCode::VSUBSH
+            | OpCode::VMULB
+            | OpCode::VMULH
+            | OpCode::VMULSB
+            | OpCode::VMULSH
+            | OpCode::VMINB
+            | OpCode::VMINH
+            | OpCode::VMINSB
+            | OpCode::VMINSH
+            | OpCode::VMAXB
+            | OpCode::VMAXH
+            | OpCode::VMAXSB
+            | OpCode::VMAXSH
+            | OpCode::VSHLB
+            | OpCode::VSHLH
+            | OpCode::VSHLSB
+            | OpCode::VSHLSH => OperationType::SIMD,
             OpCode::LOADD
             | OpCode::STORED
             | OpCode::LOAD
             | OpCode::STORE
             | OpCode::LOADB
             | OpCode::STOREB => OperationType::Memory,
-            OpCode::BR | OpCode::JUMP | OpCode::MOVC | OpCode::MOVCL => OperationType::Control,
-            _ => todo!("Operation not implemented: {:?}", self),
+            OpCode::BR | OpCode::JUMP => OperationType::Control,
         }
     }
+
+    /// The lane-wise operation, the lanes and the saturation of an integer SIMD opcode
+    pub fn int_simd(&self) -> Option<(IntSIMDOp, IntLanes, bool)> {
+        let index = INT_SIMD_OPCODES.iter().position(|op| op == self)?;
+        let operation = [
+            IntSIMDOp::Add,
+            IntSIMDOp::Sub,
+            IntSIMDOp::Mul,
+            IntSIMDOp::Min,
+            IntSIMDOp::Max,
+            IntSIMDOp::Shl,
+        ][index >> 2];
+        let lanes = if index & 1 == 1 {
+            IntLanes::I16
+        } else {
+            IntLanes::I8
+        };
+        Some((operation, lanes, index & 2 == 2))
+    }
+}
+
+/// The result of a scalar operation on floating-point operands, None for the operations with
+/// integer semantics. The comparisons are exact in f32 for both formats
+fn float_result<T>(op_code: OpCode, op1: T, op2: T) -> Option<u16>
+where
+    T: Copy
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Into<u16>
+        + Into<f32>,
+{
+    let (x, y): (f32, f32) = (op1.into(), op2.into());
+    let result = match op_code {
+        OpCode::ADD => (op1 + op2).into(),
+        OpCode::SUB => (op1 - op2).into(),
+        OpCode::MULT => (op1 * op2).into(),
+        OpCode::DIV => (op1 / op2).into(),
+        OpCode::CMP => (x == y) as u16,
+        OpCode::CLT => (x <= y) as u16,
+        OpCode::CGT => (x >= y) as u16,
+        _ => return None,
+    };
+    Some(result)
 }
 
 impl PE {
@@ -138,14 +304,25 @@ impl PE {
         }
     }
 
+    /// Get the SIMD operands in the FP8 format of the current configuration
     fn get_simd_operands(&self, op: &Operation) -> (SIMDValue, SIMDValue) {
         assert!(op.is_simd(), "Operation is not a SIMD operation");
+        let format = self.current_conf().fp8_format;
         (
-            SIMDValue::from(self.regs.reg_op1),
-            SIMDValue::from(self.regs.reg_op2),
+            SIMDValue::with_format(self.regs.reg_op1, format),
+            SIMDValue::with_format(self.regs.reg_op2, format),
         )
     }
 
+    /// Get the integer SIMD operands, the immediate is broadcast to all the lanes
+    fn get_int_simd_operands(&self, op: &Operation, lanes: IntLanes) -> (u64, u64) {
+        if let Some(immediate) = op.immediate {
+            (self.regs.reg_op1, lanes.broadcast(immediate))
+        } else {
+            (self.regs.reg_op1, self.regs.reg_op2)
+        }
+    }
+
     /// Execute the simple ALU operation and update the alu_out signal
     pub fn execute_alu_simd(&mut self, op: &Operation) {
         assert!(
@@ -153,6 +330,11 @@ impl PE {
             "Operation {:?} is not a valid ALU or SIMD operation",
             op.op_code
         );
+        if let Some((int_op, lanes, saturating)) = op.op_code.int_simd() {
+            let (op1, op2) = self.get_int_simd_operands(op, lanes);
+            self.signals.wire_alu_out = Some(int_op.apply(lanes, saturating, op1, op2));
+            return;
+        }
         match op.op_code {
             OpCode::ADD => {
                 let (op1, op2) = self.get_scalar_operands(op);
@@ -169,6 +351,37 @@ impl PE {
                 // wrapping_mul ignores overflows
                 self.signals.wire_alu_out = Some((op1.wrapping_mul(op2)) as u64);
             }
+            OpCode::ADDSAT => {
+                let (op1, op2) = self.get_scalar_operands(op);
+                self.signals.wire_alu_out = Some(fixed_point::add_sat(op1, op2) as u64);
+            }
+            OpCode::SUBSAT => {
+                let (op1, op2) = self.get_scalar_operands(op);
+                self.signals.wire_alu_out = Some(fixed_point::sub_sat(op1, op2) as u64);
+            }
+            OpCode::MULQ(shift) => {
+                let (op1, op2) = self.get_scalar_operands(op);
+                self.signals.wire_alu_out = Some(fixed_point::mul_q(op1, op2, shift) as u64);
+            }
+            OpCode::MAC | OpCode::MACSAT => {
+                let (op1, op2) = self.get_scalar_operands(op);
+                // the accumulator is the low 16 bits of reg_res
+                let acc = self.regs.reg_res as u16;
+                let saturating = op.op_code == OpCode::MACSAT;
+                self.signals.wire_alu_out =
+                    Some(fixed_point::mac(acc, op1, op2, saturating) as u64);
+            }
+            OpCode::SEXT => {
+                let (op1, width) = self.get_scalar_operands(op);
+                // op1 is already 16 bits wide with a width of 0 or at least 16
+                let result = if width == 0 || width >= 16 {
+                    op1
+                } else {
+                    let shift = 16 - width as u32;
+                    (((op1 << shift) as i16) >> shift) as u16
+                };
+                self.signals.wire_alu_out = Some(result as u64);
+            }
             OpCode::DIV => {
                 let (op1, op2) = self.get_scalar_operands(op);
                 // wrapping_div ignores overflows
@@ -184,6 +397,18 @@ impl PE {
                 let result: SIMDValue = op1 * op2;
                 self.signals.wire_alu_out = Some(result.into());
             }
+            OpCode::VMAC => {
+                let (op1, op2) = self.get_simd_operands(op);
+                // the accumulator is reg_res, in the format of the operands
+                let acc = SIMDValue::with_format(self.regs.reg_res, op1.1);
+                self.signals.wire_alu_out = Some(op1.vmac(&op2, &acc).into());
+            }
+            OpCode::VDOT => {
+                let (op1, op2) = self.get_simd_operands(op);
+                // the accumulator is the f32 in the low 32 bits of reg_res
+                let acc = f32::from_bits(self.regs.reg_res as u32);
+                self.signals.wire_alu_out = Some(op1.vdot(&op2, acc).to_bits() as u64);
+            }
             OpCode::LS => {
                 let (op1, op2) = self.get_scalar_operands(op);
                 self.signals.wire_alu_out = Some((op1 << op2) as u64);
@@ -247,6 +472,18 @@ impl PE {
                 self.signals.wire_alu_out = Some((op1 >= op2) as u64);
             }
 
+            OpCode::MOVC => {
+                let (_, constant) = self.get_scalar_operands(op);
+                let high = self.regs.reg_op1 & 0xFFFF_0000;
+                self.signals.wire_alu_out = Some(high | (constant as u64));
+            }
+
+            OpCode::MOVCL => {
+                let (_, constant) = self.get_scalar_operands(op);
+                let low = self.regs.reg_op1 & 0xFFFF;
+                self.signals.wire_alu_out = Some(((constant as u64) << 16) | low);
+            }
+
             OpCode::NOP => {
                 self.signals.wire_alu_out = Some(0);
             }
@@ -257,6 +494,28 @@ impl PE {
         }
     }
 
+    /// Execute the scalar ALU operation in floating-point mode and update the alu_out signal.
+    /// The operands are FP16 or BF16 in the float format of the current configuration, the
+    /// immediate is the bit pattern of a value in that format.
+    /// ADD, SUB, MULT and DIV are rounded to nearest even, the comparisons are false with a NaN,
+    /// the other operations keep their integer semantics.
+    pub fn execute_alu_float(&mut self, op: &Operation) {
+        assert!(
+            op.is_arith_logic(),
+            "Operation {:?} is not a valid scalar ALU operation",
+            op.op_code
+        );
+        let (op1, op2) = self.get_scalar_operands(op);
+        let result = match self.current_conf().float_format {
+            FloatFormat::FP16 => float_result(op.op_code, FP16::from(op1), FP16::from(op2)),
+            FloatFormat::BF16 => float_result(op.op_code, BF16::from(op1), BF16::from(op2)),
+        };
+        match result {
+            Some(result) => self.signals.wire_alu_out = Some(result as u64),
+            None => self.execute_alu_simd(op),
+        }
+    }
+
     // pub fn execute_jump(&mut self, op: &Operation) {
     //     assert!(
     //         op.is_control(),
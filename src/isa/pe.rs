@@ -1,15 +1,18 @@
 use crate::{
     agu::instruction::{DataWidth, InstType, Instruction},
     isa::{
+        fp16::FloatFormat,
         operation::{OpCode, Operation},
-        value::SIMDValue,
+        value::LaneView,
     },
-    sim::dmem::{DMemInterface, DMemMode},
+    sim::dmem::{DEFAULT_MEM_LATENCY, DMemInterface, DMemMode},
 };
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use strum_macros::{Display, EnumIter, EnumString};
 
 use super::configuration::{Configuration, Program};
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PERegisters {
     pub reg_op1: u64,
     pub reg_op2: u64,
@@ -42,23 +45,31 @@ impl Default for PERegisters {
 
 impl Debug for PERegisters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "reg_op1: 0x{:04x}|{:?}\nreg_op2: 0x{:04x}|{:?}\nreg_res: 0x{:04x}|{:?}\nreg_north_in: 0x{:04x}|{:?}\nreg_south_in: 0x{:04x}|{:?}\nreg_west_in: 0x{:04x}|{:?}\nreg_east_in: 0x{:04x}|{:?}\nreg_predicate: {}\nreg_loop_start: {}\nreg_loop_end: {}",
-            self.reg_op1 as u16,
-            SIMDValue::from(self.reg_op1),
-            self.reg_op2 as u16,
-            SIMDValue::from(self.reg_op2),
-            self.reg_res as u16,
-            SIMDValue::from(self.reg_res),
-            self.reg_north_in as u16,
-            SIMDValue::from(self.reg_north_in),
-            self.reg_south_in as u16,
-            SIMDValue::from(self.reg_south_in),
-            self.reg_west_in as u16,
-            SIMDValue::from(self.reg_west_in),
-            self.reg_east_in as u16,
-            SIMDValue::from(self.reg_east_in),
+        f.write_str(&self.describe(LaneView::default(), FloatFormat::default()))
+    }
+}
+
+impl PERegisters {
+    /// The registers as hexadecimal, as lanes in the given view and their low 16 bits as a
+    /// value in the given float format
+    pub fn describe(&self, view: LaneView, float_format: FloatFormat) -> String {
+        let format_value = |v: u64| {
+            format!(
+                "0x{:04x}|{}|{}",
+                v as u16,
+                view.describe(v),
+                float_format.describe(v as u16)
+            )
+        };
+        format!(
+            "reg_op1: {}\nreg_op2: {}\nreg_res: {}\nreg_north_in: {}\nreg_south_in: {}\nreg_west_in: {}\nreg_east_in: {}\nreg_predicate: {}\nreg_loop_start: {}\nreg_loop_end: {}",
+            format_value(self.reg_op1),
+            format_value(self.reg_op2),
+            format_value(self.reg_res),
+            format_value(self.reg_north_in),
+            format_value(self.reg_south_in),
+            format_value(self.reg_west_in),
+            format_value(self.reg_east_in),
             self.reg_predicate,
             self.reg_loop_start,
             self.reg_loop_end
@@ -66,6 +77,67 @@ impl Debug for PERegisters {
     }
 }
 
+/// Name of one field of PERegisters, e.g. to watch it in the simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum PERegister {
+    RegOp1,
+    RegOp2,
+    RegRes,
+    RegNorthIn,
+    RegSouthIn,
+    RegWestIn,
+    RegEastIn,
+    RegPredicate,
+    RegLoopStart,
+    RegLoopEnd,
+}
+
+impl PERegister {
+    /// The width of the register in bits
+    pub fn width(&self) -> usize {
+        match self {
+            PERegister::RegPredicate => 1,
+            PERegister::RegLoopStart | PERegister::RegLoopEnd => 8,
+            _ => 64,
+        }
+    }
+}
+
+impl PERegisters {
+    /// Read a register, the predicate is 0 or 1
+    pub fn get(&self, reg: PERegister) -> u64 {
+        match reg {
+            PERegister::RegOp1 => self.reg_op1,
+            PERegister::RegOp2 => self.reg_op2,
+            PERegister::RegRes => self.reg_res,
+            PERegister::RegNorthIn => self.reg_north_in,
+            PERegister::RegSouthIn => self.reg_south_in,
+            PERegister::RegWestIn => self.reg_west_in,
+            PERegister::RegEastIn => self.reg_east_in,
+            PERegister::RegPredicate => self.reg_predicate as u64,
+            PERegister::RegLoopStart => self.reg_loop_start as u64,
+            PERegister::RegLoopEnd => self.reg_loop_end as u64,
+        }
+    }
+
+    /// Write a register, the value is truncated to the width of the register
+    pub fn set(&mut self, reg: PERegister, value: u64) {
+        match reg {
+            PERegister::RegOp1 => self.reg_op1 = value,
+            PERegister::RegOp2 => self.reg_op2 = value,
+            PERegister::RegRes => self.reg_res = value,
+            PERegister::RegNorthIn => self.reg_north_in = value,
+            PERegister::RegSouthIn => self.reg_south_in = value,
+            PERegister::RegWestIn => self.reg_west_in = value,
+            PERegister::RegEastIn => self.reg_east_in = value,
+            PERegister::RegPredicate => self.reg_predicate = value & 1 == 1,
+            PERegister::RegLoopStart => self.reg_loop_start = value as u8,
+            PERegister::RegLoopEnd => self.reg_loop_end = value as u8,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Default)]
 pub struct PESignals {
     pub wire_alu_out: Option<u64>,
@@ -79,14 +151,37 @@ pub struct PESignals {
     pub wire_east_out: Option<u64>,
 }
 
+impl PESignals {
+    /// All the wires with their names, None if the wire is not driven in the cycle
+    pub fn wires(&self) -> [(&'static str, Option<u64>); 9] {
+        [
+            ("wire_alu_out", self.wire_alu_out),
+            ("wire_north_in", self.wire_north_in),
+            ("wire_south_in", self.wire_south_in),
+            ("wire_west_in", self.wire_west_in),
+            ("wire_east_in", self.wire_east_in),
+            ("wire_north_out", self.wire_north_out),
+            ("wire_south_out", self.wire_south_out),
+            ("wire_west_out", self.wire_west_out),
+            ("wire_east_out", self.wire_east_out),
+        ]
+    }
+}
+
 impl Debug for PESignals {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fn format_value(v: Option<u64>) -> String {
-            v.map(|v| format!("0x{:04x}|{:?}", v as u16, SIMDValue::from(v)))
+        f.write_str(&self.describe(LaneView::default()))
+    }
+}
+
+impl PESignals {
+    /// The wires as hexadecimal and as lanes in the given view
+    pub fn describe(&self, view: LaneView) -> String {
+        let format_value = |v: Option<u64>| {
+            v.map(|v| format!("0x{:04x}|{}", v as u16, view.describe(v)))
                 .unwrap_or_else(|| "None".to_string())
-        }
-        write!(
-            f,
+        };
+        format!(
             "wire_alu_out: {}\nwire_north_in: {}\nwire_south_in: {}\nwire_west_in: {}\nwire_east_in: {}\nwire_north_out: {}\nwire_south_out: {}\nwire_west_out: {}\nwire_east_out: {}",
             format_value(self.wire_alu_out),
             format_value(self.wire_north_in),
@@ -109,10 +204,9 @@ pub struct PE {
     pub configurations: Vec<Configuration>,
     /// Whether this PE is connected to memory (edge PE)
     pub is_mem_pe_flag: bool,
-    /// AGU CM executed in the previous cycle (1 cycle ago). None if AGU was not triggered.
-    pub agu_cm_s: Option<Instruction>,
-    /// AGU CM executed 2 cycles ago. None if AGU was not triggered 2 cycles ago.
-    pub agu_cm_ss: Option<Instruction>,
+    /// AGU CMs executed in the previous cycles, one per cycle of memory latency:
+    /// agu_cm_pipeline[i] was executed i + 1 cycles ago, None if AGU was not triggered.
+    pub agu_cm_pipeline: Vec<Option<Instruction>>,
     pub previous_op: Option<Operation>,
 }
 
@@ -124,8 +218,7 @@ impl PE {
             pc: 0,
             configurations: program.configurations,
             is_mem_pe_flag: false,
-            agu_cm_s: None,
-            agu_cm_ss: None,
+            agu_cm_pipeline: vec![None; DEFAULT_MEM_LATENCY],
             previous_op: None,
         }
     }
@@ -137,12 +230,16 @@ impl PE {
             pc: 0,
             configurations: program.configurations,
             is_mem_pe_flag: true,
-            agu_cm_s: None,
-            agu_cm_ss: None,
+            agu_cm_pipeline: vec![None; DEFAULT_MEM_LATENCY],
             previous_op: None,
         }
     }
 
+    /// Change the memory latency, i.e. the depth of the AGU CM pipeline, the pipeline is cleared
+    pub fn set_mem_latency(&mut self, latency: usize) {
+        self.agu_cm_pipeline = vec![None; latency];
+    }
+
     pub fn current_conf(&self) -> &Configuration {
         &self.configurations[self.pc]
     }
@@ -156,39 +253,45 @@ impl PE {
     }
 
     /// Update the alu_out signal for ALU instructions and SIMD instructions, other instructions will not trigger the update
+    /// With the ALU bypass, op1 is forwarded to alu_out whatever the instruction
     pub fn update_alu_out(&mut self) {
-        let configuration = self.configurations[self.pc].clone();
-        let operation = configuration.operation.clone();
+        let configuration = self.configurations[self.pc];
+        let operation = configuration.operation;
 
-        if operation.is_arith_logic() || operation.is_simd() {
+        if configuration.alu_bypass {
+            self.signals.wire_alu_out = Some(self.regs.reg_op1);
+        } else if configuration.use_float && operation.is_arith_logic() {
+            self.execute_alu_float(&operation);
+        } else if operation.is_arith_logic() || operation.is_simd() {
             self.execute_alu_simd(&operation);
         }
     }
 
-    /// Receive data from memory for LOAD operations that completed 2 cycles ago.
+    /// Receive data from memory for LOAD operations that completed N cycles ago, N being the memory latency.
     /// This MUST be called BEFORE update_alu_out() so that ALU can use the loaded data.
     ///
-    /// With 2-cycle memory latency:
-    /// - agu_cm_ss contains the AGU instruction from 2 cycles ago
-    /// - If agu_cm_ss was a LOAD, data is now available in reg_dmem_data_s (the shifted register)
+    /// With N-cycle memory latency:
+    /// - the last entry of agu_cm_pipeline contains the AGU instruction from N cycles ago
+    /// - If it was a LOAD, data is now available in the last register of reg_dmem_data
     /// - We update reg_op1 with the data (masked by data width)
     pub fn receive_mem_data(&mut self, dmem_interface: &DMemInterface) {
         if !self.is_mem_pe() {
             return;
         }
 
-        if let Some(agu_cm_ss) = &self.agu_cm_ss {
-            if agu_cm_ss.inst_type == InstType::LOAD {
-                if dmem_interface.reg_dmem_data_s.is_none() {
+        if let Some(Some(agu_cm)) = self.agu_cm_pipeline.last() {
+            if agu_cm.inst_type == InstType::LOAD {
+                let Some(raw_data) = dmem_interface.loaded_data() else {
                     log::error!(
-                        "AGU instruction 2 cycles ago was LOAD, but no data is available in reg_dmem_data_s. \
-                        Most likely you have setup memories wrong"
+                        "AGU instruction {} cycles ago was LOAD, but no data is available in {}. \
+                        Most likely you have setup memories wrong",
+                        self.agu_cm_pipeline.len(),
+                        DMemInterface::reg_dmem_data_name(dmem_interface.latency() - 1)
                     );
                     panic!("Simulator stops. Fatal Error.");
-                }
-                // Extract meaningful bits according to data width from the shifted register
-                let raw_data = dmem_interface.reg_dmem_data_s.unwrap();
-                let masked_data = match agu_cm_ss.data_width {
+                };
+                // Extract meaningful bits according to data width from the last shifted register
+                let masked_data = match agu_cm.data_width {
                     DataWidth::B8 => raw_data & 0xFF,
                     DataWidth::B16 => raw_data & 0xFFFF,
                     DataWidth::B64 => raw_data,
@@ -202,8 +305,8 @@ impl PE {
     /// Update the dmem_interface for STORE operations.
     /// AGU has already set the mode and address on dmem_interface before this is called (if agu_trigger).
     pub fn update_mem(&mut self, dmem_interface: &mut DMemInterface) {
-        let configuration = self.configurations[self.pc].clone();
-        let operation = configuration.operation.clone();
+        let configuration = self.configurations[self.pc];
+        let operation = configuration.operation;
         let agu_trigger = configuration.agu_trigger;
 
         // Error on PE LOAD/STORE opcodes - these are deprecated
@@ -223,6 +326,14 @@ impl PE {
             return;
         }
 
+        // A predicated STORE with reg_predicate false is suppressed, the AGU still advances
+        if dmem_interface.mode.is_store() && !self.is_enabled() {
+            dmem_interface.mode = DMemMode::NOP;
+            dmem_interface.wire_dmem_addr = None;
+            dmem_interface.wire_dmem_data = None;
+            return;
+        }
+
         // If mode is STORE, set wire_dmem_data from reg_op1
         if dmem_interface.mode.is_store() {
             dmem_interface.wire_dmem_data = Some(self.regs.reg_op1);
@@ -231,28 +342,36 @@ impl PE {
         }
     }
 
+    /// Whether the current configuration updates reg_res and stores,
+    /// false for a predicated configuration while reg_predicate is false
+    pub fn is_enabled(&self) -> bool {
+        !self.configurations[self.pc].predicated || self.regs.reg_predicate
+    }
+
     /// Update the router output signals according to the router config
     pub fn update_router_output(&mut self) -> Result<(), String> {
-        let configuration = self.configurations[self.pc].clone();
-        let router_config = configuration.router_config.clone();
+        let router_config = self.configurations[self.pc].router_config;
         self.execute_router_output(&router_config)?;
         Ok(())
     }
 
     /// Update registers at the end of the cycle.
     /// For memory PEs, also update the AGU CM pipeline state:
-    /// - `agu_cm_ss` is shifted from `agu_cm_s`
-    /// - `agu_cm_s` is updated based on current agu_trigger and the AGU instruction
+    /// - `agu_cm_pipeline` is shifted by one cycle
+    /// - `agu_cm_pipeline[0]` is updated based on current agu_trigger and the AGU instruction
     ///
     /// # Arguments
     /// * `current_agu_cm` - The AGU's current CM instruction (if AGU is triggered this cycle)
     pub fn update_registers(&mut self, current_agu_cm: Option<&Instruction>) -> Result<(), String> {
-        let configuration = self.configurations[self.pc].clone();
-        let operation = configuration.operation.clone();
+        let configuration = self.configurations[self.pc];
+        let operation = configuration.operation;
         let agu_trigger = configuration.agu_trigger;
 
-        // Update res register considering the update_res flag in the operation
-        self.update_res(&operation);
+        // Update res register considering the update_res flag in the operation,
+        // the predicate of this cycle is the one registered before the operands update
+        if self.is_enabled() {
+            self.update_res(&operation);
+        }
         // Update router input registers
         self.update_router_input_registers(&configuration.router_config)?;
         // Update operands registers (does not raise errors for missing wire signals)
@@ -262,40 +381,62 @@ impl PE {
 
         // Update AGU CM pipeline for memory PEs
         if self.is_mem_pe() {
-            // Shift the pipeline: agu_cm_ss gets the previous agu_cm_s
-            self.agu_cm_ss = self.agu_cm_s;
-
-            // Update agu_cm_s based on current AGU trigger
-            if agu_trigger {
-                // AGU was triggered this cycle, record the current AGU CM
-                self.agu_cm_s = current_agu_cm.copied();
-            } else {
-                // AGU was not triggered, clear agu_cm_s
-                self.agu_cm_s = None;
+            // Shift the pipeline: agu_cm_pipeline[i] gets the previous agu_cm_pipeline[i - 1]
+            self.agu_cm_pipeline.rotate_right(1);
+
+            // Update agu_cm_pipeline[0] based on current AGU trigger
+            if let Some(agu_cm_s) = self.agu_cm_pipeline.first_mut() {
+                // Record the current AGU CM if AGU was triggered this cycle, clear it otherwise
+                *agu_cm_s = if agu_trigger {
+                    current_agu_cm.copied()
+                } else {
+                    None
+                };
             }
         }
 
-        // update the loop registers
-        if operation.is_control() {
-            if operation.op_code == OpCode::JUMP {
+        self.update_loop_registers(&operation);
+        Ok(())
+    }
+
+    /// Update the loop registers with a control operation, they drive the PC in next_conf()
+    pub fn update_loop_registers(&mut self, operation: &Operation) {
+        match operation.op_code {
+            OpCode::JUMP => {
+                // JUMP resets the predicate, even if one is routed in the same configuration
+                self.regs.reg_predicate = false;
                 self.regs.reg_loop_start = operation.loop_start.unwrap();
                 self.regs.reg_loop_end = operation.loop_end.unwrap();
-            } else {
-                unimplemented!("Control operations other than JUMP are not supported yet");
             }
+            // BR does not touch the loop registers, it redirects the PC in next_conf()
+            OpCode::BR => {}
+            // not a control operation
+            _ => {}
         }
-        Ok(())
     }
 
     pub fn next_conf(&mut self) {
-        let current_conf = self.configurations[self.pc].clone();
+        let current_conf = self.configurations[self.pc];
         // following the RTL implementation: if current op is jump but previous op is not jump, jump
         if current_conf.operation.is_jump()
             && (self.previous_op.is_none()
                 || self.previous_op.is_some() && !self.previous_op.unwrap().is_jump())
         {
             self.pc = current_conf.operation.immediate.unwrap() as usize;
-            assert!(self.pc <= 15, "Jump destination out of bounds");
+            assert!(
+                self.pc < self.configurations.len(),
+                "Jump destination out of bounds"
+            );
+        } else if current_conf.operation.op_code == OpCode::BR && self.regs.reg_predicate {
+            // the branch is taken with reg_predicate at the end of the cycle
+            self.pc = current_conf
+                .operation
+                .immediate
+                .expect("Branch destination must be set") as usize;
+            assert!(
+                self.pc < self.configurations.len(),
+                "Branch destination out of bounds"
+            );
         } else if self.pc >= self.regs.reg_loop_end as usize
             || self.pc < self.regs.reg_loop_start as usize
         {
@@ -304,31 +445,38 @@ impl PE {
             self.pc += 1;
         }
         // keep the previous operation
-        self.previous_op = Some(current_conf.operation.clone());
+        self.previous_op = Some(current_conf.operation);
         // clean all wire signals
         self.signals = PESignals::default();
     }
 
     /// Snapshot of the PE state after a cycle of execution
-    /// Displayed current conf is the configuration that has just been executed
+    /// Displayed current conf is the configuration that has just been executed,
+    /// the lanes are shown in its view
     pub fn snapshot(&self) -> String {
+        let view = self.configurations[self.pc].lane_view();
+        let float_format = self.configurations[self.pc].float_format;
         let mut result = String::new();
         result.push_str(&format!("PC: {}\n", self.pc));
-        result.push_str(&format!("Reg:\n{:?}\n", self.regs));
-        result.push_str(&format!("Sig:\n{:?}\n", self.signals));
+        result.push_str(&format!(
+            "Reg:\n{}\n",
+            self.regs.describe(view, float_format)
+        ));
+        result.push_str(&format!("Sig:\n{}\n", self.signals.describe(view)));
         result.push_str(&format!(
             "Conf: {}\n",
             self.configurations[self.pc].to_mnemonics()
         ));
         if self.is_mem_pe() {
-            result.push_str(&format!(
-                "AGU CM (1 cycle ago): {:?}\n",
-                self.agu_cm_s
-            ));
-            result.push_str(&format!(
-                "AGU CM (2 cycles ago): {:?}\n",
-                self.agu_cm_ss
-            ));
+            for (i, agu_cm) in self.agu_cm_pipeline.iter().enumerate() {
+                let cycles = if i == 0 { "cycle" } else { "cycles" };
+                result.push_str(&format!(
+                    "AGU CM ({} {} ago): {:?}\n",
+                    i + 1,
+                    cycles,
+                    agu_cm
+                ));
+            }
         }
         result
     }
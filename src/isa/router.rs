@@ -79,6 +79,7 @@ impl Index<RouterOutDir> for RouterSwitchConfig {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Direction {
     North,
     South,
@@ -277,24 +278,45 @@ impl PE {
             RouterInDir::Invalid => unreachable!(),
             RouterInDir::Open => {}
         }
+        // the predicate register only keeps the LSB of the source
         match router_config.switch_config.predicate {
             RouterInDir::EastIn => {
-                todo!()
+                if router_config.input_register_used.east {
+                    self.regs.reg_predicate = self.regs.reg_east_in & 1 == 1;
+                } else if let Some(val) = self.signals.wire_east_in {
+                    self.regs.reg_predicate = val & 1 == 1;
+                }
             }
             RouterInDir::SouthIn => {
-                todo!()
+                if router_config.input_register_used.south {
+                    self.regs.reg_predicate = self.regs.reg_south_in & 1 == 1;
+                } else if let Some(val) = self.signals.wire_south_in {
+                    self.regs.reg_predicate = val & 1 == 1;
+                }
             }
             RouterInDir::WestIn => {
-                todo!()
+                if router_config.input_register_used.west {
+                    self.regs.reg_predicate = self.regs.reg_west_in & 1 == 1;
+                } else if let Some(val) = self.signals.wire_west_in {
+                    self.regs.reg_predicate = val & 1 == 1;
+                }
             }
             RouterInDir::NorthIn => {
-                todo!()
+                if router_config.input_register_used.north {
+                    self.regs.reg_predicate = self.regs.reg_north_in & 1 == 1;
+                } else if let Some(val) = self.signals.wire_north_in {
+                    self.regs.reg_predicate = val & 1 == 1;
+                }
             }
             RouterInDir::ALUOut => {
-                todo!()
+                let val = self
+                    .signals
+                    .wire_alu_out
+                    .ok_or("Updating Predicate register but the wire signal is not updated")?;
+                self.regs.reg_predicate = val & 1 == 1;
             }
             RouterInDir::ALURes => {
-                todo!()
+                self.regs.reg_predicate = self.regs.reg_res & 1 == 1;
             }
             RouterInDir::Invalid => unreachable!(),
             RouterInDir::Open => {}
@@ -372,8 +394,27 @@ impl PE {
             _ => {}
         }
 
-        // Note: Predicate validation is not implemented since the predicate handling uses todo!()
-        // When predicate handling is implemented, add validation here as well.
+        // Validate predicate wire signal
+        let used = &router_config.input_register_used;
+        match router_config.switch_config.predicate {
+            RouterInDir::EastIn if !used.east && self.signals.wire_east_in.is_none() => {
+                return Err("Predicate: EastIn wire signal is not updated".to_string());
+            }
+            RouterInDir::SouthIn if !used.south && self.signals.wire_south_in.is_none() => {
+                return Err("Predicate: SouthIn wire signal is not updated".to_string());
+            }
+            RouterInDir::WestIn if !used.west && self.signals.wire_west_in.is_none() => {
+                return Err("Predicate: WestIn wire signal is not updated".to_string());
+            }
+            RouterInDir::NorthIn if !used.north && self.signals.wire_north_in.is_none() => {
+                return Err("Predicate: NorthIn wire signal is not updated".to_string());
+            }
+            RouterInDir::ALUOut if self.signals.wire_alu_out.is_none() => {
+                return Err("Predicate: ALUOut wire signal is not updated".to_string());
+            }
+            // ALURes, Invalid, Open don't need wire signal validation
+            _ => {}
+        }
 
         Ok(())
     }
@@ -523,39 +564,39 @@ impl PE {
         &mut self,
         src_pe: &PE,
         direction: RouterInDir,
+    ) -> Result<(), String> {
+        self.receive_router_signal(direction, src_pe.output_wire_to(direction))
+    }
+
+    /// The output wire of the PE that feeds the given input direction of its neighbour
+    pub fn output_wire_to(&self, direction: RouterInDir) -> Option<u64> {
+        match direction {
+            RouterInDir::NorthIn => self.signals.wire_south_out,
+            RouterInDir::SouthIn => self.signals.wire_north_out,
+            RouterInDir::WestIn => self.signals.wire_east_out,
+            RouterInDir::EastIn => self.signals.wire_west_out,
+            _ => panic!("You cannot propagate router signals from inside of PE"),
+        }
+    }
+
+    /// Update the input wire of the given direction with the output of the neighbour
+    pub fn receive_router_signal(
+        &mut self,
+        direction: RouterInDir,
+        value: Option<u64>,
     ) -> Result<(), String> {
         match direction {
             RouterInDir::NorthIn => {
-                self.signals.wire_north_in = Some(
-                    src_pe
-                        .signals
-                        .wire_south_out
-                        .ok_or("SouthOut is not updated")?,
-                );
+                self.signals.wire_north_in = Some(value.ok_or("SouthOut is not updated")?);
             }
             RouterInDir::SouthIn => {
-                self.signals.wire_south_in = Some(
-                    src_pe
-                        .signals
-                        .wire_north_out
-                        .ok_or("NorthOut is not updated")?,
-                );
+                self.signals.wire_south_in = Some(value.ok_or("NorthOut is not updated")?);
             }
             RouterInDir::WestIn => {
-                self.signals.wire_west_in = Some(
-                    src_pe
-                        .signals
-                        .wire_east_out
-                        .ok_or("EastOut is not updated")?,
-                );
+                self.signals.wire_west_in = Some(value.ok_or("EastOut is not updated")?);
             }
             RouterInDir::EastIn => {
-                self.signals.wire_east_in = Some(
-                    src_pe
-                        .signals
-                        .wire_west_out
-                        .ok_or("WestOut is not updated")?,
-                );
+                self.signals.wire_east_in = Some(value.ok_or("WestOut is not updated")?);
             }
             _ => panic!("You cannot propagate router signals from inside of PE"),
         }
@@ -3,13 +3,15 @@ use std::{
     ops::{Add, Mul},
 };
 
-use crate::isa::fp8::FP8;
+use crate::isa::fp8::{FP8, FP8Format, FP8Mode};
 
-pub struct SIMDValue(pub [FP8; 8]);
+/// 8 FP8 lanes, interpreted in the given format.
+/// The conversions from u64 and from the lanes use the default format (E4M3)
+pub struct SIMDValue(pub [FP8; 8], pub FP8Format);
 
 impl Debug for SIMDValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let val_f32: [f32; 8] = self.0.map(|v| v.into());
+        let val_f32: [f32; 8] = self.0.map(|v| self.1.decode(v.into()) as f32);
         let val_u8: [u8; 8] = self.0.map(|v| v.into());
         write!(f, "SIMD[")?;
         for i in 0..8 {
@@ -22,7 +24,7 @@ impl Debug for SIMDValue {
 
 impl From<u64> for SIMDValue {
     fn from(value: u64) -> Self {
-        SIMDValue(value.to_le_bytes().map(|b| FP8::from(b)))
+        SIMDValue::with_format(value, FP8Format::default())
     }
 }
 
@@ -39,7 +41,7 @@ impl From<SIMDValue> for u64 {
 
 impl From<[FP8; 8]> for SIMDValue {
     fn from(value: [FP8; 8]) -> Self {
-        SIMDValue(value)
+        SIMDValue(value, FP8Format::default())
     }
 }
 
@@ -50,20 +52,54 @@ impl From<SIMDValue> for [FP8; 8] {
 }
 
 impl SIMDValue {
+    /// The lanes of the value in the given format, the lane 0 is the least significant byte
+    pub fn with_format(value: u64, format: FP8Format) -> Self {
+        SIMDValue(value.to_le_bytes().map(|b| FP8::from(b)), format)
+    }
+
+    /// The operations are in the format of self, without saturation: the configuration has no
+    /// bit for the saturating mode, the overflows of the datapath give NaN (E4M3) or the infinity (E5M2)
+    fn mode(&self) -> FP8Mode {
+        FP8Mode {
+            format: self.1,
+            saturating: false,
+        }
+    }
+
     pub fn vadd(&self, other: &SIMDValue) -> SIMDValue {
+        let mode = self.mode();
         let mut result = [FP8::default(); 8];
         for i in 0..8 {
-            result[i] = self.0[i] + other.0[i];
+            result[i] = mode.add(self.0[i], other.0[i]);
         }
-        SIMDValue(result)
+        SIMDValue(result, self.1)
     }
 
     pub fn vmul(&self, other: &SIMDValue) -> SIMDValue {
+        let mode = self.mode();
         let mut result = [FP8::default(); 8];
         for i in 0..8 {
-            result[i] = self.0[i] * other.0[i];
+            result[i] = mode.mul(self.0[i], other.0[i]);
         }
-        SIMDValue(result)
+        SIMDValue(result, self.1)
+    }
+
+    /// acc + self * other lane by lane, each lane rounded once
+    pub fn vmac(&self, other: &SIMDValue, acc: &SIMDValue) -> SIMDValue {
+        let mode = self.mode();
+        let result = std::array::from_fn(|i| mode.mac(self.0[i], other.0[i], acc.0[i]));
+        SIMDValue(result, self.1)
+    }
+
+    /// acc plus the sum of the products of the lanes. The products are exact in f64, they are
+    /// added to acc in f64 from the lane 0 to the lane 7, then the sum is rounded to nearest
+    /// even to f32. The sum of the products is exact for E4M3 lanes
+    pub fn vdot(&self, other: &SIMDValue, acc: f32) -> f32 {
+        let mode = self.mode();
+        let sum = (0..8).fold(acc as f64, |sum, i| {
+            sum + mode.decode(self.0[i]) * mode.decode(other.0[i])
+        });
+        sum as f32
     }
 }
 
@@ -81,6 +117,169 @@ impl Mul for SIMDValue {
     }
 }
 
+/// The signed integer lanes of a 64-bit value, the lane 0 is the least significant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntLanes {
+    /// 8 lanes of i8
+    I8,
+    /// 4 lanes of i16
+    I16,
+}
+
+impl IntLanes {
+    pub fn bits(&self) -> u32 {
+        match self {
+            IntLanes::I8 => 8,
+            IntLanes::I16 => 16,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        (64 / self.bits()) as usize
+    }
+
+    /// The smallest and the largest value of a lane
+    pub fn range(&self) -> (i64, i64) {
+        let max = (1i64 << (self.bits() - 1)) - 1;
+        (-max - 1, max)
+    }
+
+    /// The sign-extended lane i of the value
+    pub fn get(&self, value: u64, i: usize) -> i64 {
+        let shift = 64 - self.bits() * (i as u32 + 1);
+        ((value << shift) as i64) >> (64 - self.bits())
+    }
+
+    /// The value with the low bits of the immediate in every lane
+    pub fn broadcast(&self, immediate: u16) -> u64 {
+        match self {
+            IntLanes::I8 => (immediate as u8) as u64 * 0x0101_0101_0101_0101,
+            IntLanes::I16 => immediate as u64 * 0x0001_0001_0001_0001,
+        }
+    }
+}
+
+/// The lane-wise operations on integer lanes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntSIMDOp {
+    Add,
+    Sub,
+    Mul,
+    Min,
+    Max,
+    /// Shift op1 left by op2 if op2 is positive, arithmetic shift right by -op2 otherwise
+    Shl,
+}
+
+impl IntSIMDOp {
+    /// Apply the operation to each pair of lanes. The exact result of a lane is computed,
+    /// then it keeps its low bits (wrapping) or is clamped to the range of the lane (saturating).
+    /// Min, Max and the right shifts cannot overflow, both variants give the same result.
+    pub fn apply(&self, lanes: IntLanes, saturating: bool, op1: u64, op2: u64) -> u64 {
+        let bits = lanes.bits();
+        let (min, max) = lanes.range();
+        let mask = (1u64 << bits) - 1;
+        let mut result = 0;
+        for i in 0..lanes.count() {
+            let (a, b) = (lanes.get(op1, i), lanes.get(op2, i));
+            let exact = match self {
+                IntSIMDOp::Add => a + b,
+                IntSIMDOp::Sub => a - b,
+                IntSIMDOp::Mul => a * b,
+                IntSIMDOp::Min => a.min(b),
+                IntSIMDOp::Max => a.max(b),
+                // all the bits of the lane are shifted out from a shift of its width
+                IntSIMDOp::Shl if b >= 0 => a << b.min(bits as i64),
+                IntSIMDOp::Shl => a >> (-b).min(bits as i64 - 1),
+            };
+            let lane = if saturating {
+                exact.clamp(min, max)
+            } else {
+                exact
+            };
+            result |= (lane as u64 & mask) << (i as u32 * bits);
+        }
+        result
+    }
+}
+
+/// 8 i8 lanes, the lane 0 is the least significant byte
+pub struct I8x8(pub [i8; 8]);
+
+impl Debug for I8x8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "I8[")?;
+        for lane in self.0 {
+            write!(f, "{:08b}/{} ", lane as u8, lane)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl From<u64> for I8x8 {
+    fn from(value: u64) -> Self {
+        I8x8(value.to_le_bytes().map(|b| b as i8))
+    }
+}
+
+impl From<I8x8> for u64 {
+    fn from(value: I8x8) -> Self {
+        u64::from_le_bytes(value.0.map(|lane| lane as u8))
+    }
+}
+
+/// 4 i16 lanes, the lane 0 is the least significant half-word
+pub struct I16x4(pub [i16; 4]);
+
+impl Debug for I16x4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "I16[")?;
+        for lane in self.0 {
+            write!(f, "0x{:04x}/{} ", lane as u16, lane)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl From<u64> for I16x4 {
+    fn from(value: u64) -> Self {
+        I16x4(std::array::from_fn(|i| (value >> (i * 16)) as i16))
+    }
+}
+
+impl From<I16x4> for u64 {
+    fn from(value: I16x4) -> Self {
+        value
+            .0
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, lane)| acc | (*lane as u16 as u64) << (i * 16))
+    }
+}
+
+/// How the lanes of a 64-bit value are shown in the debug views and the snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneView {
+    FP8(FP8Format),
+    Int(IntLanes),
+}
+
+impl Default for LaneView {
+    fn default() -> Self {
+        LaneView::FP8(FP8Format::default())
+    }
+}
+
+impl LaneView {
+    pub fn describe(&self, value: u64) -> String {
+        match self {
+            LaneView::FP8(format) => format!("{:?}", SIMDValue::with_format(value, *format)),
+            LaneView::Int(IntLanes::I8) => format!("{:?}", I8x8::from(value)),
+            LaneView::Int(IntLanes::I16) => format!("{:?}", I16x4::from(value)),
+        }
+    }
+}
+
 pub struct ScalarValue(pub i16);
 
 impl From<u64> for ScalarValue {
@@ -105,3 +304,71 @@ impl From<i16> for ScalarValue {
         ScalarValue(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reference of IntSIMDOp::Shl on an i8 lane
+    fn shl_i8(a: i8, b: i8, saturating: bool) -> i8 {
+        if b < 0 {
+            return a >> (-(b as i32)).min(7);
+        }
+        let exact = if b >= 8 { None } else { Some((a as i32) << b) };
+        match exact {
+            Some(v) if !saturating => v as i8,
+            Some(v) if v >= i8::MIN as i32 && v <= i8::MAX as i32 => v as i8,
+            _ if !saturating || a == 0 => 0,
+            _ if a < 0 => i8::MIN,
+            _ => i8::MAX,
+        }
+    }
+
+    /// The lane-wise operations against the operations of Rust on i8
+    #[test]
+    fn test_int_simd_i8_exhaustive() {
+        for a in i8::MIN..=i8::MAX {
+            for b in i8::MIN..=i8::MAX {
+                // (a, b) in lane 3, (b, a) in lane 6
+                let op1 = u64::from(I8x8([0, 0, 0, a, 0, 0, b, 0]));
+                let op2 = u64::from(I8x8([0, 0, 0, b, 0, 0, a, 0]));
+                let check = |op: IntSIMDOp, saturating: bool, reference: fn(i8, i8) -> i8| {
+                    let lanes = I8x8::from(op.apply(IntLanes::I8, saturating, op1, op2)).0;
+                    assert_eq!(lanes[3], reference(a, b), "{:?} {} {}", op, a, b);
+                    assert_eq!(lanes[6], reference(b, a), "{:?} {} {}", op, b, a);
+                    assert_eq!(lanes[0], reference(0, 0));
+                };
+                check(IntSIMDOp::Add, false, i8::wrapping_add);
+                check(IntSIMDOp::Add, true, i8::saturating_add);
+                check(IntSIMDOp::Sub, false, i8::wrapping_sub);
+                check(IntSIMDOp::Sub, true, i8::saturating_sub);
+                check(IntSIMDOp::Mul, false, i8::wrapping_mul);
+                check(IntSIMDOp::Mul, true, i8::saturating_mul);
+                for saturating in [false, true] {
+                    check(IntSIMDOp::Min, saturating, std::cmp::min);
+                    check(IntSIMDOp::Max, saturating, std::cmp::max);
+                }
+                check(IntSIMDOp::Shl, false, |a, b| shl_i8(a, b, false));
+                check(IntSIMDOp::Shl, true, |a, b| shl_i8(a, b, true));
+            }
+        }
+    }
+
+    #[test]
+    fn test_int_simd_i16() {
+        let op1 = u64::from(I16x4([i16::MAX, -2, 300, -32768]));
+        let op2 = u64::from(I16x4([1, 3, -2, 16]));
+        let apply = |op: IntSIMDOp, saturating| {
+            I16x4::from(op.apply(IntLanes::I16, saturating, op1, op2)).0
+        };
+        assert_eq!(apply(IntSIMDOp::Add, false), [i16::MIN, 1, 298, -32752]);
+        assert_eq!(apply(IntSIMDOp::Add, true), [i16::MAX, 1, 298, -32752]);
+        assert_eq!(apply(IntSIMDOp::Mul, false), [i16::MAX, -6, -600, 0]);
+        assert_eq!(apply(IntSIMDOp::Mul, true), [i16::MAX, -6, -600, i16::MIN]);
+        assert_eq!(apply(IntSIMDOp::Min, false), [1, -2, -2, -32768]);
+        assert_eq!(apply(IntSIMDOp::Shl, false), [-2, -16, 75, 0]);
+        assert_eq!(apply(IntSIMDOp::Shl, true), [i16::MAX, -16, 75, i16::MIN]);
+        assert_eq!(IntLanes::I16.broadcast(0xFFFE), u64::from(I16x4([-2; 4])));
+        assert_eq!(IntLanes::I8.broadcast(0x1FF), u64::from(I8x8([-1; 8])));
+    }
+}
@@ -0,0 +1,441 @@
+//! The description of an architecture of the PACE family: the parameters fixed by the hardware,
+//! as opposed to the programs and the data of a folder.
+//! It drives the loading and the validation of a folder, the global address map and the header generation.
+//! The default is PACE 2.0: 8x8 PEs, 4 DMs of 8KB on the left and on the right edges.
+//!
+//! A descriptor is a TOML file (JSON if the extension is .json), the missing fields take the value of PACE 2.0:
+//! ```toml
+//! cols = 4
+//! rows = 4
+//! dms_per_edge = 2
+//! ```
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dmem::{DEFAULT_MEM_LATENCY, DataMemory},
+    grid::{Grid, PEIdx},
+    loader::{self, LoadError},
+    topology::{AguIndexing, MemEdge, MemoryTopology},
+};
+
+/// The jump destination, loop start and loop end of a configuration are 4 bits
+const MAX_CM_DEPTH: usize = 16;
+/// The loop end PC of an AGU is 8 bits in the header
+const MAX_AGU_CM_DEPTH: usize = 256;
+/// The addresses of the AGUs are 13 bits
+const MAX_DM_SIZE: usize = 8192;
+/// The deepest read pipeline of the data memories supported by the simulator
+const MAX_MEM_LATENCY: usize = 8;
+
+/// The parameters of an architecture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Architecture {
+    /// The number of PE columns
+    pub cols: usize,
+    /// The number of PE rows
+    pub rows: usize,
+    /// The depth of the configuration memory of a PE
+    pub cm_depth: usize,
+    /// The size of a data memory in bytes
+    pub dm_size: usize,
+    /// The edges carrying the memory PEs, the DMs and AGUs are numbered edge by edge in this order
+    pub edges: Vec<MemEdge>,
+    /// The number of data memories along every memory edge
+    pub dms_per_edge: usize,
+    /// The latency of a data memory access, in cycles
+    pub mem_latency: usize,
+    /// The depth of the control memory (and of the address register file) of an AGU
+    pub agu_cm_depth: usize,
+}
+
+impl Default for Architecture {
+    /// PACE 2.0
+    fn default() -> Self {
+        Architecture {
+            cols: 8,
+            rows: 8,
+            cm_depth: 16,
+            dm_size: 1024 * 8,
+            edges: vec![MemEdge::West, MemEdge::East],
+            dms_per_edge: 4,
+            mem_latency: DEFAULT_MEM_LATENCY,
+            agu_cm_depth: 16,
+        }
+    }
+}
+
+impl Architecture {
+    /// The architecture of a grid built without a descriptor: the shape, the memory edges, the
+    /// largest DM and the memory latency of the grid, the depths of PACE 2.0
+    pub fn describe(shape: PEIdx, topology: &MemoryTopology, dmems: &[DataMemory]) -> Self {
+        Architecture {
+            cols: shape.x,
+            rows: shape.y,
+            dm_size: dmems.iter().map(|dmem| dmem.capacity()).max().unwrap_or(0),
+            edges: topology.edges.clone(),
+            dms_per_edge: topology.num_dms(shape) / topology.edges.len().max(1),
+            mem_latency: dmems
+                .first()
+                .map_or(DEFAULT_MEM_LATENCY, |dmem| dmem.port1.latency()),
+            ..Default::default()
+        }
+    }
+
+    /// Load and validate a descriptor, TOML or JSON (by the extension)
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let content = loader::read_file(path)?;
+        let arch: Architecture = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|e| {
+                // the position is reported by the LoadError itself
+                let message = e.to_string();
+                let message = message.split(" at line ").next().unwrap_or_default();
+                LoadError::Parse {
+                    path: path.to_path_buf(),
+                    line: e.line(),
+                    column: e.column().max(1),
+                    message: format!("invalid architecture: {}", message),
+                }
+            })?
+        } else {
+            toml::from_str(&content).map_err(|e| {
+                let offset = e.span().map_or(0, |span| span.start);
+                let message = format!("invalid architecture: {}", e.message());
+                LoadError::parse(path, &content, offset, message)
+            })?
+        };
+        arch.validate()
+            .map_err(|message| LoadError::InvalidArchitecture {
+                path: path.to_path_buf(),
+                message,
+            })?;
+        Ok(arch)
+    }
+
+    /// The shape of the array, (x, y)
+    pub fn shape(&self) -> PEIdx {
+        PEIdx {
+            x: self.cols,
+            y: self.rows,
+        }
+    }
+
+    /// The memory topology, the AGUs are numbered edge by edge
+    pub fn topology(&self) -> MemoryTopology {
+        let edge_len = self.edges.first().map_or(0, |e| e.pes(self.shape()).len());
+        MemoryTopology {
+            edges: self.edges.clone(),
+            pes_per_dm: (edge_len / self.dms_per_edge.max(1)).max(1),
+            agu_indexing: AguIndexing::EdgeMajor,
+        }
+    }
+
+    /// Check that the architecture can be simulated and addressed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cols == 0 || self.rows == 0 {
+            return Err(format!(
+                "the array of {} cols x {} rows has no PE",
+                self.cols, self.rows
+            ));
+        }
+        if !(1..=MAX_CM_DEPTH).contains(&self.cm_depth) {
+            return Err(format!(
+                "cm_depth is {}, it must be between 1 and {} (the jump and loop fields are 4 bits)",
+                self.cm_depth, MAX_CM_DEPTH
+            ));
+        }
+        if !(1..=MAX_AGU_CM_DEPTH).contains(&self.agu_cm_depth) {
+            return Err(format!(
+                "agu_cm_depth is {}, it must be between 1 and {}",
+                self.agu_cm_depth, MAX_AGU_CM_DEPTH
+            ));
+        }
+        if self.dm_size == 0 || !self.dm_size.is_multiple_of(8) || self.dm_size > MAX_DM_SIZE {
+            return Err(format!(
+                "dm_size is {} bytes, it must be a multiple of 8 up to {} (the AGU addresses are 13 bits)",
+                self.dm_size, MAX_DM_SIZE
+            ));
+        }
+        if !(1..=MAX_MEM_LATENCY).contains(&self.mem_latency) {
+            return Err(format!(
+                "mem_latency is {} cycles, it must be from 1 to {}",
+                self.mem_latency, MAX_MEM_LATENCY
+            ));
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            if self.edges[..i].contains(edge) {
+                return Err(format!("the {:?} edge is given twice", edge));
+            }
+            let vertical = |e: &MemEdge| matches!(e, MemEdge::West | MemEdge::East);
+            if let Some(other) = self.edges[..i]
+                .iter()
+                .find(|e| vertical(e) != vertical(edge))
+            {
+                return Err(format!(
+                    "the {:?} and {:?} edges share a corner PE",
+                    other, edge
+                ));
+            }
+            let edge_len = edge.pes(self.shape()).len();
+            if self.dms_per_edge == 0
+                || !edge_len.is_multiple_of(self.dms_per_edge)
+                || !(1..=2).contains(&(edge_len / self.dms_per_edge))
+            {
+                return Err(format!(
+                    "{} DMs along the {:?} edge of {} PEs, a DM must be shared by 1 or 2 PEs",
+                    self.dms_per_edge, edge, edge_len
+                ));
+            }
+        }
+        if self.address_map().target_shift + 2 > u32::BITS {
+            return Err("the global address space does not fit in 32 bits".to_string());
+        }
+        Ok(())
+    }
+
+    /// The global address map of the architecture
+    pub fn address_map(&self) -> AddressMap {
+        let entry_bits = bits_for(self.cm_depth.max(self.agu_cm_depth));
+        let sub_region_shift = ENTRY_SHIFT + entry_bits;
+        let pe_shift = sub_region_shift + 2;
+        let pe_end = pe_shift + bits_for(self.cols * self.rows);
+        // every DM has a window of twice its size
+        let dm_shift = bits_for(self.dm_size) + 1;
+        let edge_shift = dm_shift + bits_for(self.dms_per_edge);
+        let dm_end = edge_shift + bits_for(self.edges.len());
+        AddressMap {
+            target_shift: pe_end.max(dm_end),
+            pe_shift,
+            sub_region_shift,
+            entry_shift: ENTRY_SHIFT,
+            edge_shift,
+            dm_shift,
+            dms_per_edge: self.dms_per_edge.max(1),
+        }
+    }
+}
+
+/// The number of bits to index n elements
+fn bits_for(n: usize) -> u32 {
+    n.next_power_of_two().trailing_zeros()
+}
+
+/// The entries of the CMs and ARFs are 16 words apart
+const ENTRY_SHIFT: u32 = 4;
+
+/// The targets of the global address space
+const TARGET_PE: u32 = 0b00;
+const TARGET_DM: u32 = 0b01;
+
+/// The regions of a PE
+const PE_CM: u32 = 0b00;
+const AGU_CM: u32 = 0b01;
+const AGU_ARF: u32 = 0b10;
+const AGU_MAX_COUNT: u32 = 0b11;
+
+/// The global address map (one address per 64 bits), the one of the PACE 2.0 specification
+/// with the fields sized for the architecture. For PACE 2.0:
+/// - [18:17] the target, 00 for a PE and 01 for a DM
+/// - PE: [15:10] the PE (y * cols + x), [9:8] the region (PE CM, AGU CM, AGU ARF, AGU max count), [7:4] the entry
+/// - DM: [16] the edge, [15:14] the DM along the edge (from top to bottom), the content below
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressMap {
+    pub target_shift: u32,
+    pub pe_shift: u32,
+    pub sub_region_shift: u32,
+    pub entry_shift: u32,
+    pub edge_shift: u32,
+    pub dm_shift: u32,
+    pub dms_per_edge: usize,
+}
+
+impl AddressMap {
+    /// The number of addresses (64-bit words) of the address space
+    pub fn size(&self) -> usize {
+        1 << (self.target_shift + 2)
+    }
+
+    /// The address of the first word of a data memory
+    pub fn dm_base(&self, dm_idx: usize) -> u32 {
+        let edge = (dm_idx / self.dms_per_edge) as u32;
+        let dm_within_edge = (dm_idx % self.dms_per_edge) as u32;
+        (TARGET_DM << self.target_shift)
+            | (edge << self.edge_shift)
+            | (dm_within_edge << self.dm_shift)
+    }
+
+    /// The address of an entry of the configuration memory of a PE (PE index y * cols + x)
+    pub fn pe_cm(&self, pe_idx: usize, cm_idx: usize) -> u32 {
+        self.pe_region(pe_idx, PE_CM) | ((cm_idx as u32) << self.entry_shift)
+    }
+
+    /// The address of an entry of the control memory of an AGU.
+    /// The address space gives one AGU to every PE, the AGU i is in the space of the PE i
+    /// (only the edge PEs actually have an AGU).
+    pub fn agu_cm(&self, agu_idx: usize, cm_idx: usize) -> u32 {
+        self.pe_region(agu_idx, AGU_CM) | ((cm_idx as u32) << self.entry_shift)
+    }
+
+    /// The address of an entry of the address register file of an AGU
+    pub fn agu_arf(&self, agu_idx: usize, arf_idx: usize) -> u32 {
+        self.pe_region(agu_idx, AGU_ARF) | ((arf_idx as u32) << self.entry_shift)
+    }
+
+    /// The address of the max count of an AGU
+    pub fn agu_max_count(&self, agu_idx: usize) -> u32 {
+        self.pe_region(agu_idx, AGU_MAX_COUNT)
+    }
+
+    fn pe_region(&self, pe_idx: usize, region: u32) -> u32 {
+        (TARGET_PE << self.target_shift)
+            | ((pe_idx as u32) << self.pe_shift)
+            | (region << self.sub_region_shift)
+    }
+}
+
+impl Grid {
+    /// Load a folder of the given architecture: the folder must have its shape and fit in its memories.
+    /// The DMs smaller than the DM size are padded with 0s.
+    /// All the problems found in the folder are reported at once in the LoadError.
+    pub fn from_folder_with_arch(path: &str, arch: &Architecture) -> Result<Self, LoadError> {
+        let mut grid = Grid::from_folder(path, arch.topology())?;
+        let folder = Path::new(path);
+        let mut errors = Vec::new();
+
+        if grid.shape != arch.shape() {
+            errors.push(LoadError::CountMismatch {
+                path: folder.to_path_buf(),
+                what: "PE programs".to_string(),
+                expected: arch.cols * arch.rows,
+                found: grid.shape.x * grid.shape.y,
+            });
+        }
+        if grid.agus.len() != grid.mem_ports.len() {
+            errors.push(LoadError::CountMismatch {
+                path: folder.to_path_buf(),
+                what: "AGU programs".to_string(),
+                expected: grid.mem_ports.len(),
+                found: grid.agus.len(),
+            });
+        }
+        for (y, pes_row) in grid.pes.iter().enumerate() {
+            for (x, pe) in pes_row.iter().enumerate() {
+                if pe.configurations.len() > arch.cm_depth {
+                    errors.push(LoadError::CapacityExceeded {
+                        path: folder.join(format!("PE-Y{}X{}", y, x)),
+                        what: "configurations".to_string(),
+                        capacity: arch.cm_depth,
+                        found: pe.configurations.len(),
+                    });
+                }
+            }
+        }
+        for (dm_idx, dmem) in grid.dmems.iter().enumerate() {
+            if dmem.capacity() > arch.dm_size {
+                errors.push(LoadError::SizeMismatch {
+                    path: folder.join(format!("dm{}", dm_idx)),
+                    expected: arch.dm_size,
+                    found: dmem.capacity(),
+                });
+            }
+        }
+        for (agu_idx, agu) in grid.agus.iter().enumerate() {
+            if agu.cm.len() > arch.agu_cm_depth {
+                errors.push(LoadError::CapacityExceeded {
+                    path: folder.join(format!("agu{}", agu_idx)),
+                    what: "AGU instructions".to_string(),
+                    capacity: arch.agu_cm_depth,
+                    found: agu.cm.len(),
+                });
+            }
+        }
+        LoadError::from_errors(errors)?;
+
+        for dmem in grid.dmems.iter_mut() {
+            dmem.data.resize(arch.dm_size, 0);
+        }
+        grid.set_mem_latency(arch.mem_latency);
+        grid.arch = arch.clone();
+        Ok(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pace_address_map() {
+        let map = Architecture::default().address_map();
+        assert_eq!(map.size(), 524288);
+        assert_eq!(map.dm_base(0), 0x01 << 17);
+        assert_eq!(map.dm_base(6), (0x01 << 17) | (1 << 16) | (2 << 14));
+        assert_eq!(map.pe_cm(63, 15), (63 << 10) | (15 << 4));
+        assert_eq!(map.agu_cm(9, 3), (9 << 10) | (0x01 << 8) | (3 << 4));
+        assert_eq!(map.agu_arf(2, 0), (2 << 10) | (0b10 << 8));
+        assert_eq!(map.agu_max_count(15), (15 << 10) | (0b11 << 8));
+    }
+
+    #[test]
+    fn test_address_map_regions_disjoint() {
+        // every CM entry, ARF entry and max count of the PE target has its own address
+        let map = Architecture::default().address_map();
+        let mut addresses = std::collections::HashSet::new();
+        for idx in 0..64 {
+            for entry in 0..16 {
+                assert!(addresses.insert(map.pe_cm(idx, entry)));
+                assert!(addresses.insert(map.agu_cm(idx, entry)));
+                assert!(addresses.insert(map.agu_arf(idx, entry)));
+            }
+            assert!(addresses.insert(map.agu_max_count(idx)));
+        }
+        assert!(addresses.iter().all(|addr| *addr < map.dm_base(0)));
+    }
+
+    #[test]
+    fn test_small_address_map() {
+        let arch: Architecture = toml::from_str("cols = 4\nrows = 4\ndms_per_edge = 2\n").unwrap();
+        assert_eq!(arch.dm_size, 8192);
+        arch.validate().unwrap();
+        assert_eq!(arch.topology().pes_per_dm, 2);
+        assert_eq!(arch.topology().num_dms(arch.shape()), 4);
+        let map = arch.address_map();
+        // 16 PEs in [13:10], 2 DMs per edge in [14], the edge in [15]
+        assert_eq!(map.pe_cm(15, 0), 15 << 10);
+        assert_eq!(map.dm_base(3), (0x01 << 16) | (1 << 15) | (1 << 14));
+        assert_eq!(map.size(), 1 << 18);
+    }
+
+    #[test]
+    fn test_invalid_architectures() {
+        let too_deep = Architecture {
+            cm_depth: 32,
+            ..Default::default()
+        };
+        assert!(too_deep.validate().unwrap_err().contains("cm_depth"));
+        let three_dms = Architecture {
+            dms_per_edge: 3,
+            ..Default::default()
+        };
+        assert!(
+            three_dms
+                .validate()
+                .unwrap_err()
+                .contains("shared by 1 or 2 PEs")
+        );
+        let corner = Architecture {
+            edges: vec![MemEdge::West, MemEdge::North],
+            ..Default::default()
+        };
+        assert!(corner.validate().unwrap_err().contains("corner"));
+        let no_latency = Architecture {
+            mem_latency: 0,
+            ..Default::default()
+        };
+        assert!(no_latency.validate().unwrap_err().contains("mem_latency"));
+        assert!(toml::from_str::<Architecture>("depth = 16").is_err());
+    }
+}
@@ -0,0 +1,407 @@
+//! Breakpoints and watchpoints on the state of a grid.
+//! The conditions are checked at the end of every cycle, see Grid::simulate_cycle_with_breakpoints
+
+use std::{fmt::Display, str::FromStr};
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::{digit1, hex_digit1, multispace0, multispace1},
+    combinator::{map_res, opt},
+    sequence::preceded,
+};
+
+use crate::isa::pe::PERegister;
+
+use super::grid::{Grid, PEIdx};
+
+/// A condition on which the simulation stops.
+/// Syntax (numbers are decimal or 0x hexadecimal):
+/// - `PE-Y1X0 pc 3`: the PE executes the configuration at PC 3
+/// - `PE-Y1X0 reg_res`: the register of the PE changes
+/// - `PE-Y1X0 reg_res == 0x10`: the register of the PE becomes equal to the value
+/// - `dm0 read 16` / `dm0 write 16`: the byte at address 16 of the DM is read / written by one of its ports
+/// - `agu2 count 4`: the count of the AGU becomes 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    Pc {
+        pe: PEIdx,
+        pc: usize,
+    },
+    RegChange {
+        pe: PEIdx,
+        reg: PERegister,
+    },
+    RegEquals {
+        pe: PEIdx,
+        reg: PERegister,
+        value: u64,
+    },
+    DmRead {
+        dm: usize,
+        addr: u64,
+    },
+    DmWrite {
+        dm: usize,
+        addr: u64,
+    },
+    AguCount {
+        agu: usize,
+        count: u32,
+    },
+}
+
+/// The breakpoint that stopped the simulation, and what was observed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopReason {
+    pub breakpoint: Breakpoint,
+    pub message: String,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "breakpoint `{}` hit: {}", self.breakpoint, self.message)
+    }
+}
+
+impl Breakpoint {
+    /// Check that the PE, DM or AGU of the breakpoint exists in the grid
+    pub fn validate(&self, grid: &Grid) -> Result<(), String> {
+        match *self {
+            Breakpoint::Pc { pe, .. }
+            | Breakpoint::RegChange { pe, .. }
+            | Breakpoint::RegEquals { pe, .. } => {
+                if pe.x >= grid.shape.x || pe.y >= grid.shape.y {
+                    return Err(format!(
+                        "PE (y={}, x={}) is out of the {} cols x {} rows grid",
+                        pe.y, pe.x, grid.shape.x, grid.shape.y
+                    ));
+                }
+            }
+            Breakpoint::DmRead { dm, .. } | Breakpoint::DmWrite { dm, .. } => {
+                if dm >= grid.dmems.len() {
+                    return Err(format!(
+                        "dm{} does not exist, the grid has {} data memories",
+                        dm,
+                        grid.dmems.len()
+                    ));
+                }
+            }
+            Breakpoint::AguCount { agu, .. } => {
+                if agu >= grid.agus.len() {
+                    return Err(format!(
+                        "agu{} does not exist, the grid has {} AGUs",
+                        agu,
+                        grid.agus.len()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The watched value before a cycle, None if the breakpoint does not depend on the previous state
+    pub fn sample(&self, grid: &Grid) -> Option<u64> {
+        match *self {
+            Breakpoint::RegChange { pe, reg } | Breakpoint::RegEquals { pe, reg, .. } => {
+                Some(grid.pes[pe.y][pe.x].regs.get(reg))
+            }
+            Breakpoint::AguCount { agu, .. } => Some(grid.agus[agu].count as u64),
+            _ => None,
+        }
+    }
+
+    /// Check the breakpoint after a cycle, given the value sampled before the cycle
+    pub fn check(&self, grid: &Grid, before: Option<u64>) -> Option<StopReason> {
+        let message = match *self {
+            Breakpoint::Pc { pe, pc } => {
+                (grid.pes[pe.y][pe.x].pc == pc).then(|| format!("PE executed PC {}", pc))
+            }
+            Breakpoint::RegChange { pe, reg } => {
+                let (old, new) = (before.unwrap(), grid.pes[pe.y][pe.x].regs.get(reg));
+                (old != new).then(|| format!("{} changed from 0x{:x} to 0x{:x}", reg, old, new))
+            }
+            Breakpoint::RegEquals { pe, reg, value } => {
+                let (old, new) = (before.unwrap(), grid.pes[pe.y][pe.x].regs.get(reg));
+                (old != value && new == value).then(|| format!("{} is now 0x{:x}", reg, new))
+            }
+            Breakpoint::DmRead { dm, addr } | Breakpoint::DmWrite { dm, addr } => {
+                let is_read = matches!(self, Breakpoint::DmRead { .. });
+                [1, 2].into_iter().find_map(|port| {
+                    let interface = grid.dmems[dm].port(port);
+                    let start = interface.wire_dmem_addr?;
+                    let accessed = if is_read {
+                        interface.mode.is_load()
+                    } else {
+                        interface.mode.is_store()
+                    };
+                    (accessed && (start..start + interface.mode.num_bytes()).contains(&addr))
+                        .then(|| format!("port {} {} at address {}", port, interface.mode, start))
+                })
+            }
+            Breakpoint::AguCount { agu, count } => {
+                let (old, new) = (before.unwrap(), grid.agus[agu].count);
+                (old != count as u64 && new == count).then(|| format!("count reached {}", count))
+            }
+        };
+        message.map(|message| StopReason {
+            breakpoint: *self,
+            message,
+        })
+    }
+
+    fn parse_number(s: &str) -> IResult<&str, u64> {
+        alt((
+            map_res(preceded(tag("0x"), hex_digit1), |h| {
+                u64::from_str_radix(h, 16)
+            }),
+            map_res(digit1, str::parse::<u64>),
+        ))
+        .parse(s)
+    }
+
+    fn parse_index<'a>(
+        prefix: &'static str,
+    ) -> impl Parser<&'a str, Output = usize, Error = nom::error::Error<&'a str>> {
+        preceded(tag(prefix), map_res(digit1, str::parse::<usize>))
+    }
+
+    fn parse_pe(s: &str) -> IResult<&str, Breakpoint> {
+        let (input, y) = Self::parse_index("PE-Y").parse(s)?;
+        let (input, x) = Self::parse_index("X").parse(input)?;
+        let pe = PEIdx { x, y };
+        let (input, _) = multispace1(input)?;
+        let pc: IResult<&str, usize> = preceded(
+            (tag("pc"), multispace1),
+            map_res(digit1, str::parse::<usize>),
+        )
+        .parse(input);
+        if let Ok((input, pc)) = pc {
+            return Ok((input, Breakpoint::Pc { pe, pc }));
+        }
+        let (input, reg) = map_res(
+            take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+            PERegister::from_str,
+        )
+        .parse(input)?;
+        let (input, value) = opt(preceded(
+            (multispace0, tag("=="), multispace0),
+            Self::parse_number,
+        ))
+        .parse(input)?;
+        let breakpoint = match value {
+            Some(value) => Breakpoint::RegEquals { pe, reg, value },
+            None => Breakpoint::RegChange { pe, reg },
+        };
+        Ok((input, breakpoint))
+    }
+
+    fn parse_dm(s: &str) -> IResult<&str, Breakpoint> {
+        let (input, dm) = Self::parse_index("dm").parse(s)?;
+        let (input, _) = multispace1(input)?;
+        let (input, access) = alt((tag("read"), tag("write"))).parse(input)?;
+        let (input, _) = multispace1(input)?;
+        let (input, addr) = Self::parse_number(input)?;
+        let breakpoint = match access {
+            "read" => Breakpoint::DmRead { dm, addr },
+            _ => Breakpoint::DmWrite { dm, addr },
+        };
+        Ok((input, breakpoint))
+    }
+
+    fn parse_agu(s: &str) -> IResult<&str, Breakpoint> {
+        let (input, agu) = Self::parse_index("agu").parse(s)?;
+        let (input, _) = (multispace1, tag("count"), multispace1).parse(input)?;
+        let (input, count) = map_res(Self::parse_number, u32::try_from).parse(input)?;
+        Ok((input, Breakpoint::AguCount { agu, count }))
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (input, breakpoint) = alt((Self::parse_pe, Self::parse_dm, Self::parse_agu))
+            .parse(s.trim())
+            .map_err(|_| format!("Invalid breakpoint: {}", s))?;
+        if !input.is_empty() {
+            return Err(format!("Invalid breakpoint: {}, unexpected `{}`", s, input));
+        }
+        Ok(breakpoint)
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Pc { pe, pc } => write!(f, "PE-Y{}X{} pc {}", pe.y, pe.x, pc),
+            Breakpoint::RegChange { pe, reg } => write!(f, "PE-Y{}X{} {}", pe.y, pe.x, reg),
+            Breakpoint::RegEquals { pe, reg, value } => {
+                write!(f, "PE-Y{}X{} {} == 0x{:x}", pe.y, pe.x, reg, value)
+            }
+            Breakpoint::DmRead { dm, addr } => write!(f, "dm{} read {}", dm, addr),
+            Breakpoint::DmWrite { dm, addr } => write!(f, "dm{} write {}", dm, addr),
+            Breakpoint::AguCount { agu, count } => write!(f, "agu{} count {}", agu, count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agu::agu::AGU,
+        isa::{configuration::Program, pe::PE},
+        sim::{dmem::DataMemory, grid::SimulationError, topology::MemoryTopology},
+    };
+
+    use super::*;
+
+    /// A single sided 2x2 grid: PE-Y0X0 loads dm0 from address 0 every other cycle,
+    /// PE-Y1X0 stores to dm0 from address 4 every cycle, PE-Y0X1 counts in reg_res
+    fn counter_grid() -> Grid {
+        // JUMP to a loop over the two given configurations
+        let program = |confs: [(&str, &str); 2]| {
+            let mut s =
+                String::from("operation: JUMP [1, 2]\nswitch_config: { Open -> predicate, };\n");
+            s.push_str("input_register_used: {};\ninput_register_write: {};\n");
+            for (operation, switch) in confs {
+                s.push_str(&format!(
+                    "operation: {}\nswitch_config: {{ {} }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+                    operation, switch
+                ));
+            }
+            Program::from_mnemonics(&s).unwrap()
+        };
+        let open = "Open -> predicate,";
+        let count = ("ADD! 1", "ALURes -> alu_op1,");
+        let pes = vec![
+            vec![
+                PE::new_mem_pe(program([("NOP?", open), ("NOP", open)])),
+                PE::new(program([count, count])),
+            ],
+            vec![
+                PE::new_mem_pe(program([("NOP?", open), ("NOP?", open)])),
+                PE::new(program([("NOP", open), ("NOP", open)])),
+            ],
+        ];
+        let agus = vec![
+            AGU::from_mnemonics("CM: LOAD,STRIDED,B16,1 ARF: 0 MAX COUNT: 100").unwrap(),
+            AGU::from_mnemonics("CM: STORE,STRIDED,B8,1 ARF: 4 MAX COUNT: 100").unwrap(),
+        ];
+        Grid::new(
+            PEIdx { x: 2, y: 2 },
+            pes,
+            vec![DataMemory::new(64)],
+            agus,
+            MemoryTopology::single_sided(),
+        )
+    }
+
+    /// Run until a breakpoint is hit, return the number of cycles run before the hit and the stop reason.
+    /// The grid is left ready to resume.
+    fn run_until(grid: &mut Grid, breakpoints: &[Breakpoint]) -> (usize, StopReason) {
+        for cycle in 0..20 {
+            let result = grid.simulate_cycle_with_breakpoints(breakpoints);
+            grid.next_cycle();
+            match result {
+                Ok(()) => {}
+                Err(SimulationError::Breakpoint(reason)) => return (cycle, reason),
+                Err(e) => panic!("Unexpected simulation error: {:?}", e),
+            }
+        }
+        panic!("No breakpoint hit");
+    }
+
+    #[test]
+    fn test_parse_breakpoints() {
+        let cases = [
+            (
+                "PE-Y1X0 pc 3",
+                Breakpoint::Pc {
+                    pe: PEIdx { x: 0, y: 1 },
+                    pc: 3,
+                },
+            ),
+            (
+                "PE-Y0X2 reg_north_in",
+                Breakpoint::RegChange {
+                    pe: PEIdx { x: 2, y: 0 },
+                    reg: PERegister::RegNorthIn,
+                },
+            ),
+            (
+                "PE-Y0X0 reg_res == 0x10",
+                Breakpoint::RegEquals {
+                    pe: PEIdx { x: 0, y: 0 },
+                    reg: PERegister::RegRes,
+                    value: 16,
+                },
+            ),
+            ("dm3 write 64", Breakpoint::DmWrite { dm: 3, addr: 64 }),
+            ("agu12 count 4", Breakpoint::AguCount { agu: 12, count: 4 }),
+        ];
+        for (s, expected) in cases {
+            let breakpoint: Breakpoint = s.parse().unwrap();
+            assert_eq!(breakpoint, expected);
+            assert_eq!(
+                breakpoint.to_string().parse::<Breakpoint>().unwrap(),
+                expected
+            );
+        }
+        assert!("PE-Y0X0 reg_foo".parse::<Breakpoint>().is_err());
+        assert!("dm0 read".parse::<Breakpoint>().is_err());
+        assert!("agu0 count 4 5".parse::<Breakpoint>().is_err());
+    }
+
+    #[test]
+    fn test_pe_breakpoints() {
+        let pe = PEIdx { x: 1, y: 0 };
+        let mut grid = counter_grid();
+        let (cycle, reason) = run_until(&mut grid, &[Breakpoint::Pc { pe, pc: 2 }]);
+        assert_eq!(cycle, 2);
+        assert_eq!(reason.breakpoint, Breakpoint::Pc { pe, pc: 2 });
+
+        let mut grid = counter_grid();
+        let reg = PERegister::RegRes;
+        let (cycle, reason) = run_until(&mut grid, &[Breakpoint::RegChange { pe, reg }]);
+        assert_eq!(cycle, 1);
+        assert_eq!(reason.message, "reg_res changed from 0x0 to 0x1");
+        // resume, the counter is 2 then 3
+        let (cycle, reason) = run_until(&mut grid, &[Breakpoint::RegEquals { pe, reg, value: 3 }]);
+        assert_eq!(cycle, 1);
+        assert_eq!(reason.message, "reg_res is now 0x3");
+        let far = Breakpoint::Pc {
+            pe: PEIdx { x: 2, y: 0 },
+            pc: 0,
+        };
+        assert!(far.validate(&grid).is_err());
+    }
+
+    #[test]
+    fn test_memory_breakpoints() {
+        let mut grid = counter_grid();
+        let read = Breakpoint::DmRead { dm: 0, addr: 3 };
+        let (cycle, reason) = run_until(&mut grid, &[read]);
+        // the counter PE loads addresses 0, 2, ... in cycles 1, 3, ...
+        assert_eq!(cycle, 3);
+        assert_eq!(reason.message, "port 1 Read16 at address 2");
+
+        let mut grid = counter_grid();
+        let (cycle, _) = run_until(&mut grid, &[read, Breakpoint::DmWrite { dm: 0, addr: 5 }]);
+        assert_eq!(cycle, 2);
+
+        let mut grid = counter_grid();
+        let (cycle, reason) = run_until(&mut grid, &[Breakpoint::AguCount { agu: 0, count: 2 }]);
+        assert_eq!(cycle, 3);
+        assert_eq!(
+            reason.to_string(),
+            "breakpoint `agu0 count 2` hit: count reached 2"
+        );
+        assert!(
+            Breakpoint::AguCount { agu: 2, count: 1 }
+                .validate(&grid)
+                .is_err()
+        );
+    }
+}
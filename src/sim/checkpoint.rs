@@ -0,0 +1,221 @@
+//! Machine-readable checkpoint of the runtime state of a grid, to resume a simulation.
+//! Unlike Grid::snapshot, a checkpoint can be loaded back: restoring it on a grid loaded from
+//! the same folder gives the same state, bit by bit, as when the checkpoint was taken.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agu::instruction::Instruction,
+    isa::{operation::Operation, pe::PERegisters},
+};
+
+use super::{
+    dmem::DataMemory,
+    grid::{Grid, PEIdx},
+    loader::{self, LoadError},
+    stats::Stats,
+};
+
+/// The runtime state of a PE, the configurations are not included
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PECheckpoint {
+    pub regs: PERegisters,
+    pub pc: usize,
+    pub previous_op: Option<Operation>,
+    pub agu_cm_pipeline: Vec<Option<Instruction>>,
+}
+
+/// The runtime state of an AGU, the CM and the max count are not included
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AGUCheckpoint {
+    pub pc: u32,
+    pub arf: Vec<u16>,
+    pub count: u32,
+}
+
+/// The runtime state of a grid between two cycles (after next_cycle).
+/// The data memories are complete: contents and port registers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The number of cycles simulated before the checkpoint
+    pub cycle: usize,
+    pub shape: PEIdx,
+    pub pes: Vec<Vec<PECheckpoint>>,
+    pub dmems: Vec<DataMemory>,
+    pub agus: Vec<AGUCheckpoint>,
+    /// The performance counters, a resumed simulation counts on from them
+    pub stats: Stats,
+}
+
+impl Checkpoint {
+    /// Save the checkpoint as JSON, the parent folders are created if needed
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    /// Load a checkpoint saved by Checkpoint::save
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let content = loader::read_file(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            // the position is reported by the LoadError itself
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or_default();
+            LoadError::Parse {
+                path: path.to_path_buf(),
+                line: e.line(),
+                column: e.column().max(1),
+                message: format!("invalid checkpoint: {}", message),
+            }
+        })
+    }
+}
+
+impl Grid {
+    /// Take a checkpoint of the grid between two cycles, i.e. after next_cycle().
+    /// The current cycle of the grid is saved, it is given back by restore()
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            cycle: self.cycle,
+            shape: self.shape,
+            pes: self
+                .pes
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|pe| PECheckpoint {
+                            regs: pe.regs,
+                            pc: pe.pc,
+                            previous_op: pe.previous_op,
+                            agu_cm_pipeline: pe.agu_cm_pipeline.clone(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            dmems: self.dmems.clone(),
+            agus: self
+                .agus
+                .iter()
+                .map(|agu| AGUCheckpoint {
+                    pc: agu.pc,
+                    arf: agu.arf.clone(),
+                    count: agu.count,
+                })
+                .collect(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Restore a checkpoint on a grid loaded from the same folder as the checkpointed one.
+    /// Return the number of cycles simulated before the checkpoint, the simulation resumes from there.
+    /// The grid is left untouched if the checkpoint does not fit it.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) -> Result<usize, String> {
+        if checkpoint.shape != self.shape {
+            return Err(format!(
+                "The checkpoint is of a {} cols x {} rows grid, not {} cols x {} rows",
+                checkpoint.shape.x, checkpoint.shape.y, self.shape.x, self.shape.y
+            ));
+        }
+        if checkpoint.pes.len() != self.shape.y
+            || checkpoint.pes.iter().any(|row| row.len() != self.shape.x)
+        {
+            return Err("The PEs of the checkpoint do not match its shape".to_string());
+        }
+        for (y, row) in checkpoint.pes.iter().enumerate() {
+            for (x, pe) in row.iter().enumerate() {
+                let configurations = self.pes[y][x].configurations.len();
+                if pe.pc >= configurations.max(1) {
+                    return Err(format!(
+                        "PE (y={}, x={}) has {} configurations, cannot restore PC {}",
+                        y, x, configurations, pe.pc
+                    ));
+                }
+                let latency = self.pes[y][x].agu_cm_pipeline.len();
+                if pe.agu_cm_pipeline.len() != latency {
+                    return Err(format!(
+                        "PE (y={}, x={}) has a memory latency of {} cycles in the checkpoint, {} in the grid",
+                        y,
+                        x,
+                        pe.agu_cm_pipeline.len(),
+                        latency
+                    ));
+                }
+            }
+        }
+        if checkpoint.dmems.len() != self.dmems.len() {
+            return Err(format!(
+                "The checkpoint has {} data memories, the grid has {}",
+                checkpoint.dmems.len(),
+                self.dmems.len()
+            ));
+        }
+        for (i, (saved, dmem)) in checkpoint.dmems.iter().zip(self.dmems.iter()).enumerate() {
+            if saved.capacity() != dmem.capacity() {
+                return Err(format!(
+                    "dm{} is of {} bytes in the checkpoint, {} bytes in the grid",
+                    i,
+                    saved.capacity(),
+                    dmem.capacity()
+                ));
+            }
+            if saved.port1.latency() != dmem.port1.latency()
+                || saved.port2.latency() != dmem.port2.latency()
+            {
+                return Err(format!(
+                    "dm{} has a latency of {} cycles in the checkpoint, {} in the grid",
+                    i,
+                    saved.port1.latency(),
+                    dmem.port1.latency()
+                ));
+            }
+        }
+        if checkpoint.agus.len() != self.agus.len() {
+            return Err(format!(
+                "The checkpoint has {} AGUs, the grid has {}",
+                checkpoint.agus.len(),
+                self.agus.len()
+            ));
+        }
+        for (i, (saved, agu)) in checkpoint.agus.iter().zip(self.agus.iter()).enumerate() {
+            if saved.arf.len() != agu.cm.len() || saved.pc as usize >= agu.cm.len().max(1) {
+                return Err(format!(
+                    "agu{} has {} instructions, cannot restore PC {} and {} addresses",
+                    i,
+                    agu.cm.len(),
+                    saved.pc,
+                    saved.arf.len()
+                ));
+            }
+        }
+        if checkpoint.stats.ports.len() != self.stats.ports.len()
+            || checkpoint.stats.agu_iterations.len() != self.stats.agu_iterations.len()
+        {
+            return Err("The statistics of the checkpoint do not match the grid".to_string());
+        }
+
+        for (row, saved_row) in self.pes.iter_mut().zip(checkpoint.pes.iter()) {
+            for (pe, saved) in row.iter_mut().zip(saved_row.iter()) {
+                pe.regs = saved.regs;
+                pe.pc = saved.pc;
+                pe.previous_op = saved.previous_op;
+                pe.agu_cm_pipeline = saved.agu_cm_pipeline.clone();
+                pe.signals = Default::default();
+            }
+        }
+        self.dmems = checkpoint.dmems.clone();
+        for (agu, saved) in self.agus.iter_mut().zip(checkpoint.agus.iter()) {
+            agu.pc = saved.pc;
+            agu.arf = saved.arf.clone();
+            agu.count = saved.count;
+        }
+        self.stats = checkpoint.stats.clone();
+        self.cycle = checkpoint.cycle;
+        Ok(checkpoint.cycle)
+    }
+}
@@ -1,10 +1,11 @@
 //! Model the data memory
 
+use serde::{Deserialize, Serialize};
 use strum_macros::Display;
 
-use crate::isa::value::SIMDValue;
+use crate::isa::value::LaneView;
 
-#[derive(Debug, Clone, Copy, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
 pub enum DMemMode {
     Read8,
     Read16,
@@ -25,6 +26,15 @@ impl DMemMode {
             DMemMode::Write8 | DMemMode::Write16 | DMemMode::Write64
         )
     }
+    /// The number of bytes accessed, 0 for NOP
+    pub fn num_bytes(&self) -> u64 {
+        match self {
+            DMemMode::Read8 | DMemMode::Write8 => 1,
+            DMemMode::Read16 | DMemMode::Write16 => 2,
+            DMemMode::Read64 | DMemMode::Write64 => 8,
+            DMemMode::NOP => 0,
+        }
+    }
 }
 
 impl Default for DMemMode {
@@ -33,51 +43,147 @@ impl Default for DMemMode {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+/// The read latency of the PACE 2.0 data memories, in cycles
+pub const DEFAULT_MEM_LATENCY: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DMemInterface {
     pub wire_dmem_addr: Option<u64>,
     pub wire_dmem_data: Option<u64>, // This wire is used to send the data to the dmem
-    pub reg_dmem_data: Option<u64>,  // This register captures the loaded data from dmem (1 cycle after LOAD)
-    pub reg_dmem_data_s: Option<u64>, // Shifted register: holds reg_dmem_data from previous cycle (2 cycles after LOAD)
+    /// The read pipeline, one register per cycle of latency:
+    /// reg_dmem_data[0] captures the loaded data from dmem (1 cycle after LOAD),
+    /// reg_dmem_data[i] holds reg_dmem_data[i - 1] from the previous cycle (i + 1 cycles after LOAD).
+    /// The PE receives the last one.
+    pub reg_dmem_data: Vec<Option<u64>>,
     pub mode: DMemMode,
 }
 
+impl Default for DMemInterface {
+    fn default() -> Self {
+        Self::with_latency(DEFAULT_MEM_LATENCY)
+    }
+}
+
+impl DMemInterface {
+    /// An idle interface whose loaded data reaches the PE `latency` cycles after the LOAD
+    pub fn with_latency(latency: usize) -> Self {
+        assert!(latency > 0, "The memory latency is at least 1 cycle");
+        Self {
+            wire_dmem_addr: None,
+            wire_dmem_data: None,
+            reg_dmem_data: vec![None; latency],
+            mode: DMemMode::NOP,
+        }
+    }
+
+    /// The read latency, in cycles
+    pub fn latency(&self) -> usize {
+        self.reg_dmem_data.len()
+    }
+
+    /// The data loaded `latency` cycles ago, received by the PE this cycle
+    pub fn loaded_data(&self) -> Option<u64> {
+        self.reg_dmem_data.last().copied().flatten()
+    }
+
+    /// Change the read latency, the pipeline is cleared
+    pub fn set_latency(&mut self, latency: usize) {
+        assert!(latency > 0, "The memory latency is at least 1 cycle");
+        self.reg_dmem_data = vec![None; latency];
+    }
+
+    /// The name of the i-th register of the read pipeline: reg_dmem_data, reg_dmem_data_s, reg_dmem_data_ss...
+    pub fn reg_dmem_data_name(i: usize) -> String {
+        if i == 0 {
+            "reg_dmem_data".to_string()
+        } else {
+            format!("reg_dmem_data_{}", "s".repeat(i))
+        }
+    }
+
+    /// Shift the read pipeline by one cycle, the first register keeps its value
+    fn shift(&mut self) {
+        for i in (1..self.reg_dmem_data.len()).rev() {
+            self.reg_dmem_data[i] = self.reg_dmem_data[i - 1];
+        }
+    }
+}
+
 impl std::fmt::Display for DMemInterface {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("wire_dmem_addr: {:?},\n", self.wire_dmem_addr))?;
+        f.write_str(&self.describe(LaneView::default()))
+    }
+}
+
+impl DMemInterface {
+    /// The port state, the data as hexadecimal and as lanes in the given view
+    pub fn describe(&self, view: LaneView) -> String {
+        let mut result = format!("wire_dmem_addr: {:?},\n", self.wire_dmem_addr);
         if let Some(v) = self.wire_dmem_data {
-            f.write_str(&format!(
-                "wire_dmem_data: 0x{:016x}|{:?},\n",
+            result.push_str(&format!(
+                "wire_dmem_data: 0x{:016x}|{},\n",
                 v,
-                SIMDValue::from(v)
-            ))?;
+                view.describe(v)
+            ));
         } else {
-            f.write_str("wire_dmem_data: None,\n")?;
+            result.push_str("wire_dmem_data: None,\n");
         }
-        if let Some(v) = self.reg_dmem_data {
-            f.write_str(&format!(
-                "reg_dmem_data: 0x{:016x}|{:?},\n",
-                v,
-                SIMDValue::from(v)
-            ))?;
-        } else {
-            f.write_str("reg_dmem_data: None,\n")?;
+        for (i, reg) in self.reg_dmem_data.iter().enumerate() {
+            let name = Self::reg_dmem_data_name(i);
+            if let Some(v) = reg {
+                result.push_str(&format!("{}: 0x{:016x}|{},\n", name, v, view.describe(*v)));
+            } else {
+                result.push_str(&format!("{}: None,\n", name));
+            }
         }
-        if let Some(v) = self.reg_dmem_data_s {
-            f.write_str(&format!(
-                "reg_dmem_data_s: 0x{:016x}|{:?},\n",
-                v,
-                SIMDValue::from(v)
-            ))?;
-        } else {
-            f.write_str("reg_dmem_data_s: None,\n")?;
+        result.push_str(&format!("mode: {}", self.mode));
+        result
+    }
+}
+
+/// What the data memories do with an access out of their range or not aligned to its width
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, clap::ValueEnum)]
+pub enum MemoryFaultPolicy {
+    /// Stop the simulation with a SimulationError::MemoryFault, the access is not performed
+    #[default]
+    Fault,
+    /// Wrap the address modulo the DM size (and align it to the width), as the SRAM decoder does
+    Wrap,
+    /// Log the fault and skip the access, a read gives 0
+    Log,
+}
+
+/// Why an access of a data memory is invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// The access does not fit in the DM of `capacity` bytes
+    OutOfRange { capacity: usize },
+    /// The address is not a multiple of the access width
+    Misaligned,
+}
+
+impl std::fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaultKind::OutOfRange { capacity } => {
+                write!(f, "out of the {} bytes of the data memory", capacity)
+            }
+            FaultKind::Misaligned => write!(f, "misaligned"),
         }
-        f.write_str(&format!("mode: {}", self.mode))?;
-        Ok(())
     }
 }
 
-#[derive(Default, Debug, Clone)]
+/// An invalid access of a port of a data memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortFault {
+    /// 1 or 2
+    pub port: usize,
+    pub addr: u64,
+    pub mode: DMemMode,
+    pub kind: FaultKind,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DataMemory {
     pub data: Vec<u8>,
     pub port1: DMemInterface,
@@ -93,6 +199,30 @@ impl DataMemory {
         }
     }
 
+    /// Get the interface of port 1 or port 2
+    pub fn port(&self, port: usize) -> &DMemInterface {
+        match port {
+            1 => &self.port1,
+            2 => &self.port2,
+            _ => panic!("Data memory has no port {}", port),
+        }
+    }
+
+    /// Get the mutable interface of port 1 or port 2
+    pub fn port_mut(&mut self, port: usize) -> &mut DMemInterface {
+        match port {
+            1 => &mut self.port1,
+            2 => &mut self.port2,
+            _ => panic!("Data memory has no port {}", port),
+        }
+    }
+
+    /// Change the read latency of both ports
+    pub fn set_latency(&mut self, latency: usize) {
+        self.port1.set_latency(latency);
+        self.port2.set_latency(latency);
+    }
+
     /// Load the data memory content from binary string.
     /// Each line is 8 bytes (64 bits). Within each byte, bits are MSB to LSB (leftmost is bit 7).
     /// Bytes are in little-endian order: the first 8 characters represent the least significant byte of the u64.
@@ -232,104 +362,142 @@ impl DataMemory {
             | (self.data[addr as usize + 7] as u64) << 56
     }
 
-    fn update_port(&mut self) {
-        // Shift register: reg_dmem_data_s always takes the previous reg_dmem_data value
-        // This implements the 2-cycle memory latency pipeline
-        self.port1.reg_dmem_data_s = self.port1.reg_dmem_data;
-        self.port2.reg_dmem_data_s = self.port2.reg_dmem_data;
-
-        match self.port1.mode {
-            DMemMode::Read8 => {
-                self.port1.reg_dmem_data = Some(self.read8(self.port1.wire_dmem_addr.unwrap()) as u64);
-            }
-            DMemMode::Read16 => {
-                self.port1.reg_dmem_data = Some(self.read16(self.port1.wire_dmem_addr.unwrap()) as u64);
-            }
-            DMemMode::Read64 => {
-                self.port1.reg_dmem_data = Some(self.read64(self.port1.wire_dmem_addr.unwrap()));
-            }
-            DMemMode::Write8 => {
-                self.write8(
-                    self.port1.wire_dmem_addr.unwrap(),
-                    self.port1.wire_dmem_data.unwrap() as u8,
-                );
-            }
-            DMemMode::Write16 => {
-                self.write16(
-                    self.port1.wire_dmem_addr.unwrap(),
-                    self.port1.wire_dmem_data.unwrap() as u16,
-                );
-            }
-            DMemMode::Write64 => {
-                self.write64(
-                    self.port1.wire_dmem_addr.unwrap(),
-                    self.port1.wire_dmem_data.unwrap() as u64,
-                );
-            }
-            DMemMode::NOP => {}
+    /// Check an access of `width` bytes at `addr`: inside the DM and aligned to its width
+    pub fn check_access(&self, addr: u64, width: u64) -> Result<(), FaultKind> {
+        if addr
+            .checked_add(width)
+            .is_none_or(|end| end > self.capacity() as u64)
+        {
+            Err(FaultKind::OutOfRange {
+                capacity: self.capacity(),
+            })
+        } else if !addr.is_multiple_of(width) {
+            Err(FaultKind::Misaligned)
+        } else {
+            Ok(())
         }
+    }
 
-        match self.port2.mode {
-            DMemMode::Read8 => {
-                self.port2.reg_dmem_data = Some(self.read8(self.port2.wire_dmem_addr.unwrap()) as u64);
-            }
-            DMemMode::Read16 => {
-                self.port2.reg_dmem_data = Some(self.read16(self.port2.wire_dmem_addr.unwrap()) as u64);
+    /// Perform the access set on the interface of the port, if any.
+    /// An invalid access is handled according to the policy and returned.
+    fn access(&mut self, port: usize, policy: MemoryFaultPolicy) -> Option<PortFault> {
+        let mode = self.port(port).mode;
+        if mode == DMemMode::NOP {
+            return None;
+        }
+        let addr = self
+            .port(port)
+            .wire_dmem_addr
+            .expect("The AGU sets the address of a memory access");
+        let width = mode.num_bytes();
+        let fault = self.check_access(addr, width).err().map(|kind| PortFault {
+            port,
+            addr,
+            mode,
+            kind,
+        });
+        let capacity = self.capacity() as u64;
+        let addr = match (fault, policy) {
+            (None, _) => addr,
+            // the address bits above the DM size and below the width are dropped
+            (Some(_), MemoryFaultPolicy::Wrap)
+                if capacity > 0 && capacity.is_multiple_of(width) =>
+            {
+                (addr % capacity) & !(width - 1)
             }
-            DMemMode::Read64 => {
-                self.port2.reg_dmem_data = Some(self.read64(self.port2.wire_dmem_addr.unwrap()));
+            (Some(_), MemoryFaultPolicy::Log) => {
+                if mode.is_load() {
+                    self.port_mut(port).reg_dmem_data[0] = Some(0);
+                }
+                return fault;
             }
+            _ => return fault,
+        };
+        let data = self.port(port).wire_dmem_data;
+        let data = || data.expect("The PE sets the data of a store");
+        let loaded = match mode {
+            DMemMode::Read8 => Some(self.read8(addr) as u64),
+            DMemMode::Read16 => Some(self.read16(addr) as u64),
+            DMemMode::Read64 => Some(self.read64(addr)),
             DMemMode::Write8 => {
-                self.write8(
-                    self.port2.wire_dmem_addr.unwrap(),
-                    self.port2.wire_dmem_data.unwrap() as u8,
-                );
+                self.write8(addr, data() as u8);
+                None
             }
             DMemMode::Write16 => {
-                self.write16(
-                    self.port2.wire_dmem_addr.unwrap(),
-                    self.port2.wire_dmem_data.unwrap() as u16,
-                );
+                self.write16(addr, data() as u16);
+                None
             }
             DMemMode::Write64 => {
-                self.write64(
-                    self.port2.wire_dmem_addr.unwrap(),
-                    self.port2.wire_dmem_data.unwrap() as u64,
-                );
+                self.write64(addr, data());
+                None
             }
-            DMemMode::NOP => {}
+            DMemMode::NOP => None,
+        };
+        if loaded.is_some() {
+            self.port_mut(port).reg_dmem_data[0] = loaded;
         }
+        fault
+    }
+
+    fn update_port(&mut self, policy: MemoryFaultPolicy) -> Vec<PortFault> {
+        // Shift registers: reg_dmem_data[i] always takes the previous reg_dmem_data[i - 1] value
+        // This implements the memory latency pipeline
+        self.port1.shift();
+        self.port2.shift();
+
+        [1, 2]
+            .into_iter()
+            .filter_map(|port| self.access(port, policy))
+            .collect()
     }
 
-    pub fn update_interface(&mut self) {
+    /// Perform the accesses of both ports, return the invalid ones (out of the DM or misaligned).
+    /// With MemoryFaultPolicy::Fault they are not performed, with Wrap they are performed at the
+    /// wrapped address, with Log they are skipped and a read gives 0.
+    pub fn update_interface(&mut self, policy: MemoryFaultPolicy) -> Vec<PortFault> {
         assert!(
             !(self.port1.mode.is_store()
                 && self.port2.mode.is_store()
                 && self.port1.wire_dmem_addr == self.port2.wire_dmem_addr),
             "Two ports of the data memory cannot be in store mode and have the same address"
         );
-        self.update_port();
+        self.update_port(policy)
     }
 
     pub fn dump(&self) -> String {
-        let mut result = String::new();
-        for (i, chunk) in self.data.chunks(32).enumerate() {
-            if i > 0 {
-                result.push('\n');
-            }
-            for (j, block) in chunk.chunks(8).enumerate() {
-                if j > 0 {
-                    result.push_str(" | ");
-                }
-                for (k, &byte) in block.iter().enumerate() {
-                    if k > 0 {
-                        result.push(' ');
-                    }
-                    result.push_str(&format!("{:02x}", byte));
-                }
-            }
-        }
-        result
+        self.data
+            .chunks(32)
+            .map(Self::dump_line)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Dump `len` bytes from `addr` in the format of dump(), every line prefixed by its address.
+    /// The range is clipped to the capacity.
+    pub fn dump_range(&self, addr: usize, len: usize) -> String {
+        let start = addr.min(self.data.len());
+        let end = addr.saturating_add(len).min(self.data.len());
+        self.data[start..end]
+            .chunks(32)
+            .enumerate()
+            .map(|(i, chunk)| format!("0x{:04x}: {}", start + i * 32, Self::dump_line(chunk)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// One line of dump: bytes in hex, grouped by 8
+    fn dump_line(bytes: &[u8]) -> String {
+        bytes
+            .chunks(8)
+            .map(|block| {
+                block
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join(" | ")
     }
 
     pub fn capacity(&self) -> usize {
@@ -396,4 +564,78 @@ mod tests {
             "0000000000000000000000000000000000000000000000000000000000001111\n"
         );
     }
+
+    #[test]
+    fn test_dmem_dump_range() {
+        use super::*;
+        let mut dmem = DataMemory::new(64);
+        dmem.write16(34, 0xbeef);
+        assert_eq!(dmem.dump_range(32, 4), "0x0020: 00 00 ef be");
+        // clipped to the capacity
+        assert_eq!(dmem.dump_range(60, 16), "0x003c: 00 00 00 00");
+        assert_eq!(dmem.dump().lines().count(), 2);
+        assert_eq!(
+            dmem.dump().lines().nth(1),
+            dmem.dump_range(32, 32).strip_prefix("0x0020: ")
+        );
+    }
+
+    #[test]
+    fn test_check_access() {
+        use super::*;
+        let mut dmem = DataMemory::new(16);
+        assert_eq!(dmem.check_access(8, 8), Ok(()));
+        assert_eq!(dmem.check_access(15, 1), Ok(()));
+        assert_eq!(
+            dmem.check_access(16, 1),
+            Err(FaultKind::OutOfRange { capacity: 16 })
+        );
+        assert_eq!(
+            dmem.check_access(u64::MAX, 2),
+            Err(FaultKind::OutOfRange { capacity: 16 })
+        );
+        assert_eq!(dmem.check_access(6, 8), Err(FaultKind::Misaligned));
+
+        // wrapped to 16 + 3 % 16, aligned to 2
+        dmem.port1.mode = DMemMode::Write16;
+        dmem.port1.wire_dmem_addr = Some(19);
+        dmem.port1.wire_dmem_data = Some(0xbeef);
+        let faults = dmem.update_interface(MemoryFaultPolicy::Wrap);
+        assert_eq!(faults.len(), 1);
+        assert_eq!(faults[0].kind, FaultKind::OutOfRange { capacity: 16 });
+        assert_eq!(dmem.read16(2), 0xbeef);
+        // not performed
+        dmem.port1.wire_dmem_data = Some(0x1234);
+        dmem.update_interface(MemoryFaultPolicy::Fault);
+        dmem.update_interface(MemoryFaultPolicy::Log);
+        assert_eq!(dmem.read16(2), 0xbeef);
+    }
+
+    #[test]
+    fn test_read_latency() {
+        use super::*;
+        for latency in 1..=4 {
+            let mut dmem = DataMemory::new(16);
+            dmem.write16(2, 0xbeef);
+            dmem.set_latency(latency);
+            dmem.port1.mode = DMemMode::Read16;
+            dmem.port1.wire_dmem_addr = Some(2);
+            dmem.update_interface(MemoryFaultPolicy::Fault);
+            dmem.port1.mode = DMemMode::NOP;
+            dmem.port1.wire_dmem_addr = None;
+            // the data reaches the last register latency - 1 cycles after the read
+            for _ in 1..latency {
+                assert_eq!(dmem.port1.loaded_data(), None);
+                dmem.update_interface(MemoryFaultPolicy::Fault);
+            }
+            assert_eq!(dmem.port1.loaded_data(), Some(0xbeef));
+            assert_eq!(dmem.port2.loaded_data(), None);
+        }
+        let port = DMemInterface::with_latency(3);
+        assert_eq!(
+            port.to_string(),
+            "wire_dmem_addr: None,\nwire_dmem_data: None,\nreg_dmem_data: None,\n\
+            reg_dmem_data_s: None,\nreg_dmem_data_ss: None,\nmode: NOP"
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use std::{fs::File, io::Write};
 
-use crate::sim::grid::DoubleSidedMemoryGrid;
+use crate::sim::grid::Grid;
 
 /// Dump the grid as a header file for IME system simulation
 pub trait DumpHeader {
@@ -8,10 +8,10 @@ pub trait DumpHeader {
     fn dump_header(&self, filename: &str);
 }
 
-impl DumpHeader for DoubleSidedMemoryGrid {
+impl DumpHeader for Grid {
     fn dump_header(&self, filename: &str) {
         let mut content = String::new();
-        assert_eq!(self.dmems.len(), 8, "Currently only support 8 dmems");
+        let address_map = self.arch.address_map();
         // dump the dmems, each variable is an array of u32
         for (dm_idx, dmem) in self.dmems.iter().enumerate() {
             // dump each data memory as an array in C
@@ -33,19 +33,7 @@ impl DumpHeader for DoubleSidedMemoryGrid {
             content.push_str("};\n\n");
 
             // dump the address map. First get the base address for the dm
-            // bits 18:17 = 01 for DMs
-            let mut base_addr: u32 = 0x01 << 17;
-
-            // bit 16 define left or right edge
-            if dm_idx < 4 {
-                base_addr |= 0x0 << 16;
-            } else {
-                base_addr |= 0x1 << 16;
-            }
-
-            // bit 15:14 define which DM within the 4 DMs of one edge
-            let dm_idx_within_edge = dm_idx % 4;
-            base_addr = base_addr | (dm_idx_within_edge as u32) << 14;
+            let base_addr = address_map.dm_base(dm_idx);
 
             let address_map_name = format!("uint32_t dmem{}_addr[{}] ", dm_idx, u32_vec.len());
             // increament from 0x0 to the length times 4, in hex
@@ -92,14 +80,7 @@ impl DumpHeader for DoubleSidedMemoryGrid {
 
             // dump the address map
             // calculate the base address for the PE CM
-            let mut base_addr: u32 = 0x00 << 17;
-
-            // bits 15:10 are the index of the PE
-            assert!(pe_idx < 64);
-            base_addr = base_addr | (pe_idx as u32) << 10;
-
-            // bits 9:8 = 00 for PE CM,
-            base_addr = base_addr | 0x00 << 8;
+            let base_addr = address_map.pe_cm(pe_idx, 0);
 
             let address_map_name = format!(
                 "uint32_t pe{}_cm_addr[{}]",
@@ -138,21 +119,7 @@ impl DumpHeader for DoubleSidedMemoryGrid {
 
             // dump the address map
             // calculate the base address for the AGU CM
-
-            // calculating the corresponding PE index from AGU index
-            let pe_y = agu_idx / 8;
-            let pe_x = agu_idx % 8;
-            let pe_idx = pe_y * 8 + pe_x;
-
-            // bits 18:17 = 00 for PE
-            let mut base_addr: u32 = 0x00 << 17;
-
-            // bits 15:10 for the index of the PE
-            assert!(pe_idx < 64);
-            base_addr = base_addr | (pe_idx as u32) << 10;
-
-            // bits 9:8 = 01 for AGU CM,
-            base_addr = base_addr | 0x01 << 8;
+            let base_addr = address_map.agu_cm(agu_idx, 0);
 
             let address_map_name = format!("uint32_t agu{}_cm_addr[{}]", agu_idx, agu.cm.len());
             // increament from 0x0 to the length times 4, in hex
@@ -186,20 +153,8 @@ impl DumpHeader for DoubleSidedMemoryGrid {
             content.push_str("};\n\n");
 
             // dump the address map
-            // calculate the corresponding PE index from AGU index
-            let pe_y = i / 8;
-            let pe_x = i % 8;
-            let pe_idx = pe_y * 8 + pe_x;
-
-            // bits 18:17 = 00 for PE
-            let mut base_addr: u32 = 0x00 << 17;
-
-            // bits 15:10 for the index of the PE
-            assert!(pe_idx < 64);
-            base_addr = base_addr | (pe_idx as u32) << 10;
-
-            // bits 9:8 = 10 for AGU ARF
-            base_addr = base_addr | 0x10 << 8;
+            // calculate the base address for the AGU ARF
+            let base_addr = address_map.agu_arf(i, 0);
 
             let address_map_name = format!("uint32_t agu{}_arf_addr[{}]", i, agu.arf.len());
             // increament from 0x0 to the length times 4, in hex
@@ -242,20 +197,7 @@ impl DumpHeader for DoubleSidedMemoryGrid {
             let b32 = b32_lower | loop_end_pc << 24;
             agu_max_count_data.push_str(&format!("\t0x{:08x},\n", b32));
             // dump the AGU max count address
-            // calculate the corresponding PE index from AGU index
-            let pe_y = agu_idx / 8;
-            let pe_x = agu_idx % 8;
-            let pe_idx = pe_y * 8 + pe_x;
-
-            // bits 18:17 = 00 for PE
-            let mut addr: u32 = 0x00 << 17;
-
-            // bits 15:10 for the index of the PE
-            assert!(pe_idx < 64);
-            addr = addr | (pe_idx as u32) << 10;
-
-            // bits 9:8 = 11 for AGU max count
-            addr = addr | 0x11 << 8;
+            let addr = address_map.agu_max_count(agu_idx);
 
             agu_max_count_addr.push_str(&format!("\t0x{:08x},\n", addr));
         }
@@ -0,0 +1,715 @@
+//! Fault injection: bit flips in the state of a grid, for soft-error studies.
+//! A campaign simulates the grid once without fault, then once per injection set, and compares
+//! the final DMs of every run with the golden ones (the fault-free run by default).
+//!
+//! The campaign description is TOML, or JSON by the extension:
+//! ```toml
+//! seed = 42                    # the random injections are reproducible
+//! runs = 1000                  # runs with random injections
+//! flips = 1                    # bits flipped in each random run
+//! targets = ["reg", "dm", "arf", "cm"]
+//! cycles = [0, 20]             # the cycles drawn from, the whole fault-free run by default
+//! max_cycles = 100             # by default twice the fault-free run, which must end by itself
+//! injections = ["12 PE-Y1X0 reg_res bit 5", "3 dm1 0x10 bit 0", "0 agu2 arf 1 bit 4", "5 PE-Y0X0 cm 2 bit 17"]
+//! ```
+//! A flip at cycle N happens before the cycle is simulated.
+
+use std::{collections::BTreeMap, fmt::Display, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use crate::isa::{binary::binary::BinaryIO, configuration::Configuration, pe::PERegister};
+
+use super::{
+    golden::{Comparison, ExpectedMemory, Golden},
+    grid::{Grid, PEIdx, SimulationError},
+    loader::{self, LoadError},
+};
+
+/// The kinds of state a flip can hit
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Display, EnumIter, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TargetKind {
+    /// A field of PERegisters
+    Reg,
+    /// A byte of a data memory
+    Dm,
+    /// An address register of an AGU
+    Arf,
+    /// The encoded configuration in the CM of a PE, decoded again after the flip
+    Cm,
+}
+
+/// The bit flipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultTarget {
+    Reg {
+        pe: PEIdx,
+        reg: PERegister,
+        bit: usize,
+    },
+    Dm {
+        dm: usize,
+        addr: usize,
+        bit: usize,
+    },
+    Arf {
+        agu: usize,
+        index: usize,
+        bit: usize,
+    },
+    Cm {
+        pe: PEIdx,
+        pc: usize,
+        bit: usize,
+    },
+}
+
+impl FaultTarget {
+    pub fn kind(&self) -> TargetKind {
+        match self {
+            FaultTarget::Reg { .. } => TargetKind::Reg,
+            FaultTarget::Dm { .. } => TargetKind::Dm,
+            FaultTarget::Arf { .. } => TargetKind::Arf,
+            FaultTarget::Cm { .. } => TargetKind::Cm,
+        }
+    }
+
+    /// Check that the bit exists in the grid
+    pub fn validate(&self, grid: &Grid) -> Result<(), String> {
+        let pe_exists = |pe: PEIdx| pe.x < grid.shape.x && pe.y < grid.shape.y;
+        let (exists, width) = match *self {
+            FaultTarget::Reg { pe, reg, bit } => (pe_exists(pe), (bit, reg.width())),
+            FaultTarget::Dm { dm, addr, bit } => (
+                grid.dmems
+                    .get(dm)
+                    .is_some_and(|dmem| addr < dmem.capacity()),
+                (bit, 8),
+            ),
+            FaultTarget::Arf { agu, index, bit } => (
+                grid.agus.get(agu).is_some_and(|agu| index < agu.arf.len()),
+                (bit, 16),
+            ),
+            FaultTarget::Cm { pe, pc, bit } => (
+                pe_exists(pe) && pc < grid.pes[pe.y][pe.x].configurations.len(),
+                (bit, 64),
+            ),
+        };
+        if !exists {
+            return Err(format!("`{}` is not in the grid", self));
+        }
+        if width.0 >= width.1 {
+            return Err(format!(
+                "`{}`: the bit is out of the {} bits",
+                self, width.1
+            ));
+        }
+        Ok(())
+    }
+
+    /// Flip the bit in the grid. A flipped configuration which does not decode is an error.
+    pub fn flip(&self, grid: &mut Grid) -> Result<(), String> {
+        match *self {
+            FaultTarget::Reg { pe, reg, bit } => {
+                let regs = &mut grid.pes[pe.y][pe.x].regs;
+                regs.set(reg, regs.get(reg) ^ (1 << bit));
+            }
+            FaultTarget::Dm { dm, addr, bit } => grid.dmems[dm].data[addr] ^= 1 << bit,
+            FaultTarget::Arf { agu, index, bit } => grid.agus[agu].arf[index] ^= 1 << bit,
+            FaultTarget::Cm { pe, pc, bit } => {
+                let code = grid.pes[pe.y][pe.x].configurations[pc].to_u64() ^ (1 << bit);
+                let configuration = Configuration::from_binary(&code.to_binary()).map_err(|e| {
+                    format!(
+                        "the flipped configuration 0x{:016x} is invalid: {}",
+                        code, e
+                    )
+                })?;
+                grid.set_configuration(pe, pc, configuration);
+            }
+        }
+        Ok(())
+    }
+
+    /// A random bit of the kind in the grid, None if the grid has no such state
+    fn random(kind: TargetKind, grid: &Grid, rng: &mut SplitMix64) -> Option<Self> {
+        let pes: Vec<PEIdx> = (0..grid.shape.y)
+            .flat_map(|y| (0..grid.shape.x).map(move |x| PEIdx { x, y }))
+            .collect();
+        match kind {
+            TargetKind::Reg => {
+                let pe = *rng.choose(&pes)?;
+                let reg = *rng.choose(&PERegister::iter().collect::<Vec<_>>())?;
+                let bit = rng.below(reg.width());
+                Some(FaultTarget::Reg { pe, reg, bit })
+            }
+            TargetKind::Dm => {
+                let dms: Vec<usize> = (0..grid.dmems.len())
+                    .filter(|dm| grid.dmems[*dm].capacity() > 0)
+                    .collect();
+                let dm = *rng.choose(&dms)?;
+                let addr = rng.below(grid.dmems[dm].capacity());
+                Some(FaultTarget::Dm {
+                    dm,
+                    addr,
+                    bit: rng.below(8),
+                })
+            }
+            TargetKind::Arf => {
+                let agus: Vec<usize> = (0..grid.agus.len())
+                    .filter(|agu| !grid.agus[*agu].arf.is_empty())
+                    .collect();
+                let agu = *rng.choose(&agus)?;
+                let index = rng.below(grid.agus[agu].arf.len());
+                Some(FaultTarget::Arf {
+                    agu,
+                    index,
+                    bit: rng.below(16),
+                })
+            }
+            TargetKind::Cm => {
+                let pes: Vec<PEIdx> = pes
+                    .into_iter()
+                    .filter(|pe| grid.pes[pe.y][pe.x].is_initialized())
+                    .collect();
+                let pe = *rng.choose(&pes)?;
+                let pc = rng.below(grid.pes[pe.y][pe.x].configurations.len());
+                Some(FaultTarget::Cm {
+                    pe,
+                    pc,
+                    bit: rng.below(64),
+                })
+            }
+        }
+    }
+}
+
+impl Display for FaultTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaultTarget::Reg { pe, reg, bit } => {
+                write!(f, "PE-Y{}X{} {} bit {}", pe.y, pe.x, reg, bit)
+            }
+            FaultTarget::Dm { dm, addr, bit } => write!(f, "dm{} 0x{:x} bit {}", dm, addr, bit),
+            FaultTarget::Arf { agu, index, bit } => {
+                write!(f, "agu{} arf {} bit {}", agu, index, bit)
+            }
+            FaultTarget::Cm { pe, pc, bit } => {
+                write!(f, "PE-Y{}X{} cm {} bit {}", pe.y, pe.x, pc, bit)
+            }
+        }
+    }
+}
+
+/// A bit flip at the start of a cycle.
+/// Syntax (numbers are decimal or 0x hexadecimal), the cycle first:
+/// - `12 PE-Y1X0 reg_res bit 5`: a bit of a register of the PE
+/// - `12 dm1 0x10 bit 3`: a bit of the byte at the address of the DM
+/// - `12 agu2 arf 1 bit 4`: a bit of the address register of the AGU
+/// - `12 PE-Y1X0 cm 3 bit 17`: a bit of the encoded configuration at the PC of the PE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Injection {
+    pub cycle: usize,
+    pub target: FaultTarget,
+}
+
+impl Injection {
+    fn parse_number(s: &str) -> Result<usize, String> {
+        match s.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16),
+            None => s.parse::<usize>(),
+        }
+        .map_err(|_| format!("Invalid number `{}`", s))
+    }
+
+    fn parse_pe(s: &str) -> Option<PEIdx> {
+        let (y, x) = s.strip_prefix("PE-Y")?.split_once('X')?;
+        Some(PEIdx {
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+        })
+    }
+}
+
+impl FromStr for Injection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid injection: {}, expecting e.g. 12 PE-Y1X0 reg_res bit 5",
+                s
+            )
+        };
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [cycle, location @ .., "bit", bit] = &fields[..] else {
+            return Err(invalid());
+        };
+        let (cycle, bit) = (Self::parse_number(cycle)?, Self::parse_number(bit)?);
+        let index =
+            |prefix: &str, s: &str| s.strip_prefix(prefix).and_then(|i| i.parse::<usize>().ok());
+        let target = match *location {
+            [pe, "cm", pc] => FaultTarget::Cm {
+                pe: Self::parse_pe(pe).ok_or_else(invalid)?,
+                pc: Self::parse_number(pc)?,
+                bit,
+            },
+            [pe, reg] if pe.starts_with("PE-") => FaultTarget::Reg {
+                pe: Self::parse_pe(pe).ok_or_else(invalid)?,
+                reg: PERegister::from_str(reg)
+                    .map_err(|_| format!("Unknown register `{}`", reg))?,
+                bit,
+            },
+            [agu, "arf", i] => FaultTarget::Arf {
+                agu: index("agu", agu).ok_or_else(invalid)?,
+                index: Self::parse_number(i)?,
+                bit,
+            },
+            [dm, addr] => FaultTarget::Dm {
+                dm: index("dm", dm).ok_or_else(invalid)?,
+                addr: Self::parse_number(addr)?,
+                bit,
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(Injection { cycle, target })
+    }
+}
+
+impl TryFrom<String> for Injection {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Injection> for String {
+    fn from(injection: Injection) -> Self {
+        injection.to_string()
+    }
+}
+
+impl Display for Injection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.cycle, self.target)
+    }
+}
+
+/// The description of a campaign, see the module documentation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Campaign {
+    /// The seed of the random injections
+    pub seed: u64,
+    /// The number of runs with random injections
+    pub runs: usize,
+    /// The number of bits flipped in each random run
+    pub flips: usize,
+    /// What the random flips hit, drawn with equal weights
+    pub targets: Vec<TargetKind>,
+    /// The first and last cycles of the random flips, the whole fault-free run by default
+    pub cycles: Option<[usize; 2]>,
+    /// The maximum number of cycles of a run. By default the fault-free run must end by itself,
+    /// and a run longer than twice it has hung.
+    pub max_cycles: Option<usize>,
+    /// The injections given explicitly, each is a run of its own
+    pub injections: Vec<Injection>,
+}
+
+impl Default for Campaign {
+    fn default() -> Self {
+        Campaign {
+            seed: 0,
+            runs: 0,
+            flips: 1,
+            targets: TargetKind::iter().collect(),
+            cycles: None,
+            max_cycles: None,
+            injections: Vec::new(),
+        }
+    }
+}
+
+/// What a fault did to the final DMs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The DMs are the golden ones
+    Masked,
+    /// Silent data corruption: the run ended normally with other DMs
+    Corrupted(Comparison),
+    /// The simulation failed, or did not end
+    Crashed(String),
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Masked => write!(f, "masked"),
+            Outcome::Corrupted(comparison) => write!(
+                f,
+                "corrupted ({} mismatching words)",
+                comparison.mismatches.len()
+            ),
+            Outcome::Crashed(reason) => write!(f, "crashed ({})", reason),
+        }
+    }
+}
+
+/// One run of a campaign
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunResult {
+    pub injections: Vec<Injection>,
+    /// The number of simulated cycles
+    pub cycles: usize,
+    pub outcome: Outcome,
+}
+
+impl Display for RunResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let injections: Vec<String> = self.injections.iter().map(|i| i.to_string()).collect();
+        write!(
+            f,
+            "[{}]: {} after {} cycles",
+            injections.join(", "),
+            self.outcome,
+            self.cycles
+        )
+    }
+}
+
+/// The results of a campaign
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CampaignReport {
+    /// The number of cycles of the fault-free run
+    pub golden_cycles: usize,
+    pub runs: Vec<RunResult>,
+}
+
+impl CampaignReport {
+    /// The numbers of masked, corrupted and crashed runs
+    pub fn counts(&self) -> [usize; 3] {
+        Self::count(self.runs.iter())
+    }
+
+    fn count<'a>(runs: impl Iterator<Item = &'a RunResult>) -> [usize; 3] {
+        let mut counts = [0; 3];
+        for run in runs {
+            counts[match run.outcome {
+                Outcome::Masked => 0,
+                Outcome::Corrupted(_) => 1,
+                Outcome::Crashed(_) => 2,
+            }] += 1;
+        }
+        counts
+    }
+}
+
+impl Display for CampaignReport {
+    /// The outcomes of all the runs, then by kind of the first flip of the run
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = |f: &mut std::fmt::Formatter<'_>, name: &str, counts: [usize; 3]| {
+            let total = counts.iter().sum::<usize>();
+            let percent = |n: usize| 100.0 * n as f64 / total.max(1) as f64;
+            writeln!(
+                f,
+                "{}: {} runs, {} masked ({:.1}%), {} corrupted ({:.1}%), {} crashed ({:.1}%)",
+                name,
+                total,
+                counts[0],
+                percent(counts[0]),
+                counts[1],
+                percent(counts[1]),
+                counts[2],
+                percent(counts[2])
+            )
+        };
+        writeln!(f, "Fault-free run: {} cycles", self.golden_cycles)?;
+        line(f, "All", self.counts())?;
+        let mut by_kind: BTreeMap<TargetKind, Vec<&RunResult>> = BTreeMap::new();
+        for run in self.runs.iter() {
+            if let Some(injection) = run.injections.first() {
+                by_kind
+                    .entry(injection.target.kind())
+                    .or_default()
+                    .push(run);
+            }
+        }
+        for (kind, runs) in by_kind {
+            line(f, &kind.to_string(), Self::count(runs.into_iter()))?;
+        }
+        Ok(())
+    }
+}
+
+/// A small seeded generator (SplitMix64), the campaigns must be reproducible
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in 0..n, n > 0
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.below(items.len())])
+    }
+}
+
+/// How a run of the grid ended
+enum RunEnd {
+    /// The AGUs ended
+    Ended(usize),
+    /// The maximum number of cycles was reached
+    Stopped(usize),
+    Failed(usize, String),
+}
+
+/// The maximum number of cycles of the fault-free run without max_cycles
+const FAULT_FREE_MAX_CYCLES: usize = 1_000_000;
+
+impl Campaign {
+    /// Load a campaign, TOML or JSON (by the extension)
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let content = loader::read_file(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|e| {
+                let message = e.to_string();
+                let message = message.split(" at line ").next().unwrap_or_default();
+                LoadError::Parse {
+                    path: path.to_path_buf(),
+                    line: e.line(),
+                    column: e.column().max(1),
+                    message: format!("invalid campaign: {}", message),
+                }
+            })
+        } else {
+            toml::from_str(&content).map_err(|e| {
+                let offset = e.span().map_or(0, |span| span.start);
+                let message = format!("invalid campaign: {}", e.message());
+                LoadError::parse(path, &content, offset, message)
+            })
+        }
+    }
+
+    /// Check the campaign against the grid
+    pub fn validate(&self, grid: &Grid) -> Result<(), String> {
+        if self.runs > 0 && (self.flips == 0 || self.targets.is_empty()) {
+            return Err("random runs need flips > 0 and at least one target".to_string());
+        }
+        if let Some([first, last]) = self.cycles
+            && first > last
+        {
+            return Err(format!("cycles [{}, {}] is empty", first, last));
+        }
+        for injection in self.injections.iter() {
+            injection.target.validate(grid)?;
+        }
+        Ok(())
+    }
+
+    /// Simulate the grid with the flips until the AGUs end or max_cycles
+    fn simulate(grid: &mut Grid, injections: &[Injection], max_cycles: usize) -> RunEnd {
+        while grid.cycle < max_cycles {
+            let cycle = grid.cycle;
+            for injection in injections.iter().filter(|i| i.cycle == cycle) {
+                if let Err(e) = injection.target.flip(grid) {
+                    return RunEnd::Failed(grid.cycle, e);
+                }
+            }
+            match grid.simulate_cycle() {
+                Ok(()) => {}
+                Err(SimulationError::SimulationEnd) => return RunEnd::Ended(grid.cycle),
+                Err(e) => return RunEnd::Failed(grid.cycle, Self::describe(&e)),
+            }
+            grid.next_cycle();
+        }
+        RunEnd::Stopped(grid.cycle)
+    }
+
+    fn describe(e: &SimulationError) -> String {
+        match e {
+            SimulationError::PEUpdateError(pe, e) => format!("PE-Y{}X{}: {}", pe.y, pe.x, e),
+            SimulationError::SimulationEnd => "end of the simulation".to_string(),
+            SimulationError::Breakpoint(reason) => reason.to_string(),
+            SimulationError::CombinationalLoop(router_loop) => {
+                format!("combinational loop in the router: {}", router_loop)
+            }
+            SimulationError::MemoryFault(fault) => format!("memory fault at {}", fault),
+        }
+    }
+
+    /// Run the campaign on a grid ready to simulate. Without golden DMs, the final DMs of the
+    /// fault-free run are expected. Fails if the fault-free run fails.
+    /// The panics of the faulty runs are caught, the panic hook still reports them.
+    pub fn run(&self, grid: &Grid, golden: Option<&Golden>) -> Result<CampaignReport, String> {
+        self.validate(grid)?;
+        let mut fault_free = grid.clone();
+        let max_cycles = self.max_cycles.unwrap_or(FAULT_FREE_MAX_CYCLES);
+        let (golden_cycles, ended) = match Self::simulate(&mut fault_free, &[], max_cycles) {
+            RunEnd::Ended(cycles) => (cycles, true),
+            RunEnd::Stopped(cycles) if self.max_cycles.is_some() => (cycles, false),
+            RunEnd::Stopped(cycles) => {
+                return Err(format!(
+                    "the fault-free run did not end after {} cycles, set max_cycles",
+                    cycles
+                ));
+            }
+            RunEnd::Failed(cycle, e) => {
+                return Err(format!(
+                    "the fault-free run failed at cycle {}: {}",
+                    cycle, e
+                ));
+            }
+        };
+        let max_cycles = self.max_cycles.unwrap_or(2 * golden_cycles);
+        let golden = match golden {
+            Some(golden) => golden.clone(),
+            None => {
+                let mut golden = Golden::default();
+                for (dm, dmem) in fault_free.dmems.iter().enumerate() {
+                    golden.insert(dm, ExpectedMemory::from(dmem));
+                }
+                golden
+            }
+        };
+        let reference = golden.compare(&fault_free.dmems)?;
+        if !reference.is_match() {
+            log::warn!(
+                "The fault-free run does not give the golden DMs:\n{}",
+                reference
+            );
+        }
+
+        let mut rng = SplitMix64(self.seed);
+        let [first, last] = self.cycles.unwrap_or([0, golden_cycles.saturating_sub(1)]);
+        let mut injection_sets: Vec<Vec<Injection>> = self
+            .injections
+            .iter()
+            .map(|injection| vec![*injection])
+            .collect();
+        for _ in 0..self.runs {
+            let mut injections = Vec::new();
+            for _ in 0..self.flips {
+                let kind = *rng.choose(&self.targets).unwrap();
+                let cycle = first + rng.below(last - first + 1);
+                if let Some(target) = FaultTarget::random(kind, grid, &mut rng) {
+                    injections.push(Injection { cycle, target });
+                }
+            }
+            injection_sets.push(injections);
+        }
+
+        let mut report = CampaignReport {
+            golden_cycles,
+            runs: Vec::new(),
+        };
+        for injections in injection_sets {
+            let mut faulty = grid.clone();
+            // a corrupted state can break the assumptions of the simulator, it is a crash too
+            let end = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                Self::simulate(&mut faulty, &injections, max_cycles)
+            }))
+            .unwrap_or_else(|panic| {
+                let message = panic
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                RunEnd::Failed(faulty.cycle, format!("panic: {}", message))
+            });
+            let (cycles, outcome) = match end {
+                RunEnd::Failed(cycle, e) => (cycle, Outcome::Crashed(e)),
+                RunEnd::Stopped(cycles) if ended => (
+                    cycles,
+                    Outcome::Crashed(format!("did not end after {} cycles", cycles)),
+                ),
+                RunEnd::Ended(cycles) | RunEnd::Stopped(cycles) => {
+                    let comparison = golden.compare(&faulty.dmems)?;
+                    match comparison.is_match() {
+                        true => (cycles, Outcome::Masked),
+                        false => (cycles, Outcome::Corrupted(comparison)),
+                    }
+                }
+            };
+            let run = RunResult {
+                injections,
+                cycles,
+                outcome,
+            };
+            log::debug!("{}", run);
+            report.runs.push(run);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_injection() {
+        let pe = PEIdx { x: 0, y: 1 };
+        for (s, target) in [
+            (
+                "12 PE-Y1X0 reg_res bit 5",
+                FaultTarget::Reg {
+                    pe,
+                    reg: PERegister::RegRes,
+                    bit: 5,
+                },
+            ),
+            (
+                "12 dm1 0x10 bit 3",
+                FaultTarget::Dm {
+                    dm: 1,
+                    addr: 16,
+                    bit: 3,
+                },
+            ),
+            (
+                "12 agu2 arf 1 bit 4",
+                FaultTarget::Arf {
+                    agu: 2,
+                    index: 1,
+                    bit: 4,
+                },
+            ),
+            (
+                "12 PE-Y1X0 cm 3 bit 17",
+                FaultTarget::Cm { pe, pc: 3, bit: 17 },
+            ),
+        ] {
+            let injection = s.parse::<Injection>().unwrap();
+            assert_eq!(injection, Injection { cycle: 12, target });
+            assert_eq!(injection.to_string(), s);
+        }
+        assert!("12 PE-Y1X0 reg_foo bit 5".parse::<Injection>().is_err());
+        assert!("12 dm1 0x10".parse::<Injection>().is_err());
+        assert!("12 agu2 cm 1 bit 4".parse::<Injection>().is_err());
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut a = SplitMix64(7);
+        let mut b = SplitMix64(7);
+        let draws: Vec<usize> = (0..100).map(|_| a.below(10)).collect();
+        assert_eq!(draws, (0..100).map(|_| b.below(10)).collect::<Vec<_>>());
+        assert!(draws.iter().all(|d| *d < 10));
+        assert!((0..10).all(|n| draws.contains(&n)));
+    }
+}
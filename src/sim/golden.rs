@@ -0,0 +1,437 @@
+//! Compare the final contents of the data memories against expected (golden) ones.
+//! A golden folder holds one file per checked DM, named dmN as in a grid folder and in the same
+//! format, where a bit can also be `x` (don't care). Only the bytes given in a file are compared,
+//! the DMs without a file are not.
+
+use std::{collections::BTreeMap, fmt::Display, ops::Range, path::Path, str::FromStr};
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{digit1, hex_digit1},
+    combinator::map_res,
+    sequence::preceded,
+};
+
+use crate::isa::value::SIMDValue;
+
+use super::{
+    dmem::DataMemory,
+    loader::{self, LoadError},
+};
+
+/// The expected content of a DM, from its first byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedMemory {
+    pub data: Vec<u8>,
+    /// The bits to compare, 0 for the don't-care bits
+    pub mask: Vec<u8>,
+}
+
+impl ExpectedMemory {
+    /// Load an expected DM: 64 bits per line like a DM file, with `x` for the don't-care bits
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let content = loader::read_file(path)?;
+        let mut expected = ExpectedMemory {
+            data: Vec::new(),
+            mask: Vec::new(),
+        };
+        let mut line_offset = 0;
+        for (line_idx, line) in content.split('\n').enumerate() {
+            let bits: Vec<(usize, char)> = line
+                .char_indices()
+                .filter(|(_, c)| *c != ' ' && *c != '\r')
+                .collect();
+            if let Some((column, c)) = bits.iter().find(|(_, c)| !"01xX".contains(*c)) {
+                return Err(LoadError::Parse {
+                    path: path.to_path_buf(),
+                    line: line_idx + 1,
+                    column: column + 1,
+                    message: format!("Unexpected character '{}' in expected binary string", c),
+                });
+            }
+            // a trailing line break is allowed
+            let is_last_line = line_offset + line.len() == content.len();
+            if bits.len() != 64 && !(is_last_line && bits.is_empty()) {
+                return Err(LoadError::Parse {
+                    path: path.to_path_buf(),
+                    line: line_idx + 1,
+                    column: line.len() + 1,
+                    message: format!(
+                        "Expected a 64-character string, but got length {}",
+                        bits.len()
+                    ),
+                });
+            }
+            // 8 bytes in little-endian order, each from MSB to LSB
+            for byte_bits in bits.chunks(8) {
+                let (mut byte, mut mask) = (0u8, 0u8);
+                for (_, c) in byte_bits {
+                    byte <<= 1;
+                    mask <<= 1;
+                    if *c != 'x' && *c != 'X' {
+                        byte |= (*c == '1') as u8;
+                        mask |= 1;
+                    }
+                }
+                expected.data.push(byte);
+                expected.mask.push(mask);
+            }
+            line_offset += line.len() + 1;
+        }
+        Ok(expected)
+    }
+}
+
+impl From<&DataMemory> for ExpectedMemory {
+    /// Expect exactly the content of a DM
+    fn from(dmem: &DataMemory) -> Self {
+        ExpectedMemory {
+            data: dmem.data.clone(),
+            mask: vec![0xff; dmem.data.len()],
+        }
+    }
+}
+
+/// A byte range of a DM, the end excluded.
+/// Syntax: `dm1:0x10..0x20`, the bounds in decimal or 0x hexadecimal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressRange {
+    pub dm: usize,
+    pub range: Range<usize>,
+}
+
+impl AddressRange {
+    fn parse_number(s: &str) -> IResult<&str, usize> {
+        alt((
+            map_res(preceded(tag("0x"), hex_digit1), |h| {
+                usize::from_str_radix(h, 16)
+            }),
+            map_res(digit1, str::parse::<usize>),
+        ))
+        .parse(s)
+    }
+
+    fn parse_range(s: &str) -> IResult<&str, AddressRange> {
+        let (input, dm) = preceded(tag("dm"), map_res(digit1, str::parse::<usize>)).parse(s)?;
+        let (input, start) = preceded(tag(":"), Self::parse_number).parse(input)?;
+        let (input, end) = preceded(tag(".."), Self::parse_number).parse(input)?;
+        Ok((
+            input,
+            AddressRange {
+                dm,
+                range: start..end,
+            },
+        ))
+    }
+}
+
+impl FromStr for AddressRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::parse_range(s.trim()) {
+            Ok(("", range)) if range.range.start < range.range.end => Ok(range),
+            _ => Err(format!(
+                "Invalid address range: {}, expecting e.g. dm1:0x0..0x20",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for AddressRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "dm{}:{:#x}..{:#x}",
+            self.dm, self.range.start, self.range.end
+        )
+    }
+}
+
+/// The expected DMs of a grid
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Golden {
+    /// The expected DMs by index
+    pub dms: BTreeMap<usize, ExpectedMemory>,
+    /// Only these ranges of the DMs are compared, all the expected bytes of a DM without range
+    pub ranges: Vec<AddressRange>,
+}
+
+impl Golden {
+    /// Load the dmN files of a folder, all the problems found are reported at once
+    pub fn load(folder: &Path) -> Result<Self, LoadError> {
+        let entries = std::fs::read_dir(folder).map_err(|e| LoadError::Io {
+            path: folder.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let mut golden = Golden::default();
+        let mut errors = Vec::new();
+        for name in entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+        {
+            if let Some(dm) = loader::parse_indexed_filename(&name, "dm") {
+                match ExpectedMemory::load(&folder.join(&name)) {
+                    Ok(expected) => golden.insert(dm, expected),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        if errors.is_empty() && golden.dms.is_empty() {
+            errors.push(LoadError::CountMismatch {
+                path: folder.to_path_buf(),
+                what: "expected data memories".to_string(),
+                expected: 1,
+                found: 0,
+            });
+        }
+        LoadError::from_errors(errors)?;
+        Ok(golden)
+    }
+
+    pub fn insert(&mut self, dm: usize, expected: ExpectedMemory) {
+        self.dms.insert(dm, expected);
+    }
+
+    /// Only compare the range of the DM, in addition to the other ranges of the DM
+    pub fn restrict(&mut self, range: AddressRange) {
+        self.ranges.push(range);
+    }
+
+    /// Compare the DMs of a grid, word by word (64 bits).
+    /// Fails if an expected DM or a range does not fit in the DMs.
+    pub fn compare(&self, dmems: &[DataMemory]) -> Result<Comparison, String> {
+        for range in self.ranges.iter() {
+            if !self.dms.contains_key(&range.dm) {
+                return Err(format!("The range {} is not in an expected DM", range));
+            }
+        }
+        let mut comparison = Comparison::default();
+        for (&dm, expected) in self.dms.iter() {
+            let Some(dmem) = dmems.get(dm) else {
+                return Err(format!(
+                    "dm{} is expected but there are {} data memories",
+                    dm,
+                    dmems.len()
+                ));
+            };
+            if expected.data.len() > dmem.capacity() {
+                return Err(format!(
+                    "The expected dm{} is {} bytes, the data memory {} bytes",
+                    dm,
+                    expected.data.len(),
+                    dmem.capacity()
+                ));
+            }
+            let ranges: Vec<&Range<usize>> = self
+                .ranges
+                .iter()
+                .filter(|r| r.dm == dm)
+                .map(|r| &r.range)
+                .collect();
+            let in_ranges =
+                |addr: usize| ranges.is_empty() || ranges.iter().any(|r| r.contains(&addr));
+            let mut words = 0;
+            for addr in (0..expected.data.len()).step_by(8) {
+                let (mut value, mut mask, mut actual) = (0u64, 0u64, 0u64);
+                for byte in (addr..expected.data.len().min(addr + 8)).filter(|b| in_ranges(*b)) {
+                    let shift = (byte - addr) * 8;
+                    value |= (expected.data[byte] as u64) << shift;
+                    mask |= (expected.mask[byte] as u64) << shift;
+                    actual |= (dmem.data[byte] as u64) << shift;
+                }
+                if mask == 0 {
+                    continue;
+                }
+                words += 1;
+                if (value ^ actual) & mask != 0 {
+                    comparison.mismatches.push(Mismatch {
+                        dm,
+                        addr,
+                        expected: value & mask,
+                        mask,
+                        actual,
+                    });
+                }
+            }
+            comparison.words.insert(dm, words);
+        }
+        Ok(comparison)
+    }
+}
+
+/// A 64-bit word of a DM different from the expected one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub dm: usize,
+    /// The byte address of the word
+    pub addr: usize,
+    /// The expected value, 0 on the don't-care bits
+    pub expected: u64,
+    /// The compared bits
+    pub mask: u64,
+    pub actual: u64,
+}
+
+impl Mismatch {
+    /// The hexadecimal value, x for the nibbles not compared
+    fn hex(value: u64, mask: u64) -> String {
+        (0..16)
+            .rev()
+            .map(|i| {
+                if (mask >> (i * 4)) & 0xf == 0 {
+                    'x'
+                } else {
+                    std::char::from_digit(((value >> (i * 4)) & 0xf) as u32, 16).unwrap()
+                }
+            })
+            .collect()
+    }
+
+    /// The value as hexadecimal, as 4 i16 and as 8 FP8 lanes
+    fn describe(value: u64, mask: u64) -> String {
+        let i16s: Vec<i16> = (0..4).map(|i| (value >> (i * 16)) as i16).collect();
+        format!(
+            "0x{}  i16 {:?}  {:?}",
+            Self::hex(value, mask),
+            i16s,
+            SIMDValue::from(value)
+        )
+    }
+}
+
+/// The result of Golden::compare
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    /// The number of compared words by DM
+    pub words: BTreeMap<usize, usize>,
+    /// The words different from the expected ones, by DM and address
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Comparison {
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl Display for Comparison {
+    /// The mismatches grouped by DM
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (&dm, &words) in self.words.iter() {
+            let mismatches: Vec<&Mismatch> =
+                self.mismatches.iter().filter(|m| m.dm == dm).collect();
+            writeln!(
+                f,
+                "dm{}: {} mismatching words out of {} compared",
+                dm,
+                mismatches.len(),
+                words
+            )?;
+            for m in mismatches {
+                writeln!(
+                    f,
+                    "  {:#06x}  expected {}",
+                    m.addr,
+                    Mismatch::describe(m.expected, m.mask)
+                )?;
+                writeln!(
+                    f,
+                    "          actual   {}",
+                    Mismatch::describe(m.actual, u64::MAX)
+                )?;
+            }
+        }
+        write!(
+            f,
+            "{} mismatching words out of {} compared",
+            self.mismatches.len(),
+            self.words.values().sum::<usize>()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dmem(words: &[u64]) -> DataMemory {
+        DataMemory {
+            data: words.iter().flat_map(|w| w.to_le_bytes()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            "dm1:0x10..0x20".parse::<AddressRange>(),
+            Ok(AddressRange {
+                dm: 1,
+                range: 16..32
+            })
+        );
+        assert_eq!("dm0:0..8".parse::<AddressRange>().unwrap().range, 0..8);
+        assert!("dm0:8..8".parse::<AddressRange>().is_err());
+        assert!("dm0:0-8".parse::<AddressRange>().is_err());
+        assert_eq!(
+            "dm1:0x10..0x20"
+                .parse::<AddressRange>()
+                .unwrap()
+                .to_string(),
+            "dm1:0x10..0x20"
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let actual = [dmem(&[0x1111, 0x2222, 0x3333]), dmem(&[0xffff])];
+        let mut golden = Golden::default();
+        golden.insert(0, ExpectedMemory::from(&dmem(&[0x1111, 0x2202, 0x3330])));
+        let comparison = golden.compare(&actual).unwrap();
+        assert_eq!(comparison.words[&0], 3);
+        assert_eq!(
+            comparison
+                .mismatches
+                .iter()
+                .map(|m| m.addr)
+                .collect::<Vec<_>>(),
+            vec![8, 16]
+        );
+
+        // don't care on the low nibble of the last word
+        golden.dms.get_mut(&0).unwrap().mask[16] = 0xf0;
+        let comparison = golden.compare(&actual).unwrap();
+        assert_eq!(comparison.mismatches.len(), 1);
+        let lines: Vec<String> = comparison.to_string().lines().map(String::from).collect();
+        assert_eq!(lines[0], "dm0: 1 mismatching words out of 3 compared");
+        assert!(
+            lines[1]
+                .starts_with("  0x0008  expected 0x0000000000002202  i16 [8706, 0, 0, 0]  SIMD[")
+        );
+        assert!(lines[2].starts_with("          actual   0x0000000000002222  i16 [8738, 0, 0, 0]"));
+
+        // only the first word
+        golden.restrict("dm0:0..8".parse().unwrap());
+        let comparison = golden.compare(&actual).unwrap();
+        assert!(comparison.is_match());
+        assert_eq!(comparison.words[&0], 1);
+
+        golden.restrict("dm1:0..8".parse().unwrap());
+        assert!(golden.compare(&actual).is_err());
+        golden.ranges.clear();
+        golden.insert(2, ExpectedMemory::from(&dmem(&[0])));
+        assert!(golden.compare(&actual).is_err());
+    }
+
+    #[test]
+    fn test_hex_dont_care() {
+        assert_eq!(
+            Mismatch::hex(0x1234, 0xffff_ffff_ffff_f0f0),
+            "0000000000001x3x"
+        );
+    }
+}
@@ -1,39 +1,168 @@
-use log::{error, info};
+use std::{collections::HashMap, rc::Rc};
+
+use log::info;
 use nom::{
     IResult,
     bytes::complete::tag,
     character::complete::{digit1, multispace0},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     agu::{agu::AGU, instruction::Instruction},
     isa::{
-        binary::binary::{BinaryIO, BinaryStringIO},
-        configuration::Program,
+        configuration::Configuration,
+        operation::Operation,
         pe::*,
         router::{self, RouterOutDir},
+        value::LaneView,
     },
 };
 
-use super::dmem::DataMemory;
+use super::{
+    arch::Architecture,
+    breakpoint::{Breakpoint, StopReason},
+    dmem::{DMemMode, DataMemory, FaultKind, MemoryFaultPolicy},
+    loader::{self, LoadError},
+    netlist::Netlist,
+    route_check::RouterLoop,
+    stats::Stats,
+    topology::{MemPort, MemoryTopology},
+};
 
-// The mem PEs are at the left and right edges of the grid.
-// The shape is (x, y), x the number of columns
-#[derive(Debug)]
-pub struct DoubleSidedMemoryGrid {
+/// A grid of PEs with data memories attached to some of its edges.
+/// Where the memories are, and how the DMs and AGUs are numbered, is given by the topology.
+/// The shape is (x, y), x the number of columns
+#[derive(Debug, Clone)]
+pub struct Grid {
     pub shape: PEIdx,
     pub pes: Vec<Vec<PE>>,
     pub dmems: Vec<DataMemory>,
     pub agus: Vec<AGU>,
+    pub topology: MemoryTopology,
+    /// The memory PEs and their DM ports, in the order of the DMs
+    pub mem_ports: Vec<MemPort>,
+    /// The parameters of the hardware: given by the descriptor the grid was loaded with,
+    /// described from the grid itself otherwise
+    pub arch: Architecture,
+    /// The performance counters since the grid was created
+    pub stats: Stats,
+    /// The current cycle: the number of cycles completed by next_cycle(), or restored from a checkpoint
+    pub cycle: usize,
+    /// What to do with an access out of a DM or not aligned to its width
+    pub mem_fault_policy: MemoryFaultPolicy,
+    /// The netlists of the router met so far, by PCs of the PEs (row by row).
+    /// The configurations must not change once the grid is simulated.
+    pub(crate) netlists: HashMap<Vec<usize>, Result<Rc<Netlist>, RouterLoop>>,
 }
 
 #[derive(Debug)]
 pub enum SimulationError {
     PEUpdateError(PEIdx, String),
     SimulationEnd,
+    /// A breakpoint was hit, the cycle has been fully simulated
+    Breakpoint(StopReason),
+    /// The router outputs of the current configurations form a combinational loop
+    CombinationalLoop(RouterLoop),
+    /// An AGU accessed out of its DM or misaligned, with MemoryFaultPolicy::Fault.
+    /// The cycle is interrupted after the access
+    MemoryFault(MemoryFault),
+}
+
+/// An access of a memory PE out of its data memory, or not aligned to its width
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryFault {
+    pub cycle: usize,
+    pub dm: usize,
+    /// 1 or 2
+    pub port: usize,
+    /// The AGU which issued the access
+    pub agu: usize,
+    /// The memory PE of the port
+    pub pe: PEIdx,
+    pub addr: u64,
+    /// The width of the access in bytes
+    pub width: u64,
+    pub mode: DMemMode,
+    pub kind: FaultKind,
 }
 
-impl DoubleSidedMemoryGrid {
+impl std::fmt::Display for MemoryFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cycle {}: agu{} (PE-Y{}X{}) {} of {} bytes at address {:#x} of dm{} port {} is {}",
+            self.cycle,
+            self.agu,
+            self.pe.y,
+            self.pe.x,
+            self.mode,
+            self.width,
+            self.addr,
+            self.dm,
+            self.port,
+            self.kind
+        )
+    }
+}
+
+impl Grid {
+    /// Create a grid from its components, the memory PEs are given by the topology
+    pub fn new(
+        shape: PEIdx,
+        pes: Vec<Vec<PE>>,
+        dmems: Vec<DataMemory>,
+        agus: Vec<AGU>,
+        topology: MemoryTopology,
+    ) -> Self {
+        let mem_ports = topology.mem_ports(shape);
+        assert_eq!(
+            dmems.len(),
+            topology.num_dms(shape),
+            "The number of data memories does not match the topology"
+        );
+        assert!(
+            agus.is_empty() || agus.len() == mem_ports.len(),
+            "The number of AGUs does not match the topology"
+        );
+        let stats = Stats::new(shape, &mem_ports, agus.len());
+        let arch = Architecture::describe(shape, &topology, &dmems);
+        Grid {
+            shape,
+            pes,
+            dmems,
+            agus,
+            topology,
+            mem_ports,
+            arch,
+            stats,
+            cycle: 0,
+            mem_fault_policy: MemoryFaultPolicy::default(),
+            netlists: HashMap::new(),
+        }
+    }
+
+    /// Change the read latency of the data memories, in cycles: the loaded data reaches reg_op1 of
+    /// the memory PE `latency` cycles after the AGU instruction.
+    /// The memory pipelines of the DM ports and of the PEs are cleared, call it before the simulation.
+    pub fn set_mem_latency(&mut self, latency: usize) {
+        assert!(latency > 0, "The memory latency is at least 1 cycle");
+        for pe in self.pes.iter_mut().flatten() {
+            pe.set_mem_latency(latency);
+        }
+        for dmem in self.dmems.iter_mut() {
+            dmem.set_latency(latency);
+        }
+        self.arch.mem_latency = latency;
+    }
+
+    /// Replace the configuration at the PC of a PE, e.g. to flip bits of the CM.
+    /// The netlists of the router met so far are dropped.
+    pub fn set_configuration(&mut self, pe: PEIdx, pc: usize, configuration: Configuration) {
+        self.pes[pe.y][pe.x].configurations[pc] = configuration;
+        self.netlists.clear();
+    }
+
     /// Simulate one cycle of the grid
     /// AGU is required for all memory PEs in the new design.
     pub fn simulate_cycle(&mut self) -> Result<(), SimulationError> {
@@ -43,636 +172,176 @@ impl DoubleSidedMemoryGrid {
             "AGU is required for memory operations in the new design"
         );
 
-        // Step 1: Receive memory data for LOAD operations (2-cycle latency)
+        // Step 1: Receive memory data for LOAD operations (arch.mem_latency cycles)
         // This MUST happen BEFORE update_alu_out() so ALU can use loaded data
-        // Left edge memory PEs
-        for y in 0..self.shape.y {
-            let mem_idx = y / 2;
-            let port = if y % 2 == 0 {
-                &self.dmems[mem_idx].port1
-            } else {
-                &self.dmems[mem_idx].port2
-            };
-            self.pes[y][0].receive_mem_data(port);
-        }
-        // Right edge memory PEs
-        for y in 0..self.shape.y {
-            let mem_idx = self.shape.y / 2 + y / 2;
-            let port = if y % 2 == 0 {
-                &self.dmems[mem_idx].port1
-            } else {
-                &self.dmems[mem_idx].port2
-            };
-            self.pes[y][self.shape.x - 1].receive_mem_data(port);
+        for mem_port in self.mem_ports.iter() {
+            let port = self.dmems[mem_port.dm].port(mem_port.port);
+            self.pes[mem_port.pe.y][mem_port.pe.x].receive_mem_data(port);
         }
 
+        // The counters of this cycle, added to the stats once the cycle has completed
+        let mut alu_ops: Vec<(PEIdx, Operation)> = Vec::new();
+        let mut accesses: Vec<(usize, DMemMode)> = Vec::new();
+        let mut res_writes: Vec<PEIdx> = Vec::new();
+
         // Step 2: Update the ALU outputs of all PEs (now reg_op1 may have loaded data)
         for y in 0..self.shape.y {
             for x in 0..self.shape.x {
                 let pe = &mut self.pes[y][x];
+                let operation = pe.current_conf().operation;
+                if (operation.is_arith_logic() || operation.is_simd())
+                    && !pe.current_conf().alu_bypass
+                    && pe.is_enabled()
+                {
+                    alu_ops.push((PEIdx { x, y }, operation));
+                }
                 pe.update_alu_out();
             }
         }
 
-        // Step 3: Update memory interface for left column (handle STORE operations)
-        for y in 0..self.shape.y {
-            let agu_idx = y;
-            let mem_idx = y / 2;
-            let port = if y % 2 == 0 { 1 } else { 2 };
-
-            assert!(
-                self.agus[agu_idx].is_enabled(),
-                "AGU {} must be enabled for memory PE at y={}",
-                agu_idx,
-                y
-            );
-
-            let mem_interface = if port == 1 {
-                &mut self.dmems[mem_idx].port1
-            } else {
-                &mut self.dmems[mem_idx].port2
-            };
-
-            // Check AguTrigger first - only call AGU.update() if triggered
-            let pe = &mut self.pes[y][0];
-            let agu_trigger = pe.current_conf().agu_trigger;
-
-            if agu_trigger {
-                // AGU sets mode and address on DMemInterface
-                self.agus[agu_idx].update(mem_interface);
-            }
-
-            // PE processes (sets wire_dmem_data for STORE, invalidates mode if no trigger)
-            pe.update_mem(mem_interface);
-
-            // Call AGU.next() based on AguTrigger
-            if agu_trigger {
-                self.agus[agu_idx]
-                    .next()
-                    .map_err(|_| SimulationError::SimulationEnd)?;
-            }
-
-            // Only call update_interface() after both ports have been set up
-            // (port 1 is processed when y is even, port 2 when y is odd)
-            if y % 2 == 1 {
-                self.dmems[mem_idx].update_interface();
-            }
-        }
-
-        // for the last column, update the memory interface
-        for y in 0..self.shape.y {
-            let agu_idx = y + self.shape.y;
-            let mem_idx = self.shape.y / 2 + y / 2;
-            let port = if y % 2 == 0 { 1 } else { 2 };
-
+        // Step 3: Update memory interface of the memory PEs (handle STORE operations)
+        // The AGU instructions issued this cycle, by memory port, recorded in the PE pipelines at the end of the cycle
+        let mut issued: Vec<Option<Instruction>> = vec![None; self.mem_ports.len()];
+        for (port_idx, mem_port) in self.mem_ports.iter().enumerate() {
+            let agu = &mut self.agus[mem_port.agu];
             assert!(
-                self.agus[agu_idx].is_enabled(),
+                agu.is_enabled(),
                 "AGU {} must be enabled for memory PE at y={}, x={}",
-                agu_idx,
-                y,
-                self.shape.x - 1
+                mem_port.agu,
+                mem_port.pe.y,
+                mem_port.pe.x
             );
 
-            let mem_interface = if port == 1 {
-                &mut self.dmems[mem_idx].port1
-            } else {
-                &mut self.dmems[mem_idx].port2
-            };
+            let mem_interface = self.dmems[mem_port.dm].port_mut(mem_port.port);
 
             // Check AguTrigger first - only call AGU.update() if triggered
-            let pe = &mut self.pes[y][self.shape.x - 1];
+            let pe = &mut self.pes[mem_port.pe.y][mem_port.pe.x];
             let agu_trigger = pe.current_conf().agu_trigger;
 
             if agu_trigger {
                 // 1. AGU sets mode and address on DMemInterface
-                self.agus[agu_idx].update(mem_interface);
+                agu.update(mem_interface);
             }
 
             // 2. PE processes (sets wire_dmem_data for STORE, invalidates mode if no trigger)
             pe.update_mem(mem_interface);
 
-            // 3. Call AGU.next() based on AguTrigger, not PE opcode
-            if agu_trigger {
-                self.agus[agu_idx]
-                    .next()
-                    .map_err(|_| SimulationError::SimulationEnd)?;
+            if mem_interface.mode.is_load() || mem_interface.mode.is_store() {
+                accesses.push((port_idx, mem_interface.mode));
             }
 
-            // Only call update_interface() after both ports have been set up
-            // (port 1 is processed when y is even, port 2 when y is odd)
-            if y % 2 == 1 {
-                self.dmems[mem_idx].update_interface();
+            // 3. Call AGU.next() based on AguTrigger, not PE opcode
+            if agu_trigger {
+                issued[port_idx] = Some(*agu.current_instruction());
+                let count = agu.count;
+                agu.next().map_err(|_| SimulationError::SimulationEnd)?;
+                if agu.count > count {
+                    self.stats.agu_iterations[mem_port.agu] += 1;
+                }
             }
-        }
 
-        // For each PE, if it is a source of a multi-hop path, update the router outputs all along
-        for y in 0..self.shape.y {
-            for x in 0..self.shape.x {
-                let pe_idx = PEIdx { x, y };
-                let pe = &mut self.pes[y][x];
-                let router_config = pe.configurations[pe.pc].router_config.clone();
-                if router_config.is_path_source() {
-                    // update the router output signals
-                    pe.execute_router_output(&router_config)
-                        .map_err(|e| SimulationError::PEUpdateError(pe_idx, e))?;
-                    for output_direction in router_config.find_outputs_from_reg() {
-                        let output_pe_idx = pe_idx.output_pe_idx(output_direction);
-                        assert!(
-                            output_pe_idx.x < self.shape.x,
-                            "edge PE (y={}, x={}) is not able to send out of the array",
-                            pe_idx.y,
-                            pe_idx.x
-                        );
-                        assert!(
-                            output_pe_idx.y < self.shape.y,
-                            "edge PE (y={}, x={}) is not able to send out of the array",
-                            pe_idx.y,
-                            pe_idx.x
-                        );
-                        let next_pe_input_direction = output_direction.opposite_in_dir();
-                        self.propagate_router_signals(
-                            pe_idx,
-                            output_pe_idx,
-                            next_pe_input_direction,
-                        )
-                        .map_err(|e| SimulationError::PEUpdateError(pe_idx, e))?;
+            // Only call update_interface() after all ports of the DM have been set up
+            if mem_port.last_of_dm {
+                let faults = self.dmems[mem_port.dm].update_interface(self.mem_fault_policy);
+                for fault in faults {
+                    let fault_port = self
+                        .mem_ports
+                        .iter()
+                        .find(|p| p.dm == mem_port.dm && p.port == fault.port)
+                        .expect("Only the ports of memory PEs are accessed");
+                    let fault = MemoryFault {
+                        cycle: self.cycle,
+                        dm: mem_port.dm,
+                        port: fault.port,
+                        agu: fault_port.agu,
+                        pe: fault_port.pe,
+                        addr: fault.addr,
+                        width: fault.mode.num_bytes(),
+                        mode: fault.mode,
+                        kind: fault.kind,
+                    };
+                    match self.mem_fault_policy {
+                        MemoryFaultPolicy::Fault => {
+                            return Err(SimulationError::MemoryFault(fault));
+                        }
+                        MemoryFaultPolicy::Wrap => log::debug!("{}, wrapped", fault),
+                        MemoryFaultPolicy::Log => log::warn!("{}, skipped", fault),
                     }
                 }
             }
         }
 
+        // For each PE, if it is a source of a multi-hop path, update the router outputs all along.
+        // A loop of forwarding outputs is a combinational loop in the RTL, its wires never settle
+        self.propagate_router_signals()?;
+
         // Update registers for all PEs, passing AGU instruction for memory PEs
         for y in 0..self.shape.y {
             for x in 0..self.shape.x {
+                // For memory PEs, pass the AGU instruction issued this cycle if agu_trigger is set,
+                // the AGU has already moved to the next one
+                let current_agu_cm: Option<&Instruction> = self
+                    .mem_ports
+                    .iter()
+                    .position(|p| p.pe == PEIdx { x, y })
+                    .and_then(|port_idx| issued[port_idx].as_ref());
                 let pe = &mut self.pes[y][x];
-                // For memory PEs, pass the current AGU instruction if agu_trigger is set
-                let current_agu_cm: Option<&Instruction> = if x == 0 {
-                    // Left edge memory PE - AGU index is y
-                    let agu_idx = y;
-                    if pe.current_conf().agu_trigger {
-                        Some(self.agus[agu_idx].current_instruction())
-                    } else {
-                        None
-                    }
-                } else if x == self.shape.x - 1 {
-                    // Right edge memory PE - AGU index is y + shape.y
-                    let agu_idx = y + self.shape.y;
-                    if pe.current_conf().agu_trigger {
-                        Some(self.agus[agu_idx].current_instruction())
-                    } else {
-                        None
-                    }
-                } else {
-                    // Non-memory PE
-                    None
-                };
+                let operation = pe.current_conf().operation;
+                if !operation.is_control() && operation.update_res && pe.is_enabled() {
+                    res_writes.push(PEIdx { x, y });
+                }
                 pe.update_registers(current_agu_cm)
                     .map_err(|e| SimulationError::PEUpdateError(PEIdx { x, y }, e))?;
             }
         }
 
-        Ok(())
-    }
-
-    pub fn next_cycle(&mut self) {
-        for y in 0..self.shape.y {
-            for x in 0..self.shape.x {
-                let pe = &mut self.pes[y][x];
-                pe.next_conf();
-            }
-        }
-    }
-
-    pub fn dump_mem(&self, folder_path: &str) {
-        std::fs::create_dir_all(folder_path).unwrap();
-        for y in 0..self.shape.y / 2 {
-            let filename = format!("dm{}", y);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(file_path, self.dmems[y].to_binary_str()).unwrap();
-        }
-        for y in 0..self.shape.y / 2 {
-            let filename = format!("dm{}", y + self.shape.y / 2);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(file_path, self.dmems[y + self.shape.y / 2].to_binary_str()).unwrap();
-        }
-    }
-
-    pub fn snapshot(&self, folder_path: &str) {
-        std::fs::create_dir_all(folder_path).unwrap();
-        for y in 0..self.shape.y / 2 {
-            let filename = format!("dm{}", y);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(file_path, self.dmems[y].to_binary_str()).unwrap();
-            let filename = format!("dm{}_port1", y);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(file_path, self.dmems[y].port1.to_string()).unwrap();
-            let filename = format!("dm{}_port2", y);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(file_path, self.dmems[y].port2.to_string()).unwrap();
-        }
-        for y in 0..self.shape.y / 2 {
-            let filename = format!("dm{}", y + self.shape.y / 2);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(file_path, self.dmems[y + self.shape.y / 2].to_binary_str()).unwrap();
-            let filename = format!("dm{}_port1", y + self.shape.y / 2);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(
-                file_path,
-                self.dmems[y + self.shape.y / 2].port1.to_string(),
-            )
-            .unwrap();
-            let filename = format!("dm{}_port2", y + self.shape.y / 2);
-            let file_path = std::path::Path::new(&folder_path).join(filename);
-            std::fs::write(
-                file_path,
-                self.dmems[y + self.shape.y / 2].port2.to_string(),
-            )
-            .unwrap();
-        }
-        for y in 0..self.shape.y {
-            for x in 0..self.shape.x {
-                let filename = format!("PE-Y{}X{}.state", y, x);
-                let file_path = std::path::Path::new(&folder_path).join(filename);
-                std::fs::write(file_path, self.pes[y][x].snapshot()).unwrap();
-            }
-        }
-
-        // dump the agus
-        if self.is_agu_enabled() {
-            for y in 0..self.shape.y {
-                let filename = format!("agu{}", y);
-                let file_path = std::path::Path::new(&folder_path).join(filename);
-                std::fs::write(file_path, self.agus[y].to_string()).unwrap();
-            }
-            for y in 0..self.shape.y {
-                let filename = format!("agu{}", y + self.shape.y);
-                let file_path = std::path::Path::new(&folder_path).join(filename);
-                std::fs::write(file_path, self.agus[y + self.shape.y].to_string()).unwrap();
-            }
-        }
-    }
-
-    /// Loading the grid from a folder.
-    /// The folder contains the program of each PE as binprog files.
-    /// The filename of each PE is in the format of PE-YyXx, e.g. PE-Y1X0
-    /// The shape is automatically inferred from the max x and y in the filenames
-    /// You must provide the program for each (x, y), panic if some is missing
-    /// The data memory content is also automatically loaded.
-    /// The file for the data memories is dmx, where x is the index of the data memory
-    /// PE-YyX0 (the left edge PEs) are connected to the datamemory y%2.
-    /// PE-YyXX (the right edge PEs) are connected to the datamemory y%2+Y.
-    /// This means that the order of the memory files is top left -> bottom left -> top right -> bottom right
-    /// This means every two PEs are connected to the same data memory.
-    /// The data memory files are named as dm0, dm1, dm2, dm3, ...
-    /// So for a X x Y grid, you need to provide X memory files from dm0 to dmX-1
-    /// The AGU program files are named as agu0, agu1, agu2, agu3, ...,
-    /// In our case of 2 port data memories, there are 2 AGU files per memory.
-    /// The files are named aguX, where Y = PE.Y for the first column (left edge), then Y = ARRAY.Y + PE.Y for the last column (right edge)
-    pub fn from_folder(path: &str) -> Self {
-        let mut entries = std::fs::read_dir(&path).unwrap();
-        let mut max_x = usize::MIN;
-        let mut max_y = usize::MIN;
-        while let Some(entry) = entries.next() {
-            let entry = entry.unwrap();
-            let filename = entry.file_name().into_string().unwrap();
-            if let Ok((_, (x, y))) = Self::parse_pe_filename(&filename) {
-                max_x = max_x.max(x);
-                max_y = max_y.max(y);
-            }
-        }
-        let shape = PEIdx {
-            x: max_x + 1,
-            y: max_y + 1,
-        };
-
-        // Check that no PE program file is missing, i.e. each (x, y) is present
-        for x in 0..shape.x {
-            for y in 0..shape.y {
-                let filename = format!("PE-Y{}X{}", y, x);
-                let file_path = std::path::Path::new(&path).join(filename);
-                if !file_path.exists() {
-                    panic!("File {} is missing", file_path.display());
-                }
-            }
-        }
-
-        // Check the memory content files are present
-        for y in 0..shape.y {
-            let filename = format!("dm{}", y / 2);
-            let file_path = std::path::Path::new(&path).join(filename);
-            if !file_path.exists() {
-                panic!("File {} is missing", file_path.display());
-            }
-            let filename = format!("dm{}", y / 2 + shape.y / 2);
-            let file_path = std::path::Path::new(&path).join(filename);
-            if !file_path.exists() {
-                panic!("File {} is missing", file_path.display());
-            }
-        }
-
-        // Try to find if there is any AGU program file named aguX, if not, just consider non-AGU cases:
-        let mut agu_files_present = false;
-        for y in 0..shape.y {
-            let filename = format!("agu{}", y);
-            let file_path = std::path::Path::new(&path).join(filename);
-            if file_path.exists() {
-                agu_files_present = true;
-            }
-            let filename = format!("agu{}", y + shape.y);
-            let file_path = std::path::Path::new(&path).join(filename);
-            if file_path.exists() {
-                agu_files_present = true;
-            }
-        }
-
-        if !agu_files_present {
-            log::info!("No AGU program files found, considering non-AGU setting");
-        } else {
-            log::info!("AGU program files found, considering AGU setting");
-            // make sure all agu files are present
-            for y in 0..shape.y {
-                let filename = format!("agu{}", y);
-                let file_path = std::path::Path::new(&path).join(filename);
-                if !file_path.exists() {
-                    log::error!("AGU program file {} is missing", file_path.display());
-                    panic!(
-                        "AGU program file {} is missing, Simulator stops. Fatal Error.",
-                        file_path.display()
-                    );
-                }
-            }
-            for y in 0..shape.y {
-                let filename = format!("agu{}", y + shape.y);
-                let file_path = std::path::Path::new(&path).join(filename);
-                if !file_path.exists() {
-                    log::error!("AGU program file {} is missing", file_path.display());
-                    panic!(
-                        "AGU program file {} is missing, Simulator stops. Fatal Error.",
-                        file_path.display()
-                    );
-                }
-            }
-        }
-
-        // Load the programs and create the PEs
-        let mut pes: Vec<Vec<PE>> = Vec::new();
-        // initialize the PEs with default values
-        for _ in 0..shape.y {
-            let mut pes_row: Vec<PE> = Vec::new();
-            for _ in 0..shape.x {
-                let pe = PE::default();
-                pes_row.push(pe);
-            }
-            pes.push(pes_row);
-        }
-
-        // Load the PE programs
-        for y in 0..shape.y {
-            // The first column and last column are mem PEs
-            let filename = format!("PE-Y{}X{}", y, 0);
-            let file_path = std::path::Path::new(&path).join(filename);
-            let program = Vec::<u8>::from_binary_prog_file(file_path.to_str().unwrap()).unwrap();
-            let program = Program::from_binary(&program).unwrap();
-            let pe = PE::new_mem_pe(program);
-            pes[y][0] = pe;
-            for x in 1..shape.x - 1 {
-                let filename = format!("PE-Y{}X{}", y, x);
-                let file_path = std::path::Path::new(&path).join(filename);
-                let program =
-                    Vec::<u8>::from_binary_prog_file(file_path.to_str().unwrap()).unwrap();
-                let program = Program::from_binary(&program).unwrap();
-                let pe = PE::new(program);
-                pes[y][x] = pe;
-            }
-
-            let filename = format!("PE-Y{}X{}", y, shape.x - 1);
-            let file_path = std::path::Path::new(&path).join(filename);
-            let program = Vec::<u8>::from_binary_prog_file(file_path.to_str().unwrap()).unwrap();
-            let program = Program::from_binary(&program).unwrap();
-            let pe = PE::new_mem_pe(program);
-            pes[y][shape.x - 1] = pe;
-        }
-        log::info!("PE programs loaded successfully");
-
-        // Load the data memories
-        let mut dmems: Vec<DataMemory> = Vec::new();
-        for y in 0..shape.y / 2 {
-            let filename = format!("dm{}", y);
-            let file_path = std::path::Path::new(&path).join(&filename);
-            let dmem = DataMemory::from_binary_str(&std::fs::read_to_string(file_path).unwrap());
-            dmems.push(dmem);
-        }
-        for y in 0..shape.y / 2 {
-            let filename = format!("dm{}", y + shape.y / 2);
-            let file_path = std::path::Path::new(&path).join(&filename);
-            let dmem = DataMemory::from_binary_str(&std::fs::read_to_string(file_path).unwrap());
-            dmems.push(dmem);
-        }
-        log::info!("Data memories loaded successfully");
-
-        // Load the AGUs
-        let mut agus: Vec<AGU> = Vec::new();
-        if agu_files_present {
-            for y in 0..shape.y {
-                let filename = format!("agu{}", y);
-                let file_path = std::path::Path::new(&path).join(&filename);
-                let agu =
-                    AGU::from_mnemonics(&std::fs::read_to_string(file_path).unwrap()).unwrap();
-                agus.push(agu);
-            }
-            for y in 0..shape.y {
-                let filename = format!("agu{}", y + shape.y);
-                let file_path = std::path::Path::new(&path).join(&filename);
-                let agu =
-                    AGU::from_mnemonics(&std::fs::read_to_string(file_path).unwrap()).unwrap();
-                agus.push(agu);
+        for (pe, operation) in alu_ops {
+            let stats = &mut self.stats.pes[pe.y][pe.x];
+            *stats.ops.entry(operation.op_code).or_default() += 1;
+            if operation.is_simd() {
+                stats.simd_ops += 1;
+            } else {
+                stats.scalar_ops += 1;
             }
         }
-        DoubleSidedMemoryGrid {
-            shape,
-            pes,
-            dmems,
-            agus,
+        for (port_idx, mode) in accesses {
+            *self.stats.ports[port_idx].accesses.entry(mode).or_default() += 1;
         }
-    }
-
-    /// Parse the filename of a PE program file, returns the coordinates of the PE
-    /// Syntax: PE-YyXx
-    fn parse_pe_filename(filename: &str) -> IResult<&str, (usize, usize)> {
-        // use nom
-        let (input, _) = tag("PE-Y")(filename)?;
-        let (input, y) = digit1(input)?;
-        let (input, _) = tag("X")(input)?;
-        let (input, x) = digit1(input)?;
-        let (input, _) = multispace0(input)?;
-        if !input.is_empty() {
-            return Err(nom::Err::Error(nom::error::Error::new(
-                input,
-                nom::error::ErrorKind::Tag,
-            )));
+        for pe in res_writes {
+            self.stats.pes[pe.y][pe.x].res_writes += 1;
         }
-        Ok((input, (x.parse().unwrap(), y.parse().unwrap())))
+        self.stats.cycles += 1;
+        Ok(())
     }
 
-    /// propagate the router signals from the src_pe to the dst_pe in the given direction (as input direction of the dst_pe)
-    fn propagate_router_signals(
+    /// Simulate one cycle of the grid, then check the breakpoints.
+    /// The first breakpoint hit in the cycle is returned as SimulationError::Breakpoint,
+    /// the simulation can be resumed with next_cycle()
+    pub fn simulate_cycle_with_breakpoints(
         &mut self,
-        src: PEIdx,
-        dst: PEIdx,
-        direction: router::RouterInDir,
-    ) -> Result<(), String> {
-        let src_pe = self.pes[src.y][src.x].clone();
-        let dst_pe = &mut self.pes[dst.y][dst.x];
-        let router_switch_config = dst_pe.configurations[dst_pe.pc]
-            .router_config
-            .switch_config
-            .clone();
-
-        // update the dst_pe's router signals from the src_pe
-        dst_pe.update_router_signals_from(&src_pe, direction)?;
-        // update the dst_pe's router output signals according to its router config
-        dst_pe.update_router_output()?;
-
-        // find the output directions from the given direction
-        let output_directions = router_switch_config.find_output_directions(direction);
-
-        // propagate the router signals to the next PEs
-        for output_direction in output_directions {
-            let opposite_direction = output_direction.opposite_in_dir();
-            let next_pe = dst.output_pe_idx(output_direction);
-            self.propagate_router_signals(dst, next_pe, opposite_direction)?;
+        breakpoints: &[Breakpoint],
+    ) -> Result<(), SimulationError> {
+        let before: Vec<Option<u64>> = breakpoints.iter().map(|b| b.sample(self)).collect();
+        self.simulate_cycle()?;
+        for (breakpoint, before) in breakpoints.iter().zip(before) {
+            if let Some(reason) = breakpoint.check(self, before) {
+                return Err(SimulationError::Breakpoint(reason));
+            }
         }
         Ok(())
     }
 
-    fn is_agu_enabled(&self) -> bool {
-        !self.agus.is_empty()
-    }
-}
-
-pub struct SingleSidedMemoryGrid {
-    pub shape: PEIdx,
-    pub pes: Vec<Vec<PE>>,
-    pub dmems: Vec<DataMemory>, // only left-side memories
-    pub agus: Vec<AGU>,         // only left-side AGUs
-}
-
-impl SingleSidedMemoryGrid {
-    /// Simulate one cycle of the grid, with memory only on left edge
-    /// AGU is required for all memory PEs in the new design.
-    pub fn simulate_cycle(&mut self) -> Result<(), SimulationError> {
-        // Verify AGU is enabled (required in new design)
-        assert!(
-            !self.agus.is_empty(),
-            "AGU is required for memory operations in the new design"
-        );
-
-        // Step 1: Receive memory data for LOAD operations (2-cycle latency)
-        // This MUST happen BEFORE update_alu_out() so ALU can use loaded data
-        for y in 0..self.shape.y {
-            let mem_idx = y / 2;
-            let port = if y % 2 == 0 {
-                &self.dmems[mem_idx].port1
-            } else {
-                &self.dmems[mem_idx].port2
-            };
-            self.pes[y][0].receive_mem_data(port);
-        }
-
-        // Step 2: Update ALU for all PEs (now reg_op1 may have loaded data)
-        for y in 0..self.shape.y {
-            for x in 0..self.shape.x {
-                self.pes[y][x].update_alu_out();
-            }
-        }
-
-        // Step 3: Update memory interface only for leftmost column (handle STORE operations)
-        for y in 0..self.shape.y {
-            let mem_idx = y / 2;
-
-            assert!(
-                self.agus.get(y).map_or(false, |agu| agu.is_enabled()),
-                "AGU {} must be enabled for memory PE at y={}",
-                y,
-                y
-            );
-
-            let mem = &mut self.dmems[mem_idx];
-            let port = if y % 2 == 0 {
-                &mut mem.port1
-            } else {
-                &mut mem.port2
-            };
-
-            // Check AguTrigger first - only call AGU.update() if triggered
-            let pe = &mut self.pes[y][0];
-            let agu_trigger = pe.current_conf().agu_trigger;
-
-            if agu_trigger {
-                // AGU sets mode and address on DMemInterface
-                self.agus[y].update(port);
-            }
-
-            // PE processes (sets wire_dmem_data for STORE, invalidates mode if no trigger)
-            pe.update_mem(port);
-
-            // Call AGU.next() based on AguTrigger
-            if agu_trigger {
-                self.agus[y]
-                    .next()
-                    .map_err(|_| SimulationError::SimulationEnd)?;
-            }
-
-            // Only call update_interface() after both ports have been set up
-            // (port 1 is processed when y is even, port 2 when y is odd)
-            if y % 2 == 1 {
-                self.dmems[mem_idx].update_interface();
-            }
-        }
-
-        // 3) Router propagation
-        for y in 0..self.shape.y {
-            for x in 0..self.shape.x {
-                let pe_idx = PEIdx { x, y };
-                let pe = &mut self.pes[y][x];
-                let cfg = pe.configurations[pe.pc].router_config.clone();
-                if cfg.is_path_source() {
-                    pe.execute_router_output(&cfg)
-                        .map_err(|e| SimulationError::PEUpdateError(pe_idx, e))?;
-                    for dir in cfg.find_outputs_from_reg() {
-                        let dst = pe_idx.output_pe_idx(dir);
-                        self.propagate_router_signals(pe_idx, dst, dir.opposite_in_dir())
-                            .map_err(|e| SimulationError::PEUpdateError(pe_idx, e))?;
-                    }
-                }
-            }
-        }
-
-        // 4) Update registers for all PEs, passing AGU instruction for memory PEs
+    pub fn next_cycle(&mut self) {
         for y in 0..self.shape.y {
             for x in 0..self.shape.x {
                 let pe = &mut self.pes[y][x];
-                // For memory PEs (left edge), pass the current AGU instruction if agu_trigger is set
-                let current_agu_cm: Option<&Instruction> = if x == 0 {
-                    // Left edge memory PE - AGU index is y
-                    if pe.current_conf().agu_trigger {
-                        Some(self.agus[y].current_instruction())
-                    } else {
-                        None
-                    }
-                } else {
-                    // Non-memory PE
-                    None
-                };
-                pe.update_registers(current_agu_cm)
-                    .map_err(|e| SimulationError::PEUpdateError(PEIdx { x, y }, e))?;
+                pe.next_conf();
             }
         }
-
-        Ok(())
+        self.cycle += 1;
     }
 
-    /// Dump only left-side data memories to `folder_path` as dm0, dm1, ...
+    /// Dump the data memories to `folder_path` as dm0, dm1, ...
     pub fn dump_mem(&self, folder_path: &str) {
         info!(
             "Dumping {} data memories to {}",
@@ -687,7 +356,18 @@ impl SingleSidedMemoryGrid {
         }
     }
 
-    /// Snapshot DMem ports, PE states, and AGUs (left-side only)
+    /// The lane view of the current configuration of the memory PE of a DM port,
+    /// the default one for a port without PE
+    fn port_lane_view(&self, dm: usize, port: usize) -> LaneView {
+        self.mem_ports
+            .iter()
+            .find(|p| p.dm == dm && p.port == port)
+            .map(|p| self.pes[p.pe.y][p.pe.x].current_conf().lane_view())
+            .unwrap_or_default()
+    }
+
+    /// Snapshot DMem ports, PE states, and AGUs.
+    /// The lanes of a DM port are shown in the view of its memory PE
     pub fn snapshot(&self, folder_path: &str) {
         info!("Snapshotting grid state to {}", folder_path);
         std::fs::create_dir_all(folder_path).unwrap();
@@ -696,9 +376,9 @@ impl SingleSidedMemoryGrid {
             let base = std::path::Path::new(folder_path).join(format!("dm{}", i));
             std::fs::write(&base, mem.to_binary_str()).unwrap();
             let p1 = std::path::Path::new(folder_path).join(format!("dm{}_port1", i));
-            std::fs::write(&p1, mem.port1.to_string()).unwrap();
+            std::fs::write(&p1, mem.port1.describe(self.port_lane_view(i, 1))).unwrap();
             let p2 = std::path::Path::new(folder_path).join(format!("dm{}_port2", i));
-            std::fs::write(&p2, mem.port2.to_string()).unwrap();
+            std::fs::write(&p2, mem.port2.describe(self.port_lane_view(i, 2))).unwrap();
         }
         // PE snapshots
         for y in 0..self.shape.y {
@@ -709,154 +389,181 @@ impl SingleSidedMemoryGrid {
             }
         }
         // AGU snapshots if enabled
-        if !self.agus.is_empty() {
-            for (y, agu) in self.agus.iter().enumerate() {
-                let filename = format!("agu{}", y);
+        if self.is_agu_enabled() {
+            for (i, agu) in self.agus.iter().enumerate() {
+                let filename = format!("agu{}", i);
                 let file_path = std::path::Path::new(folder_path).join(&filename);
                 std::fs::write(&file_path, agu.to_string()).unwrap();
             }
         }
     }
 
-    /// Load grid from folder, only left-side memories and AGUs
-    pub fn from_folder(path: &str) -> Self {
+    /// Loading the grid from a folder.
+    /// The folder contains the program of each PE as binprog files.
+    /// The filename of each PE is in the format of PE-YyXx, e.g. PE-Y1X0
+    /// The shape is automatically inferred from the max x and y in the filenames
+    /// You must provide the program for each (x, y)
+    /// The data memory content is also automatically loaded.
+    /// The data memory files are named as dm0, dm1, dm2, dm3, ...
+    /// The AGU program files are named as agu0, agu1, agu2, agu3, ..., one per memory PE.
+    /// Which PEs are memory PEs, and the numbering of the DMs and AGUs, is given by the topology.
+    /// For MemoryTopology::double_sided(), the order of the memory and AGU files is
+    /// top left -> bottom left -> top right -> bottom right, every two PEs share one DM.
+    /// AGU files are optional, but if any of them is present, all of them must be.
+    /// All the problems found in the folder are reported at once in the LoadError.
+    pub fn from_folder(path: &str, topology: MemoryTopology) -> Result<Self, LoadError> {
         info!("Loading grid from folder: {}", path);
-        let mut entries = std::fs::read_dir(path).unwrap();
-        let mut max_x = 0;
-        let mut max_y = 0;
+        let folder = std::path::Path::new(path);
+        let entries = std::fs::read_dir(folder).map_err(|e| LoadError::Io {
+            path: folder.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let filenames: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
 
         // find shape from PE filenames
-        for entry in entries.by_ref() {
-            let name = entry.unwrap().file_name().into_string().unwrap();
-            if let Ok((_, (x, y))) = Self::parse_pe_filename(&name) {
-                max_x = max_x.max(x);
-                max_y = max_y.max(y);
-            }
+        let pe_coords: Vec<(usize, usize)> = filenames
+            .iter()
+            .filter_map(|name| Self::parse_pe_filename(name).ok().map(|(_, xy)| xy))
+            .collect();
+        if pe_coords.is_empty() {
+            return Err(LoadError::CountMismatch {
+                path: folder.to_path_buf(),
+                what: "PE programs".to_string(),
+                expected: 1,
+                found: 0,
+            });
         }
         let shape = PEIdx {
-            x: max_x + 1,
-            y: max_y + 1,
+            x: pe_coords.iter().map(|(x, _)| *x).max().unwrap() + 1,
+            y: pe_coords.iter().map(|(_, y)| *y).max().unwrap() + 1,
         };
         info!("Determined grid shape: {} cols x {} rows", shape.x, shape.y);
+        let mem_ports = topology.mem_ports(shape);
+        let num_dms = topology.num_dms(shape);
+        let mut errors = Vec::new();
 
-        // verify all PE files present
-        for y in 0..shape.y {
-            for x in 0..shape.x {
-                let f = format!("PE-Y{}X{}", y, x);
-                let pth = std::path::Path::new(path).join(&f);
-                if !pth.exists() {
-                    error!("Missing PE program file: {}", pth.display());
-                    panic!("Missing PE program file: {}", f);
+        // Load the PE programs, the memory PEs are given by the topology
+        let mut pes = vec![vec![PE::default(); shape.x]; shape.y];
+        for (y, pes_row) in pes.iter_mut().enumerate() {
+            for (x, pe) in pes_row.iter_mut().enumerate() {
+                let file_path = folder.join(format!("PE-Y{}X{}", y, x));
+                match loader::load_program(&file_path) {
+                    Ok(program) if mem_ports.iter().any(|p| p.pe == PEIdx { x, y }) => {
+                        *pe = PE::new_mem_pe(program)
+                    }
+                    Ok(program) => *pe = PE::new(program),
+                    Err(e) => errors.push(e),
                 }
             }
         }
 
-        // load PE programs
-        let mut pes = vec![vec![PE::default(); shape.x]; shape.y];
-        for y in 0..shape.y {
-            for x in 0..shape.x {
-                let f = format!("PE-Y{}X{}", y, x);
-                let prog = Vec::<u8>::from_binary_prog_file(
-                    std::path::Path::new(path).join(&f).to_str().unwrap(),
-                )
-                .unwrap();
-                let prog = Program::from_binary(&prog).unwrap();
-                pes[y][x] = if x == 0 {
-                    PE::new_mem_pe(prog)
-                } else {
-                    PE::new(prog)
-                };
+        // Load the data memories
+        let mut dmems = Vec::new();
+        for i in 0..num_dms {
+            match loader::load_dmem(&folder.join(format!("dm{}", i))) {
+                Ok(dmem) => dmems.push(dmem),
+                Err(e) => errors.push(e),
             }
         }
-        info!("Loaded {} PEs", shape.x * shape.y);
-
-        // load left-side memories
-        let mut dmems = Vec::new();
-        for i in 0..(shape.y + 1) / 2 {
-            let f = format!("dm{}", i);
-            let s = std::fs::read_to_string(std::path::Path::new(path).join(&f)).unwrap();
-            dmems.push(DataMemory::from_binary_str(&s));
+        let dm_files = filenames
+            .iter()
+            .filter_map(|name| loader::parse_indexed_filename(name, "dm"));
+        if let Some(max_dm) = dm_files.max().filter(|max_dm| *max_dm >= num_dms) {
+            errors.push(LoadError::CountMismatch {
+                path: folder.to_path_buf(),
+                what: "data memories".to_string(),
+                expected: num_dms,
+                found: max_dm + 1,
+            });
         }
-        info!("Loaded {} data memories", dmems.len());
 
-        // load left-side AGUs if present
+        // Load the AGUs if there is any AGU program file named aguX, if not, just consider non-AGU cases
         let mut agus = Vec::new();
-        let mut has_agu = true;
-        for y in 0..shape.y {
-            if !std::path::Path::new(path)
-                .join(format!("agu{}", y))
-                .exists()
-            {
-                has_agu = false;
-                break;
+        let agu_files: Vec<usize> = filenames
+            .iter()
+            .filter_map(|name| loader::parse_indexed_filename(name, "agu"))
+            .collect();
+        if agu_files.is_empty() {
+            info!("No AGU program files found, considering non-AGU setting");
+        } else {
+            info!("AGU program files found, considering AGU setting");
+            // all agu files must be present
+            for i in 0..mem_ports.len() {
+                match loader::load_agu(&folder.join(format!("agu{}", i))) {
+                    Ok(agu) => agus.push(agu),
+                    Err(e) => errors.push(e),
+                }
             }
-        }
-        if has_agu {
-            for y in 0..shape.y {
-                let f = format!("agu{}", y);
-                let s = std::fs::read_to_string(std::path::Path::new(path).join(&f)).unwrap();
-                agus.push(AGU::from_mnemonics(&s).unwrap());
+            let max_agu = agu_files.iter().max().unwrap();
+            if *max_agu >= mem_ports.len() {
+                errors.push(LoadError::CountMismatch {
+                    path: folder.to_path_buf(),
+                    what: "AGU programs".to_string(),
+                    expected: mem_ports.len(),
+                    found: max_agu + 1,
+                });
             }
-            info!("Loaded {} AGUs", agus.len());
-        } else {
-            info!("No AGU programs found; running without AGU support");
-        }
-
-        SingleSidedMemoryGrid {
-            shape,
-            pes,
-            dmems,
-            agus,
         }
-    }
 
-    pub fn next_cycle(&mut self) {
-        for y in 0..self.shape.y {
-            for x in 0..self.shape.x {
-                let pe = &mut self.pes[y][x];
-                pe.next_conf();
-            }
-        }
+        LoadError::from_errors(errors)?;
+        info!(
+            "Loaded {} PEs, {} data memories and {} AGUs",
+            shape.x * shape.y,
+            dmems.len(),
+            agus.len()
+        );
+        Ok(Grid::new(shape, pes, dmems, agus, topology))
     }
 
+    /// Parse the filename of a PE program file, returns the coordinates of the PE
+    /// Syntax: PE-YyXx
     fn parse_pe_filename(filename: &str) -> IResult<&str, (usize, usize)> {
-        let (i, _) = tag("PE-Y")(filename)?;
-        let (i, y) = digit1(i)?;
-        let (i, _) = tag("X")(i)?;
-        let (i, x) = digit1(i)?;
-        let (i, _) = multispace0(i)?;
-        if !i.is_empty() {
+        // use nom
+        let (input, _) = tag("PE-Y")(filename)?;
+        let (input, y) = digit1(input)?;
+        let (input, _) = tag("X")(input)?;
+        let (input, x) = digit1(input)?;
+        let (input, _) = multispace0(input)?;
+        if !input.is_empty() {
             return Err(nom::Err::Error(nom::error::Error::new(
-                i,
+                input,
                 nom::error::ErrorKind::Tag,
             )));
         }
-        Ok((i, (x.parse().unwrap(), y.parse().unwrap())))
+        Ok((input, (x.parse().unwrap(), y.parse().unwrap())))
     }
 
-    fn propagate_router_signals(
-        &mut self,
-        src: PEIdx,
-        dst: PEIdx,
-        dir: router::RouterInDir,
-    ) -> Result<(), String> {
-        let src_pe = self.pes[src.y][src.x].clone();
-        let dst_pe = &mut self.pes[dst.y][dst.x];
-        let cfg = dst_pe.configurations[dst_pe.pc]
-            .router_config
-            .switch_config
-            .clone();
-        dst_pe.update_router_signals_from(&src_pe, dir)?;
-        dst_pe.update_router_output()?;
-        for out in cfg.find_output_directions(dir) {
-            let next = dst.output_pe_idx(out);
-            self.propagate_router_signals(dst, next, out.opposite_in_dir())?;
-        }
-        Ok(())
+    /// The memory port of a PE, None if it is not a memory PE
+    pub fn mem_port_of(&self, pe_idx: PEIdx) -> Option<&MemPort> {
+        self.mem_ports.iter().find(|p| p.pe == pe_idx)
+    }
+
+    /// The neighbour PE in the given output direction, panic if it is out of the array
+    pub(crate) fn output_pe_idx(&self, pe_idx: PEIdx, direction: RouterOutDir) -> PEIdx {
+        let out_of_array = match direction {
+            RouterOutDir::NorthOut => pe_idx.y == 0,
+            RouterOutDir::SouthOut => pe_idx.y + 1 >= self.shape.y,
+            RouterOutDir::WestOut => pe_idx.x == 0,
+            RouterOutDir::EastOut => pe_idx.x + 1 >= self.shape.x,
+            _ => false,
+        };
+        assert!(
+            !out_of_array,
+            "edge PE (y={}, x={}) is not able to send out of the array",
+            pe_idx.y, pe_idx.x
+        );
+        pe_idx.output_pe_idx(direction)
+    }
+
+    fn is_agu_enabled(&self) -> bool {
+        !self.agus.is_empty()
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub struct PEIdx {
     pub x: usize,
     pub y: usize,
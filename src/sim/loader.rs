@@ -0,0 +1,444 @@
+//! Load the PE programs, data memories and AGUs from files.
+//! Nothing here panics on a bad input, the problems are reported as LoadError.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    agu::agu::AGU,
+    isa::{
+        binary::binary::{BinaryIO, BinaryStringIO},
+        configuration::Program,
+    },
+    sim::dmem::DataMemory,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The file does not exist
+    MissingFile { path: PathBuf },
+    /// The file exists but cannot be read
+    Io { path: PathBuf, message: String },
+    /// The content is malformed, line and column start from 1
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The content has the wrong size (in bytes)
+    SizeMismatch {
+        path: PathBuf,
+        expected: usize,
+        found: usize,
+    },
+    /// The folder does not have the expected number of PEs, DMs or AGUs
+    CountMismatch {
+        path: PathBuf,
+        what: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The content does not fit in the memory of the architecture
+    CapacityExceeded {
+        path: PathBuf,
+        what: String,
+        capacity: usize,
+        found: usize,
+    },
+    /// The architecture descriptor is well-formed but cannot be simulated
+    InvalidArchitecture { path: PathBuf, message: String },
+    /// All the problems found in a folder
+    Multiple(Vec<LoadError>),
+}
+
+impl LoadError {
+    /// Ok if there is no error, the error itself if there is one, Multiple otherwise
+    pub fn from_errors(mut errors: Vec<LoadError>) -> Result<(), LoadError> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(LoadError::Multiple(errors)),
+        }
+    }
+
+    /// All the individual errors, Multiple is flattened
+    pub fn errors(&self) -> Vec<&LoadError> {
+        match self {
+            LoadError::Multiple(errors) => errors.iter().flat_map(|e| e.errors()).collect(),
+            _ => vec![self],
+        }
+    }
+
+    /// Create a parse error at the given byte offset of the content
+    pub(crate) fn parse(path: &Path, content: &str, offset: usize, message: String) -> Self {
+        let (line, column) = line_column(content, offset);
+        LoadError::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::MissingFile { path } => write!(f, "{}: file is missing", path.display()),
+            LoadError::Io { path, message } => write!(f, "{}: {}", path.display(), message),
+            LoadError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            LoadError::SizeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected at most {} bytes, found {}",
+                path.display(),
+                expected,
+                found
+            ),
+            LoadError::CountMismatch {
+                path,
+                what,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {} {}, found {}",
+                path.display(),
+                expected,
+                what,
+                found
+            ),
+            LoadError::CapacityExceeded {
+                path,
+                what,
+                capacity,
+                found,
+            } => write!(
+                f,
+                "{}: expected at most {} {}, found {}",
+                path.display(),
+                capacity,
+                what,
+                found
+            ),
+            LoadError::InvalidArchitecture { path, message } => {
+                write!(f, "{}: invalid architecture: {}", path.display(), message)
+            }
+            LoadError::Multiple(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// The line and column (starting from 1) of a byte offset in the content
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Read the whole file, distinguishing a missing file from an unreadable one
+pub fn read_file(path: &Path) -> Result<String, LoadError> {
+    if !path.exists() {
+        return Err(LoadError::MissingFile {
+            path: path.to_path_buf(),
+        });
+    }
+    std::fs::read_to_string(path).map_err(|e| LoadError::Io {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+/// Load a PE program from a binprog file (a binary string, spaces and line breaks are ignored)
+pub fn load_program(path: &Path) -> Result<Program, LoadError> {
+    let content = read_file(path)?;
+    // the offsets of the bits in the file, to locate the errors
+    let bits: Vec<(usize, char)> = content
+        .char_indices()
+        .filter(|(_, c)| !matches!(c, ' ' | '\n' | '\r'))
+        .collect();
+    if let Some((offset, c)) = bits.iter().find(|(_, c)| *c != '0' && *c != '1') {
+        return Err(LoadError::parse(
+            path,
+            &content,
+            *offset,
+            format!("Unexpected character '{}' in binary string", c),
+        ));
+    }
+    if !bits.len().is_multiple_of(64) {
+        return Err(LoadError::parse(
+            path,
+            &content,
+            content.len(),
+            format!(
+                "Binary string length ({}) must be a multiple of 64",
+                bits.len()
+            ),
+        ));
+    }
+    let bit_str: String = bits.iter().map(|(_, c)| c).collect();
+    let code = Vec::<u8>::from_binary_str(&bit_str).map_err(|e| LoadError::Parse {
+        path: path.to_path_buf(),
+        line: 1,
+        column: 1,
+        message: e,
+    })?;
+    let mut configurations = Vec::new();
+    for (i, chunk) in code.chunks(8).enumerate() {
+        let configuration = Program::from_binary(&chunk.to_vec())
+            .map_err(|e| LoadError::parse(path, &content, bits[i * 64].0, e))?;
+        configurations.extend(configuration.configurations);
+    }
+    Ok(Program { configurations })
+}
+
+/// Load a data memory from a file of 64-character binary lines, spaces are ignored
+pub fn load_dmem(path: &Path) -> Result<DataMemory, LoadError> {
+    let content = read_file(path)?;
+    let mut lines = Vec::new();
+    let mut line_offset = 0;
+    for (line_idx, line) in content.split('\n').enumerate() {
+        let bits: Vec<(usize, char)> = line
+            .char_indices()
+            .filter(|(_, c)| *c != ' ' && *c != '\r')
+            .collect();
+        if let Some((column, c)) = bits.iter().find(|(_, c)| *c != '0' && *c != '1') {
+            return Err(LoadError::Parse {
+                path: path.to_path_buf(),
+                line: line_idx + 1,
+                column: column + 1,
+                message: format!("Unexpected character '{}' in binary string", c),
+            });
+        }
+        // a trailing line break is allowed
+        let is_last_line = line_offset + line.len() == content.len();
+        if bits.len() != 64 && !(is_last_line && bits.is_empty()) {
+            return Err(LoadError::Parse {
+                path: path.to_path_buf(),
+                line: line_idx + 1,
+                column: line.len() + 1,
+                message: format!(
+                    "Expected a 64-character string, but got length {}",
+                    bits.len()
+                ),
+            });
+        }
+        if !bits.is_empty() {
+            lines.push(bits.iter().map(|(_, c)| c).collect::<String>());
+        }
+        line_offset += line.len() + 1;
+    }
+    Ok(DataMemory::from_binary_str(&lines.join("\n")))
+}
+
+/// Load an AGU from its mnemonics
+pub fn load_agu(path: &Path) -> Result<AGU, LoadError> {
+    let content = read_file(path)?;
+    let (input, agu) = AGU::parse_mnemonics(&content).map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => LoadError::parse(
+            path,
+            &content,
+            content.len() - e.input.len(),
+            format!("Invalid AGU mnemonics ({:?})", e.code),
+        ),
+        nom::Err::Incomplete(_) => LoadError::parse(
+            path,
+            &content,
+            content.len(),
+            "Incomplete input".to_string(),
+        ),
+    })?;
+    if !input.is_empty() {
+        return Err(LoadError::parse(
+            path,
+            &content,
+            content.len() - input.len(),
+            "Unexpected input after MAX COUNT".to_string(),
+        ));
+    }
+    agu.validate().map_err(|e| {
+        // point at the section that is inconsistent
+        let section = if agu.cm.len() != agu.arf.len() {
+            "ARF:"
+        } else {
+            "MAX COUNT:"
+        };
+        LoadError::parse(path, &content, content.find(section).unwrap_or(0), e)
+    })?;
+    Ok(agu)
+}
+
+/// Parse a filename made of a prefix and an index, e.g. dm3 or agu12
+pub fn parse_indexed_filename(filename: &str, prefix: &str) -> Option<usize> {
+    let index = filename.strip_prefix(prefix)?;
+    if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write the content to a temporary file, return its path
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join("pace_sim_loader_tests");
+        std::fs::create_dir_all(&folder).unwrap();
+        let path = folder.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let path = Path::new("tests/this_folder_does_not_exist/dm0");
+        assert_eq!(
+            load_dmem(path).unwrap_err(),
+            LoadError::MissingFile {
+                path: path.to_path_buf()
+            }
+        );
+    }
+
+    #[test]
+    fn test_dmem_parse_error() {
+        let line = "0".repeat(64);
+        let path = temp_file(
+            "dm_bad",
+            &format!("{}\n{}2{}\n", line, "0".repeat(10), "0".repeat(53)),
+        );
+        let err = load_dmem(&path).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                LoadError::Parse {
+                    line: 2,
+                    column: 11,
+                    ..
+                }
+            ),
+            "{}",
+            err
+        );
+
+        let path = temp_file("dm_short", &format!("{}\n0000\n", line));
+        let err = load_dmem(&path).unwrap_err();
+        assert!(matches!(err, LoadError::Parse { line: 2, .. }), "{}", err);
+
+        let path = temp_file("dm_good", &format!("{}\n{}", line, line));
+        assert_eq!(load_dmem(&path).unwrap().data.len(), 16);
+    }
+
+    #[test]
+    fn test_agu_parse_error() {
+        let path = temp_file(
+            "agu_bad",
+            "CM:\nLOAD,STRIDED,B16,1\nARF:\n0\nMAX COUNT:\nfive\n",
+        );
+        let err = load_agu(&path).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                LoadError::Parse {
+                    line: 6,
+                    column: 1,
+                    ..
+                }
+            ),
+            "{}",
+            err
+        );
+
+        let path = temp_file(
+            "agu_mismatch",
+            "CM:\nLOAD,STRIDED,B16,1\nARF:\n0\n1\nMAX COUNT:\n5\n",
+        );
+        let err = load_agu(&path).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                LoadError::Parse {
+                    line: 3,
+                    column: 1,
+                    ..
+                }
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_program_parse_error() {
+        // opcode 22 does not exist
+        let config = (22u64 << 30).to_binary().to_binary_str();
+        let path = temp_file("PE_bad", &format!("{}\n{}", "0".repeat(64), config));
+        let err = load_program(&path).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                LoadError::Parse {
+                    line: 2,
+                    column: 1,
+                    ..
+                }
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_grid_reports_all_errors() {
+        use crate::sim::{grid::Grid, topology::MemoryTopology};
+        let folder = std::env::temp_dir().join("pace_sim_loader_tests/grid");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("PE-Y0X0"), "0".repeat(64)).unwrap();
+        std::fs::write(folder.join("PE-Y0X1"), "0".repeat(64)).unwrap();
+        // dm0 is missing, agu0 is malformed
+        std::fs::write(folder.join("agu0"), "CM:\nARF:\nMAX COUNT:\n").unwrap();
+        let err = Grid::from_folder(folder.to_str().unwrap(), MemoryTopology::single_sided())
+            .unwrap_err();
+        let errors = err.errors();
+        assert_eq!(errors.len(), 2, "{}", err);
+        assert!(matches!(errors[0], LoadError::MissingFile { .. }));
+        assert!(matches!(errors[1], LoadError::Parse { line: 4, .. }));
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        let errors = vec![
+            LoadError::MissingFile {
+                path: PathBuf::from("dm0"),
+            },
+            LoadError::Multiple(vec![LoadError::MissingFile {
+                path: PathBuf::from("agu1"),
+            }]),
+        ];
+        let err = LoadError::from_errors(errors).unwrap_err();
+        assert_eq!(err.errors().len(), 2);
+        assert_eq!(
+            err.to_string(),
+            "dm0: file is missing\nagu1: file is missing"
+        );
+        assert!(LoadError::from_errors(vec![]).is_ok());
+    }
+}
@@ -3,3 +3,15 @@ pub mod global_mem;
 pub mod grid;
 pub mod pace;
 pub mod dump_header;
+pub mod topology;
+pub mod loader;
+pub mod breakpoint;
+pub mod checkpoint;
+pub mod vcd;
+pub mod stats;
+pub mod route_check;
+pub mod netlist;
+pub mod arch;
+pub mod golden;
+pub mod rtl_trace;
+pub mod fault_injection;
@@ -0,0 +1,209 @@
+//! The propagation of the router signals in a cycle, precomputed as a netlist.
+//! The order of the propagation only depends on the router configurations, i.e. on the PC of every PE,
+//! so it is computed once per combination of PCs and then evaluated in place every cycle.
+//! The netlist follows the multi-hop paths from their sources depth first, in the order of the PEs,
+//! which gives the same signals (and the same first error) as following them recursively every cycle.
+
+use std::rc::Rc;
+
+use crate::isa::router::{RouterInDir, RouterOutDir};
+
+use super::{
+    grid::{Grid, PEIdx, SimulationError},
+    route_check::RouterLoop,
+};
+
+/// One step of the propagation of the router signals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetOp {
+    /// Compute the router outputs of a PE from its registers and input wires
+    Drive { pe: PEIdx },
+    /// Pass the output of `src` to the `direction` input of its neighbour `dst`,
+    /// then compute the router outputs of `dst`
+    Hop {
+        src: PEIdx,
+        dst: PEIdx,
+        direction: RouterInDir,
+    },
+}
+
+/// The ordered propagation of the router signals for one combination of PCs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Netlist {
+    /// The steps in order, with the source PE of their path (the errors are reported on it)
+    pub ops: Vec<(PEIdx, NetOp)>,
+}
+
+impl Netlist {
+    /// Build the netlist of the grid at its current PCs
+    fn build(grid: &Grid) -> Self {
+        let mut ops = Vec::new();
+        for y in 0..grid.shape.y {
+            for x in 0..grid.shape.x {
+                let pe = PEIdx { x, y };
+                let router_config = grid.pes[y][x].current_conf().router_config;
+                if router_config.is_path_source() {
+                    ops.push((pe, NetOp::Drive { pe }));
+                    for output_direction in router_config.find_outputs_from_reg() {
+                        Self::add_hops(grid, &mut ops, pe, pe, output_direction);
+                    }
+                }
+            }
+        }
+        Netlist { ops }
+    }
+
+    /// Add the hop from `src` through its output, and the hops following it
+    fn add_hops(
+        grid: &Grid,
+        ops: &mut Vec<(PEIdx, NetOp)>,
+        source: PEIdx,
+        src: PEIdx,
+        output_direction: RouterOutDir,
+    ) {
+        let dst = grid.output_pe_idx(src, output_direction);
+        let direction = output_direction.opposite_in_dir();
+        ops.push((
+            source,
+            NetOp::Hop {
+                src,
+                dst,
+                direction,
+            },
+        ));
+        let switch_config = grid.pes[dst.y][dst.x]
+            .current_conf()
+            .router_config
+            .switch_config;
+        for next_direction in switch_config.find_output_directions(direction) {
+            Self::add_hops(grid, ops, source, dst, next_direction);
+        }
+    }
+}
+
+impl Grid {
+    /// The netlist of the current PCs, built the first time the combination of PCs is met
+    pub fn netlist(&mut self) -> Result<Rc<Netlist>, RouterLoop> {
+        let pcs: Vec<usize> = self.pes.iter().flatten().map(|pe| pe.pc).collect();
+        if let Some(netlist) = self.netlists.get(&pcs) {
+            return netlist.clone();
+        }
+        // a combinational loop has no propagation order
+        let netlist = match self.find_router_loop() {
+            Some(router_loop) => Err(router_loop),
+            None => Ok(Rc::new(Netlist::build(self))),
+        };
+        self.netlists.insert(pcs, netlist.clone());
+        netlist
+    }
+
+    /// Propagate the router signals of the cycle along the multi-hop paths
+    pub(crate) fn propagate_router_signals(&mut self) -> Result<(), SimulationError> {
+        let netlist = self.netlist().map_err(SimulationError::CombinationalLoop)?;
+        let mut hops = 0;
+        for (source, op) in netlist.ops.iter() {
+            let result = match *op {
+                NetOp::Drive { pe } => self.pes[pe.y][pe.x].update_router_output(),
+                NetOp::Hop {
+                    src,
+                    dst,
+                    direction,
+                } => {
+                    hops += 1;
+                    let value = self.pes[src.y][src.x].output_wire_to(direction);
+                    let dst_pe = &mut self.pes[dst.y][dst.x];
+                    dst_pe
+                        .receive_router_signal(direction, value)
+                        .and_then(|_| dst_pe.update_router_output())
+                }
+            };
+            if let Err(e) = result {
+                self.stats.router_hops += hops;
+                return Err(SimulationError::PEUpdateError(*source, e));
+            }
+        }
+        self.stats.router_hops += hops;
+        self.stats.max_router_hops = self.stats.max_router_hops.max(hops);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        isa::{configuration::Program, pe::PE},
+        sim::{dmem::DataMemory, topology::MemoryTopology},
+    };
+
+    use super::*;
+
+    fn pe(switch: &str) -> PE {
+        let program = format!(
+            "operation: NOP\nswitch_config: {{ {} }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+            switch
+        );
+        PE::new(Program::from_mnemonics(&program).unwrap())
+    }
+
+    #[test]
+    fn test_netlist_multi_hop() {
+        // Y0X0 sends its result east, Y0X1 forwards it south to Y1X1 and back west to Y0X0
+        let mut grid = Grid::new(
+            PEIdx { x: 2, y: 2 },
+            vec![
+                vec![
+                    pe("ALURes -> east_out, EastIn -> alu_op1,"),
+                    pe("WestIn -> south_out, WestIn -> west_out,"),
+                ],
+                vec![pe("Open -> predicate,"), pe("NorthIn -> alu_op1,")],
+            ],
+            vec![DataMemory::new(64)],
+            vec![],
+            MemoryTopology::single_sided(),
+        );
+        let (y0x0, y0x1, y1x1) = (
+            PEIdx { x: 0, y: 0 },
+            PEIdx { x: 1, y: 0 },
+            PEIdx { x: 1, y: 1 },
+        );
+        let netlist = grid.netlist().unwrap();
+        assert_eq!(
+            netlist.ops,
+            vec![
+                (y0x0, NetOp::Drive { pe: y0x0 }),
+                (
+                    y0x0,
+                    NetOp::Hop {
+                        src: y0x0,
+                        dst: y0x1,
+                        direction: RouterInDir::WestIn
+                    }
+                ),
+                (
+                    y0x0,
+                    NetOp::Hop {
+                        src: y0x1,
+                        dst: y1x1,
+                        direction: RouterInDir::NorthIn
+                    }
+                ),
+                (
+                    y0x0,
+                    NetOp::Hop {
+                        src: y0x1,
+                        dst: y0x0,
+                        direction: RouterInDir::EastIn
+                    }
+                ),
+            ]
+        );
+        // built once for the combination of PCs
+        assert!(Rc::ptr_eq(&netlist, &grid.netlist().unwrap()));
+
+        grid.pes[0][0].regs.reg_res = 42;
+        grid.propagate_router_signals().unwrap();
+        assert_eq!(grid.pes[0][0].signals.wire_east_in, Some(42));
+        assert_eq!(grid.pes[1][1].signals.wire_north_in, Some(42));
+        assert_eq!(grid.stats.router_hops, 3);
+    }
+}
@@ -0,0 +1,680 @@
+//! Static validation of the routing of a grid, run on the configurations before the simulation.
+//! The simulation reports routing mistakes late, as a PEUpdateError in the middle of the run or as a panic,
+//! the checker reports them all at once with the PE, the PC and the router field at fault.
+//! The PEs do not run in lock-step (loops), so a neighbour is considered driving an output
+//! if any of its configurations drives it.
+//! The combinational loops of the router depend on the PC of every PE, they are checked on
+//! the combinations of PCs the grid goes through, a BR going both ways.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+};
+
+use strum::IntoEnumIterator;
+
+use crate::isa::{
+    operation::{OpCode, Operation},
+    pe::PE,
+    router::{Direction, RouterInDir, RouterOutDir},
+};
+
+use super::grid::{Grid, PEIdx};
+
+/// A routing mistake in a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub pe: PEIdx,
+    pub pc: usize,
+    /// The field of the router configuration, e.g. switch_config.alu_op1 or input_register_write.north
+    pub field: String,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PE-Y{}X{} pc {} {}: {}",
+            self.pe.y, self.pe.x, self.pc, self.field, self.message
+        )
+    }
+}
+
+/// A combinational loop of the router: outputs forwarding each other's signal around
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouterLoop {
+    /// The outputs of the loop in the order of the signal, each drives an input of the next one's PE
+    pub hops: Vec<(PEIdx, RouterOutDir)>,
+}
+
+impl Display for RouterLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (pe, out_dir) in self.hops.iter() {
+            write!(f, "PE-Y{}X{} {} -> ", pe.y, pe.x, out_name(*out_dir))?;
+        }
+        let (first, _) = self.hops[0];
+        write!(f, "PE-Y{}X{}", first.y, first.x)
+    }
+}
+
+/// The combinational loops found by Grid::check_router_loops
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouterLoopCheck {
+    /// The loops found and the first cycle (from now) each one appears at
+    pub loops: Vec<(usize, RouterLoop)>,
+    /// The search stopped at MAX_PC_COMBINATIONS: the combinations of PCs beyond are not checked
+    pub truncated: bool,
+}
+
+/// The upper bound of PC combinations explored by Grid::check_router_loops
+pub const MAX_PC_COMBINATIONS: usize = 4096;
+
+/// The outputs of a router to the neighbours, the nodes of the switch graph
+const NEIGHBOUR_OUTPUTS: [RouterOutDir; 4] = [
+    RouterOutDir::NorthOut,
+    RouterOutDir::SouthOut,
+    RouterOutDir::WestOut,
+    RouterOutDir::EastOut,
+];
+
+fn out_name(out_dir: RouterOutDir) -> &'static str {
+    switch_field(out_dir).trim_start_matches("switch_config.")
+}
+
+fn switch_field(out_dir: RouterOutDir) -> &'static str {
+    match out_dir {
+        RouterOutDir::PredicateOut => "switch_config.predicate",
+        RouterOutDir::ALUOp1 => "switch_config.alu_op1",
+        RouterOutDir::ALUOp2 => "switch_config.alu_op2",
+        RouterOutDir::EastOut => "switch_config.east_out",
+        RouterOutDir::SouthOut => "switch_config.south_out",
+        RouterOutDir::WestOut => "switch_config.west_out",
+        RouterOutDir::NorthOut => "switch_config.north_out",
+    }
+}
+
+/// The direction of an input of the router, None for the sources inside the PE
+fn in_direction(in_dir: RouterInDir) -> Option<Direction> {
+    match in_dir {
+        RouterInDir::NorthIn => Some(Direction::North),
+        RouterInDir::SouthIn => Some(Direction::South),
+        RouterInDir::WestIn => Some(Direction::West),
+        RouterInDir::EastIn => Some(Direction::East),
+        _ => None,
+    }
+}
+
+fn out_direction(out_dir: RouterOutDir) -> Option<Direction> {
+    match out_dir {
+        RouterOutDir::NorthOut => Some(Direction::North),
+        RouterOutDir::SouthOut => Some(Direction::South),
+        RouterOutDir::WestOut => Some(Direction::West),
+        RouterOutDir::EastOut => Some(Direction::East),
+        _ => None,
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::North => "north",
+        Direction::South => "south",
+        Direction::West => "west",
+        Direction::East => "east",
+    }
+}
+
+/// The output of a PE that drives the input of its neighbour in the opposite direction
+fn facing_output(direction: Direction) -> RouterOutDir {
+    match direction {
+        Direction::North => RouterOutDir::NorthOut,
+        Direction::South => RouterOutDir::SouthOut,
+        Direction::West => RouterOutDir::WestOut,
+        Direction::East => RouterOutDir::EastOut,
+    }
+}
+
+impl Grid {
+    /// The neighbour of a PE in the given direction, None at the edge of the array
+    fn neighbour(&self, pe: PEIdx, direction: Direction) -> Option<PEIdx> {
+        match direction {
+            Direction::North if pe.y > 0 => Some(pe.north()),
+            Direction::South if pe.y + 1 < self.shape.y => Some(pe.south()),
+            Direction::West if pe.x > 0 => Some(pe.west()),
+            Direction::East if pe.x + 1 < self.shape.x => Some(pe.east()),
+            _ => None,
+        }
+    }
+
+    /// Whether any configuration of the PE drives the output
+    fn ever_drives(&self, pe: PEIdx, out_dir: RouterOutDir) -> bool {
+        self.pes[pe.y][pe.x].configurations.iter().any(|conf| {
+            !matches!(
+                conf.router_config.switch_config[out_dir],
+                RouterInDir::Open | RouterInDir::Invalid
+            )
+        })
+    }
+
+    /// Check that the wire coming from the given direction is driven by the neighbour
+    fn check_input_wire(&self, pe: PEIdx, direction: Direction) -> Result<(), String> {
+        let name = direction_name(direction);
+        match self.neighbour(pe, direction) {
+            None => Err(format!("reads the {} input at the edge of the array", name)),
+            Some(neighbour) => {
+                let output = facing_output(direction.opposite());
+                if self.ever_drives(neighbour, output) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "reads the {} input but PE-Y{}X{} never drives its {}",
+                        name,
+                        neighbour.y,
+                        neighbour.x,
+                        out_name(output)
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Statically check the routing of all the configurations of all the PEs.
+    /// Return the mistakes found, empty if the routing is valid.
+    pub fn check_routing(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for y in 0..self.shape.y {
+            for x in 0..self.shape.x {
+                let pe_idx = PEIdx { x, y };
+                let pe = &self.pes[y][x];
+                // the input registers written by any configuration
+                let ever_written = |direction: Direction| {
+                    pe.configurations
+                        .iter()
+                        .any(|conf| conf.router_config.input_register_write[direction])
+                };
+
+                for (pc, conf) in pe.configurations.iter().enumerate() {
+                    let router_config = conf.router_config;
+                    let mut report = |field: String, message: String| {
+                        diagnostics.push(Diagnostic {
+                            pe: pe_idx,
+                            pc,
+                            field,
+                            message,
+                        })
+                    };
+
+                    for out_dir in RouterOutDir::iter() {
+                        let field = switch_field(out_dir).to_string();
+                        let in_dir = router_config.switch_config[out_dir];
+                        if in_dir == RouterInDir::Invalid {
+                            report(field, "invalid switch code".to_string());
+                            continue;
+                        }
+                        if in_dir == RouterInDir::Open {
+                            continue;
+                        }
+                        if let Some(direction) = out_direction(out_dir)
+                            && self.neighbour(pe_idx, direction).is_none()
+                        {
+                            report(
+                                field.clone(),
+                                format!("routes {} out of the array", direction_name(direction)),
+                            );
+                        }
+                        let Some(direction) = in_direction(in_dir) else {
+                            continue;
+                        };
+                        if router_config.input_register_used[direction] {
+                            if !ever_written(direction) {
+                                report(
+                                    field,
+                                    format!(
+                                        "reads reg_{}_in, which no configuration writes",
+                                        direction_name(direction)
+                                    ),
+                                );
+                            }
+                        } else if let Err(message) = self.check_input_wire(pe_idx, direction) {
+                            report(field, message);
+                        }
+                    }
+
+                    let write = router_config.input_register_write;
+                    for (direction, enabled) in [
+                        (Direction::North, write.north),
+                        (Direction::South, write.south),
+                        (Direction::West, write.west),
+                        (Direction::East, write.east),
+                    ] {
+                        if !enabled {
+                            continue;
+                        }
+                        if let Err(message) = self.check_input_wire(pe_idx, direction) {
+                            report(
+                                format!("input_register_write.{}", direction_name(direction)),
+                                message,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+impl Grid {
+    /// Find a combinational loop in the router with the current PCs
+    pub fn find_router_loop(&self) -> Option<RouterLoop> {
+        let pcs: Vec<Vec<usize>> = self
+            .pes
+            .iter()
+            .map(|row| row.iter().map(|pe| pe.pc).collect())
+            .collect();
+        self.find_router_loop_at(&pcs)
+    }
+
+    /// Find a combinational loop in the router with the given PC of every PE, indexed by [y][x].
+    /// An output forwarding an input is driven by the output of the neighbour on that side,
+    /// so every output has at most one driver and a loop is found by following the drivers.
+    pub fn find_router_loop_at(&self, pcs: &[Vec<usize>]) -> Option<RouterLoop> {
+        let node = |pe: PEIdx, out_dir: RouterOutDir| {
+            let out = NEIGHBOUR_OUTPUTS
+                .iter()
+                .position(|o| *o == out_dir)
+                .unwrap();
+            (pe.y * self.shape.x + pe.x) * 4 + out
+        };
+        let driver = |pe: PEIdx, out_dir: RouterOutDir| {
+            let conf = self.pes[pe.y][pe.x].configurations.get(pcs[pe.y][pe.x])?;
+            let direction = in_direction(conf.router_config.switch_config[out_dir])?;
+            let neighbour = self.neighbour(pe, direction)?;
+            Some((neighbour, facing_output(direction.opposite())))
+        };
+
+        // None: not visited, Some(false): on the current chain of drivers, Some(true): no loop through it
+        let mut visited = vec![None; self.shape.x * self.shape.y * 4];
+        let mut chain = Vec::new();
+        for y in 0..self.shape.y {
+            for x in 0..self.shape.x {
+                for out_dir in NEIGHBOUR_OUTPUTS {
+                    let mut current = Some((PEIdx { x, y }, out_dir));
+                    while let Some((pe, out_dir)) = current {
+                        match visited[node(pe, out_dir)] {
+                            None => {
+                                visited[node(pe, out_dir)] = Some(false);
+                                chain.push((pe, out_dir));
+                                current = driver(pe, out_dir);
+                            }
+                            Some(false) => {
+                                // back on the chain: the drivers from there form a loop
+                                let start = chain.iter().position(|hop| *hop == (pe, out_dir));
+                                let mut hops = chain.split_off(start.unwrap());
+                                // the chain goes against the signal
+                                hops.reverse();
+                                return Some(RouterLoop { hops });
+                            }
+                            Some(true) => break,
+                        }
+                    }
+                    for (pe, out_dir) in chain.drain(..) {
+                        visited[node(pe, out_dir)] = Some(true);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Statically check the combinational loops of the router, for every combination of PCs the grid
+    /// goes through from its current state. The PCs only depend on the data through BR, whose
+    /// predicate is not known statically, so both the taken and the not taken branches are followed.
+    /// At most MAX_PC_COMBINATIONS combinations are checked, the check is then marked as truncated.
+    pub fn check_router_loops(&self) -> RouterLoopCheck {
+        let initial: Vec<PcState> = self.pes.iter().flatten().map(PcState::of).collect();
+        let mut seen = HashSet::from([initial.clone()]);
+        let mut check = RouterLoopCheck::default();
+        // breadth first, so that a loop is reported at the first cycle it can appear
+        let mut queue = VecDeque::from([(0, initial)]);
+        'search: while let Some((cycle, state)) = queue.pop_front() {
+            let pes = self.pes.iter().flatten();
+            // the simulation cannot go on either
+            if pes
+                .clone()
+                .zip(state.iter())
+                .any(|(pe, pc_state)| pc_state.pc >= pe.configurations.len())
+            {
+                continue;
+            }
+            let pcs: Vec<Vec<usize>> = state
+                .chunks(self.shape.x)
+                .map(|row| row.iter().map(|pc_state| pc_state.pc).collect())
+                .collect();
+            if let Some(router_loop) = self.find_router_loop_at(&pcs)
+                && !check.loops.iter().any(|(_, l)| *l == router_loop)
+            {
+                check.loops.push((cycle, router_loop));
+            }
+            // the next PC states of every PE, two for a BR going different ways
+            let choices: Vec<Vec<PcState>> = pes
+                .zip(state.iter())
+                .map(|(pe, pc_state)| {
+                    pc_state.successors(&pe.configurations[pc_state.pc].operation)
+                })
+                .collect();
+            // enumerate the combinations one by one, the number of BRs is not bounded
+            let mut digits = vec![0; choices.len()];
+            'combinations: loop {
+                if seen.len() >= MAX_PC_COMBINATIONS {
+                    check.truncated = true;
+                    break 'search;
+                }
+                let next: Vec<PcState> = choices
+                    .iter()
+                    .zip(digits.iter())
+                    .map(|(choice, digit)| choice[*digit])
+                    .collect();
+                if seen.insert(next.clone()) {
+                    queue.push_back((cycle + 1, next));
+                }
+                for (digit, choice) in digits.iter_mut().zip(choices.iter()) {
+                    *digit += 1;
+                    if *digit < choice.len() {
+                        continue 'combinations;
+                    }
+                    *digit = 0;
+                }
+                break;
+            }
+        }
+        check
+    }
+}
+
+/// The state of a PE that drives its PC, the rest of the PE does not matter to the router loops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PcState {
+    pc: usize,
+    /// Whether the previous operation was a JUMP, None before the first cycle
+    previous_jump: Option<bool>,
+    loop_start: u8,
+    loop_end: u8,
+}
+
+impl PcState {
+    fn of(pe: &PE) -> Self {
+        PcState {
+            pc: pe.pc,
+            previous_jump: pe.previous_op.map(|op| op.is_jump()),
+            loop_start: pe.regs.reg_loop_start,
+            loop_end: pe.regs.reg_loop_end,
+        }
+    }
+
+    /// The states after executing the operation, following PE::update_loop_registers and
+    /// PE::next_conf: one, or two for a BR whose destination is not the next PC anyway
+    fn successors(&self, operation: &Operation) -> Vec<PcState> {
+        let (loop_start, loop_end) = if operation.op_code == OpCode::JUMP {
+            (operation.loop_start.unwrap(), operation.loop_end.unwrap())
+        } else {
+            (self.loop_start, self.loop_end)
+        };
+        let next = |taken: bool| {
+            let pc = if operation.is_jump() && self.previous_jump != Some(true) {
+                operation.immediate.unwrap() as usize
+            } else if operation.op_code == OpCode::BR && taken {
+                operation.immediate.expect("Branch destination must be set") as usize
+            } else if self.pc >= loop_end as usize || self.pc < loop_start as usize {
+                loop_start as usize
+            } else {
+                self.pc + 1
+            };
+            PcState {
+                pc,
+                previous_jump: Some(operation.is_jump()),
+                loop_start,
+                loop_end,
+            }
+        };
+        let mut successors = vec![next(false)];
+        if operation.op_code == OpCode::BR && next(true) != successors[0] {
+            successors.push(next(true));
+        }
+        successors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        agu::agu::AGU,
+        isa::{configuration::Program, pe::PE},
+        sim::{dmem::DataMemory, grid::SimulationError, topology::MemoryTopology},
+    };
+
+    use super::*;
+
+    /// A PE with a single NOP configuration routed as given
+    fn pe(switch: &str, used: &str, write: &str) -> PE {
+        let program = format!(
+            "operation: NOP\nswitch_config: {{ {} }};\ninput_register_used: {{{}}};\ninput_register_write: {{{}}};\n",
+            switch, used, write
+        );
+        PE::new(Program::from_mnemonics(&program).unwrap())
+    }
+
+    /// A PE looping over two NOP configurations routed as given
+    fn looping_pe(switches: [&str; 2]) -> PE {
+        let mut program =
+            String::from("operation: JUMP [1, 2]\nswitch_config: { Open -> predicate, };\n");
+        program.push_str("input_register_used: {};\ninput_register_write: {};\n");
+        for switch in switches {
+            program.push_str(&format!(
+                "operation: NOP\nswitch_config: {{ {} }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+                switch
+            ));
+        }
+        PE::new(Program::from_mnemonics(&program).unwrap())
+    }
+
+    fn grid(pes: Vec<Vec<PE>>) -> Grid {
+        Grid::new(
+            PEIdx { x: 2, y: 2 },
+            pes,
+            vec![DataMemory::new(64)],
+            vec![],
+            MemoryTopology::single_sided(),
+        )
+    }
+
+    #[test]
+    fn test_check_routing_valid() {
+        // Y0X0 sends its result east, Y0X1 latches it and reads the register
+        let grid = grid(vec![
+            vec![
+                pe("ALURes -> east_out,", "", ""),
+                pe("WestIn -> alu_op1,", "west", "west"),
+            ],
+            vec![
+                pe("Open -> predicate,", "", ""),
+                pe("Open -> predicate,", "", ""),
+            ],
+        ]);
+        assert_eq!(grid.check_routing(), vec![]);
+    }
+
+    #[test]
+    fn test_check_routing_mistakes() {
+        let mut grid = grid(vec![
+            vec![
+                pe("ALURes -> north_out,", "", ""),
+                pe("WestIn -> alu_op1,", "", ""),
+            ],
+            vec![
+                pe("NorthIn -> alu_op2,", "north", ""),
+                pe("Open -> predicate,", "", "east"),
+            ],
+        ]);
+        grid.pes[1][1].configurations[0]
+            .router_config
+            .switch_config
+            .alu_op1 = RouterInDir::Invalid;
+        let diagnostics: Vec<String> = grid.check_routing().iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "PE-Y0X0 pc 0 switch_config.north_out: routes north out of the array",
+                "PE-Y0X1 pc 0 switch_config.alu_op1: reads the west input but PE-Y0X0 never drives its east_out",
+                "PE-Y1X0 pc 0 switch_config.alu_op2: reads reg_north_in, which no configuration writes",
+                "PE-Y1X1 pc 0 switch_config.alu_op1: invalid switch code",
+                "PE-Y1X1 pc 0 input_register_write.east: reads the east input at the edge of the array",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_router_loop() {
+        // Y0X0 forwards its east input east, Y0X1 forwards its west input west
+        let grid = grid(vec![
+            vec![
+                pe("EastIn -> east_out,", "", ""),
+                pe("WestIn -> west_out,", "", ""),
+            ],
+            vec![
+                pe("Open -> predicate,", "", ""),
+                pe("Open -> predicate,", "", ""),
+            ],
+        ]);
+        let router_loop = grid.find_router_loop().unwrap();
+        assert_eq!(
+            router_loop.to_string(),
+            "PE-Y0X1 west_out -> PE-Y0X0 east_out -> PE-Y0X1"
+        );
+        assert_eq!(grid.check_router_loops().loops, vec![(0, router_loop)]);
+    }
+
+    /// Y0X0 forwards its east input east and Y0X1 its west input west, at the given PCs
+    fn grid_with_loop_at(pcs: [usize; 2]) -> Grid {
+        let open = "Open -> predicate,";
+        let switches = |pc: usize, switch| {
+            if pc == 1 {
+                [switch, open]
+            } else {
+                [open, switch]
+            }
+        };
+        grid(vec![
+            vec![
+                looping_pe(switches(pcs[0], "EastIn -> east_out,")),
+                looping_pe(switches(pcs[1], "WestIn -> west_out,")),
+            ],
+            vec![looping_pe([open, open]), looping_pe([open, open])],
+        ])
+    }
+
+    #[test]
+    fn test_check_router_loops_pcs() {
+        // both PEs at PC 2 at cycles 2, 4, ...
+        let grid = grid_with_loop_at([2, 2]);
+        assert_eq!(grid.find_router_loop(), None);
+        let check = grid.check_router_loops();
+        assert_eq!(check.loops.len(), 1);
+        assert_eq!(check.loops[0].0, 2);
+        assert!(!check.truncated);
+
+        // never at PC 1 and 2 together
+        let grid = grid_with_loop_at([1, 2]);
+        assert_eq!(grid.check_router_loops(), RouterLoopCheck::default());
+    }
+
+    #[test]
+    fn test_check_router_loops_branch() {
+        // Y0X0 only forwards its east input east once its BR is taken
+        let open = "Open -> predicate,";
+        let mut program = String::new();
+        for (operation, switch) in [
+            ("JUMP [1, 2]", open),
+            ("BR 3", open),
+            ("NOP", open),
+            ("NOP", "EastIn -> east_out,"),
+        ] {
+            program.push_str(&format!(
+                "operation: {}\nswitch_config: {{ {} }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+                operation, switch
+            ));
+        }
+        let grid = grid(vec![
+            vec![
+                PE::new(Program::from_mnemonics(&program).unwrap()),
+                looping_pe(["WestIn -> west_out,", "WestIn -> west_out,"]),
+            ],
+            vec![looping_pe([open, open]), looping_pe([open, open])],
+        ]);
+        let check = grid.check_router_loops();
+        assert_eq!(check.loops.len(), 1);
+        assert_eq!(check.loops[0].0, 2);
+        assert!(!check.truncated);
+    }
+
+    #[test]
+    fn test_check_router_loops_many_branches() {
+        // 16 BRs at the same PC, 2^16 combinations at every cycle, the search stops at the bound
+        let mut program = String::new();
+        for operation in ["JUMP [1, 3]", "BR 1", "BR 2", "NOP"] {
+            program.push_str(&format!(
+                "operation: {}\nswitch_config: {{ Open -> predicate, }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+                operation
+            ));
+        }
+        let pes = (0..4)
+            .map(|_| {
+                (0..4)
+                    .map(|_| PE::new(Program::from_mnemonics(&program).unwrap()))
+                    .collect()
+            })
+            .collect();
+        let grid = Grid::new(
+            PEIdx { x: 4, y: 4 },
+            pes,
+            vec![DataMemory::new(64), DataMemory::new(64)],
+            vec![],
+            MemoryTopology::single_sided(),
+        );
+        let check = grid.check_router_loops();
+        assert_eq!(check.loops, vec![]);
+        assert!(check.truncated);
+    }
+
+    #[test]
+    fn test_simulate_router_loop() {
+        let open = "Open -> predicate,";
+        let mem_pe = |switch: &str| {
+            let program = format!(
+                "operation: NOP\nswitch_config: {{ {} }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+                switch
+            );
+            PE::new_mem_pe(Program::from_mnemonics(&program).unwrap())
+        };
+        let agu = || AGU::from_mnemonics("CM: LOAD,STRIDED,B16,1 ARF: 0 MAX COUNT: 100").unwrap();
+        let mut grid = Grid::new(
+            PEIdx { x: 2, y: 2 },
+            vec![
+                vec![
+                    mem_pe("EastIn -> east_out,"),
+                    pe("WestIn -> west_out,", "", ""),
+                ],
+                vec![mem_pe(open), pe(open, "", "")],
+            ],
+            vec![DataMemory::new(64)],
+            vec![agu(), agu()],
+            MemoryTopology::single_sided(),
+        );
+        match grid.simulate_cycle() {
+            Err(SimulationError::CombinationalLoop(router_loop)) => {
+                assert_eq!(router_loop.hops.len(), 2);
+            }
+            result => panic!("Expected a combinational loop, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Traces of the RTL simulation, to run the simulator in lock-step with it.
+//! A trace has one event per line, `#` starts a comment, numbers are decimal or 0x hexadecimal:
+//! - `12 PE-Y1X0 pc 3`: the PE executes the configuration at PC 3 in cycle 12
+//! - `12 PE-Y1X0 reg_res 0x10`: the register of the PE holds the value at the end of cycle 12
+//! - `12 dm1 write 0x8 0x1234`: a port of the DM writes the value at the address in cycle 12
+//!
+//! Only the PCs and registers given are compared. The DM writes of a cycle are all compared, for
+//! every cycle up to the last one of the trace.
+
+use std::{collections::BTreeMap, fmt::Display, path::Path, str::FromStr};
+
+use strum::IntoEnumIterator;
+
+use crate::isa::pe::PERegister;
+
+use super::{
+    grid::{Grid, PEIdx},
+    loader::{self, LoadError},
+};
+
+/// One line of a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    Pc {
+        pe: PEIdx,
+        pc: usize,
+    },
+    Reg {
+        pe: PEIdx,
+        reg: PERegister,
+        value: u64,
+    },
+    DmWrite {
+        dm: usize,
+        addr: u64,
+        value: u64,
+    },
+}
+
+impl TraceEvent {
+    fn parse_number(s: &str) -> Result<u64, String> {
+        match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse::<u64>(),
+        }
+        .map_err(|_| format!("Invalid number `{}`", s))
+    }
+
+    fn parse_pe(s: &str) -> Option<PEIdx> {
+        let (y, x) = s.strip_prefix("PE-Y")?.split_once('X')?;
+        Some(PEIdx {
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+        })
+    }
+}
+
+impl FromStr for TraceEvent {
+    type Err = String;
+
+    /// Parse an event without its cycle, e.g. `PE-Y1X0 reg_res 0x10`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.is_empty() {
+            return Err("Empty event, expecting e.g. PE-Y1X0 pc 3".to_string());
+        }
+        if let Some(pe) = fields.first().and_then(|f| Self::parse_pe(f)) {
+            return match fields[1..] {
+                ["pc", pc] => Ok(TraceEvent::Pc {
+                    pe,
+                    pc: Self::parse_number(pc)? as usize,
+                }),
+                [reg, value] => Ok(TraceEvent::Reg {
+                    pe,
+                    reg: PERegister::from_str(reg)
+                        .map_err(|_| format!("Unknown register `{}`", reg))?,
+                    value: Self::parse_number(value)?,
+                }),
+                _ => Err(format!(
+                    "Invalid PE event `{}`, expecting e.g. PE-Y1X0 reg_res 0x10",
+                    s
+                )),
+            };
+        }
+        let dm = fields
+            .first()
+            .and_then(|f| f.strip_prefix("dm"))
+            .and_then(|dm| dm.parse::<usize>().ok());
+        match (dm, &fields[1..]) {
+            (Some(dm), ["write", addr, value]) => Ok(TraceEvent::DmWrite {
+                dm,
+                addr: Self::parse_number(addr)?,
+                value: Self::parse_number(value)?,
+            }),
+            _ => Err(format!(
+                "Invalid event `{}`, expecting e.g. PE-Y1X0 pc 3 or dm1 write 0x8 0x1234",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceEvent::Pc { pe, pc } => write!(f, "PE-Y{}X{} pc {}", pe.y, pe.x, pc),
+            TraceEvent::Reg { pe, reg, value } => {
+                write!(f, "PE-Y{}X{} {} 0x{:x}", pe.y, pe.x, reg, value)
+            }
+            TraceEvent::DmWrite { dm, addr, value } => {
+                write!(f, "dm{} write 0x{:x} 0x{:x}", dm, addr, value)
+            }
+        }
+    }
+}
+
+/// The first difference between the RTL trace and the simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub cycle: usize,
+    /// What differs, e.g. `PE-Y1X0 reg_res` or `dm1 writes`
+    pub signal: String,
+    pub rtl: String,
+    pub sim: String,
+    /// The PE and the mnemonics of the configuration it executes in the simulation
+    pub conf: Option<(PEIdx, String)>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cycle {}: {} differs, RTL {}, simulation {}",
+            self.cycle, self.signal, self.rtl, self.sim
+        )?;
+        if let Some((pe, conf)) = &self.conf {
+            write!(f, "\nPE-Y{}X{} executes:\n{}", pe.y, pe.x, conf)?;
+        }
+        Ok(())
+    }
+}
+
+/// The events of an RTL simulation by cycle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RtlTrace {
+    pub cycles: BTreeMap<usize, Vec<TraceEvent>>,
+}
+
+impl RtlTrace {
+    /// Load a trace, the first invalid line is reported
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let content = loader::read_file(path)?;
+        let mut trace = RtlTrace::default();
+        for (line_idx, line) in content.lines().enumerate() {
+            let line_content = line.split('#').next().unwrap().trim();
+            if line_content.is_empty() {
+                continue;
+            }
+            let parse_error = |message: String| LoadError::Parse {
+                path: path.to_path_buf(),
+                line: line_idx + 1,
+                column: line.len() - line.trim_start().len() + 1,
+                message,
+            };
+            let (cycle, event) = line_content
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| {
+                    parse_error(format!(
+                        "Expected a cycle and an event, got `{}`",
+                        line_content
+                    ))
+                })?;
+            let cycle = TraceEvent::parse_number(cycle).map_err(parse_error)?;
+            let event = event.parse::<TraceEvent>().map_err(parse_error)?;
+            trace.push(cycle as usize, event);
+        }
+        Ok(trace)
+    }
+
+    pub fn push(&mut self, cycle: usize, event: TraceEvent) {
+        self.cycles.entry(cycle).or_default().push(event);
+    }
+
+    /// The last cycle of the trace
+    pub fn last_cycle(&self) -> Option<usize> {
+        self.cycles.keys().next_back().copied()
+    }
+
+    /// Check that the PEs and DMs of the trace exist in the grid
+    pub fn validate(&self, grid: &Grid) -> Result<(), String> {
+        for (cycle, event) in self.events() {
+            let exists = match event {
+                TraceEvent::Pc { pe, .. } | TraceEvent::Reg { pe, .. } => {
+                    pe.x < grid.shape.x && pe.y < grid.shape.y
+                }
+                TraceEvent::DmWrite { dm, .. } => *dm < grid.dmems.len(),
+            };
+            if !exists {
+                return Err(format!("cycle {}: `{}` is not in the grid", cycle, event));
+            }
+        }
+        Ok(())
+    }
+
+    fn events(&self) -> impl Iterator<Item = (usize, &TraceEvent)> {
+        self.cycles
+            .iter()
+            .flat_map(|(cycle, events)| events.iter().map(move |event| (*cycle, event)))
+    }
+
+    /// The DM writes of the grid in the current cycle, by DM and address
+    fn dm_writes(grid: &Grid) -> Vec<(usize, u64, u64)> {
+        let mut writes = Vec::new();
+        for (dm, dmem) in grid.dmems.iter().enumerate() {
+            for port in [1, 2] {
+                let interface = dmem.port(port);
+                if let (Some(addr), Some(data)) =
+                    (interface.wire_dmem_addr, interface.wire_dmem_data)
+                    && interface.mode.is_store()
+                {
+                    let bits = interface.mode.num_bytes() * 8;
+                    let mask = if bits == 64 {
+                        u64::MAX
+                    } else {
+                        (1 << bits) - 1
+                    };
+                    writes.push((dm, addr, data & mask));
+                }
+            }
+        }
+        writes.sort();
+        writes
+    }
+
+    /// Record the PCs, the registers of every PE and the DM writes of the current cycle of the grid,
+    /// e.g. to write the trace of the simulation and compare it with the RTL one
+    pub fn record(&mut self, grid: &Grid) {
+        for y in 0..grid.shape.y {
+            for x in 0..grid.shape.x {
+                let pe = PEIdx { x, y };
+                let regs = &grid.pes[y][x].regs;
+                self.push(
+                    grid.cycle,
+                    TraceEvent::Pc {
+                        pe,
+                        pc: grid.pes[y][x].pc,
+                    },
+                );
+                for reg in PERegister::iter() {
+                    let value = regs.get(reg);
+                    self.push(grid.cycle, TraceEvent::Reg { pe, reg, value });
+                }
+            }
+        }
+        for (dm, addr, value) in Self::dm_writes(grid) {
+            self.push(grid.cycle, TraceEvent::DmWrite { dm, addr, value });
+        }
+    }
+
+    /// Compare the current cycle of the grid, after Grid::simulate_cycle, with the trace
+    pub fn check(&self, grid: &Grid) -> Result<(), Divergence> {
+        let cycle = grid.cycle;
+        let divergence = |signal: String, rtl: String, sim: String, pe: Option<PEIdx>| Divergence {
+            cycle,
+            signal,
+            rtl,
+            sim,
+            conf: pe.map(|pe| (pe, grid.pes[pe.y][pe.x].current_conf().to_mnemonics())),
+        };
+        let events = self
+            .cycles
+            .get(&cycle)
+            .map_or(&[][..], |events| &events[..]);
+        let mut rtl_writes = Vec::new();
+        for event in events {
+            match *event {
+                TraceEvent::Pc { pe, pc } => {
+                    let sim = grid.pes[pe.y][pe.x].pc;
+                    if sim != pc {
+                        return Err(divergence(
+                            format!("PE-Y{}X{} pc", pe.y, pe.x),
+                            pc.to_string(),
+                            sim.to_string(),
+                            Some(pe),
+                        ));
+                    }
+                }
+                TraceEvent::Reg { pe, reg, value } => {
+                    let sim = grid.pes[pe.y][pe.x].regs.get(reg);
+                    if sim != value {
+                        return Err(divergence(
+                            format!("PE-Y{}X{} {}", pe.y, pe.x, reg),
+                            format!("0x{:x}", value),
+                            format!("0x{:x}", sim),
+                            Some(pe),
+                        ));
+                    }
+                }
+                TraceEvent::DmWrite { dm, addr, value } => rtl_writes.push((dm, addr, value)),
+            }
+        }
+        if self.last_cycle().is_none_or(|last| cycle > last) {
+            return Ok(());
+        }
+        rtl_writes.sort();
+        let sim_writes = Self::dm_writes(grid);
+        for dm in 0..grid.dmems.len() {
+            let of_dm = |writes: &[(usize, u64, u64)]| {
+                writes
+                    .iter()
+                    .filter(|w| w.0 == dm)
+                    .map(|(_, addr, value)| format!("0x{:x} at 0x{:x}", value, addr))
+                    .collect::<Vec<String>>()
+            };
+            let (rtl, sim) = (of_dm(&rtl_writes), of_dm(&sim_writes));
+            if rtl != sim {
+                let list = |writes: Vec<String>| match writes.is_empty() {
+                    true => "no write".to_string(),
+                    false => writes.join(", "),
+                };
+                return Err(divergence(
+                    format!("dm{} writes", dm),
+                    list(rtl),
+                    list(sim),
+                    None,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for RtlTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (cycle, event) in self.events() {
+            writeln!(f, "{} {}", cycle, event)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_event() {
+        let pe = PEIdx { x: 0, y: 1 };
+        assert_eq!("PE-Y1X0 pc 3".parse(), Ok(TraceEvent::Pc { pe, pc: 3 }));
+        assert_eq!(
+            "PE-Y1X0  reg_res 0x10".parse(),
+            Ok(TraceEvent::Reg {
+                pe,
+                reg: PERegister::RegRes,
+                value: 16
+            })
+        );
+        assert_eq!(
+            "dm1 write 0x8 4660".parse(),
+            Ok(TraceEvent::DmWrite {
+                dm: 1,
+                addr: 8,
+                value: 0x1234
+            })
+        );
+        for event in [
+            "PE-Y1X0 reg_res 0x10",
+            "PE-Y1X0 pc 3",
+            "dm1 write 0x8 0x1234",
+        ] {
+            assert_eq!(event.parse::<TraceEvent>().unwrap().to_string(), event);
+        }
+        assert!("PE-Y1X0 reg_foo 1".parse::<TraceEvent>().is_err());
+        assert!("PE-Y1X0 pc".parse::<TraceEvent>().is_err());
+        assert!("dm1 read 0x8".parse::<TraceEvent>().is_err());
+        assert!("dm1 write 0x8 0xg".parse::<TraceEvent>().is_err());
+        assert!("".parse::<TraceEvent>().is_err());
+        assert!(" \t ".parse::<TraceEvent>().is_err());
+    }
+}
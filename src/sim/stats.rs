@@ -0,0 +1,160 @@
+//! Performance counters of a grid, updated by Grid::simulate_cycle since the grid was created
+
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::isa::operation::OpCode;
+
+use super::{dmem::DMemMode, grid::PEIdx, topology::MemPort};
+
+/// The counters of one PE
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PEStats {
+    /// The executed ALU operations (arithmetic/logic and SIMD), by opcode, not the predicated off ones
+    pub ops: HashMap<OpCode, u64>,
+    pub scalar_ops: u64,
+    pub simd_ops: u64,
+    /// The number of writes to reg_res
+    pub res_writes: u64,
+}
+
+impl PEStats {
+    pub fn alu_ops(&self) -> u64 {
+        self.scalar_ops + self.simd_ops
+    }
+}
+
+/// The counters of one DM port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortStats {
+    pub dm: usize,
+    pub port: usize,
+    /// The number of accesses by mode, NOP cycles are not counted
+    pub accesses: HashMap<DMemMode, u64>,
+}
+
+impl PortStats {
+    pub fn reads(&self) -> u64 {
+        self.accesses
+            .iter()
+            .filter(|(mode, _)| mode.is_load())
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    pub fn writes(&self) -> u64 {
+        self.accesses
+            .iter()
+            .filter(|(mode, _)| mode.is_store())
+            .map(|(_, n)| n)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// The number of fully simulated cycles
+    pub cycles: u64,
+    /// Indexed by [y][x]
+    pub pes: Vec<Vec<PEStats>>,
+    /// The number of router hops, i.e. signals passed from a PE to its neighbour
+    pub router_hops: u64,
+    /// The maximum number of router hops in one cycle
+    pub max_router_hops: u64,
+    /// In the order of Grid::mem_ports
+    pub ports: Vec<PortStats>,
+    /// The number of completed iterations (count increments) of each AGU
+    pub agu_iterations: Vec<u64>,
+}
+
+impl Stats {
+    pub fn new(shape: PEIdx, mem_ports: &[MemPort], num_agus: usize) -> Self {
+        Stats {
+            cycles: 0,
+            pes: vec![vec![PEStats::default(); shape.x]; shape.y],
+            router_hops: 0,
+            max_router_hops: 0,
+            ports: mem_ports
+                .iter()
+                .map(|p| PortStats {
+                    dm: p.dm,
+                    port: p.port,
+                    accesses: HashMap::new(),
+                })
+                .collect(),
+            agu_iterations: vec![0; num_agus],
+        }
+    }
+
+    /// The percentage of cycles in which the PE executed an ALU operation
+    pub fn utilization(&self, pe: PEIdx) -> f64 {
+        if self.cycles == 0 {
+            return 0.0;
+        }
+        self.pes[pe.y][pe.x].alu_ops() as f64 * 100.0 / self.cycles as f64
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Cycles: {}", self.cycles)?;
+        writeln!(
+            f,
+            "Router hops: {} ({:.2} per cycle, max {})",
+            self.router_hops,
+            self.router_hops as f64 / self.cycles.max(1) as f64,
+            self.max_router_hops
+        )?;
+        writeln!(f, "PEs (utilization = ALU ops / cycles):")?;
+        let mut idle = 0;
+        for (y, row) in self.pes.iter().enumerate() {
+            for (x, pe) in row.iter().enumerate() {
+                if pe.alu_ops() == 0 {
+                    idle += 1;
+                    continue;
+                }
+                let mut ops: Vec<(&OpCode, &u64)> = pe.ops.iter().collect();
+                ops.sort_by_key(|(op, n)| (std::cmp::Reverse(**n), op.to_string()));
+                let ops: Vec<String> = ops.iter().map(|(op, n)| format!("{} {}", op, n)).collect();
+                writeln!(
+                    f,
+                    "  PE-Y{}X{}: {:.1}%, {} scalar, {} SIMD, {} res writes ({})",
+                    y,
+                    x,
+                    self.utilization(PEIdx { x, y }),
+                    pe.scalar_ops,
+                    pe.simd_ops,
+                    pe.res_writes,
+                    ops.join(", ")
+                )?;
+            }
+        }
+        writeln!(f, "  {} PEs without ALU operation", idle)?;
+        writeln!(f, "DM ports:")?;
+        for port in self.ports.iter() {
+            let mut modes: Vec<(&DMemMode, &u64)> = port.accesses.iter().collect();
+            modes.sort_by_key(|(mode, _)| mode.to_string());
+            let modes: Vec<String> = modes
+                .iter()
+                .map(|(mode, n)| format!("{} {}", mode, n))
+                .collect();
+            writeln!(
+                f,
+                "  dm{} port{}: {} reads, {} writes ({})",
+                port.dm,
+                port.port,
+                port.reads(),
+                port.writes(),
+                modes.join(", ")
+            )?;
+        }
+        let agus: Vec<String> = self
+            .agu_iterations
+            .iter()
+            .enumerate()
+            .map(|(i, n)| format!("agu{} {}", i, n))
+            .collect();
+        write!(f, "AGU iterations: {}", agus.join(", "))
+    }
+}
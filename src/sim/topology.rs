@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use super::grid::PEIdx;
+
+/// The edge of the array that carries memory PEs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemEdge {
+    /// The first column (x = 0), from top to bottom
+    West,
+    /// The last column (x = X-1), from top to bottom
+    East,
+    /// The first row (y = 0), from left to right
+    North,
+    /// The last row (y = Y-1), from left to right
+    South,
+}
+
+impl MemEdge {
+    /// The PEs along this edge, in the order they are attached to the memories
+    pub fn pes(&self, shape: PEIdx) -> Vec<PEIdx> {
+        match self {
+            MemEdge::West => (0..shape.y).map(|y| PEIdx { x: 0, y }).collect(),
+            MemEdge::East => (0..shape.y).map(|y| PEIdx { x: shape.x - 1, y }).collect(),
+            MemEdge::North => (0..shape.x).map(|x| PEIdx { x, y: 0 }).collect(),
+            MemEdge::South => (0..shape.x).map(|x| PEIdx { x, y: shape.y - 1 }).collect(),
+        }
+    }
+}
+
+/// How the AGUs are numbered (and so how the aguN files are named)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AguIndexing {
+    /// All the AGUs of the first edge, then all the AGUs of the second edge, ...
+    EdgeMajor,
+    /// The first PE of every edge, then the second PE of every edge, ...
+    Interleaved,
+}
+
+/// Description of where the data memories are attached to the array
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryTopology {
+    /// The edges carrying memory PEs. DMs are numbered edge by edge in this order.
+    pub edges: Vec<MemEdge>,
+    /// The number of consecutive edge PEs sharing one DM (1 or 2, a DM has 2 ports)
+    pub pes_per_dm: usize,
+    pub agu_indexing: AguIndexing,
+}
+
+/// The named topologies, as selected on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TopologyPreset {
+    /// Memories on the left and right edges
+    DoubleSided,
+    /// Memories on the left edge only
+    SingleSided,
+    /// Memories on the top and bottom edges
+    NorthSouth,
+}
+
+impl From<TopologyPreset> for MemoryTopology {
+    fn from(preset: TopologyPreset) -> Self {
+        match preset {
+            TopologyPreset::DoubleSided => MemoryTopology::double_sided(),
+            TopologyPreset::SingleSided => MemoryTopology::single_sided(),
+            TopologyPreset::NorthSouth => MemoryTopology::north_south(),
+        }
+    }
+}
+
+/// The connection of one memory PE to its DM port and its AGU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemPort {
+    pub pe: PEIdx,
+    /// Index of the data memory
+    pub dm: usize,
+    /// 1 or 2
+    pub port: usize,
+    /// Index of the AGU driving this port
+    pub agu: usize,
+    /// Whether this is the last port of the DM to be set up in a cycle
+    pub last_of_dm: bool,
+}
+
+impl MemoryTopology {
+    /// Memories on the left and right edges, every two PEs share one DM.
+    /// Order of DMs and AGUs: top left -> bottom left -> top right -> bottom right
+    pub fn double_sided() -> Self {
+        MemoryTopology {
+            edges: vec![MemEdge::West, MemEdge::East],
+            pes_per_dm: 2,
+            agu_indexing: AguIndexing::EdgeMajor,
+        }
+    }
+
+    /// Memories on the left edge only, every two PEs share one DM.
+    pub fn single_sided() -> Self {
+        MemoryTopology {
+            edges: vec![MemEdge::West],
+            pes_per_dm: 2,
+            agu_indexing: AguIndexing::EdgeMajor,
+        }
+    }
+
+    /// Memories on the top and bottom edges, every two PEs share one DM.
+    pub fn north_south() -> Self {
+        MemoryTopology {
+            edges: vec![MemEdge::North, MemEdge::South],
+            pes_per_dm: 2,
+            agu_indexing: AguIndexing::EdgeMajor,
+        }
+    }
+
+    /// Compute the memory ports of a grid of the given shape, in the order of the DMs.
+    /// Panic if two edges share a corner PE, a PE can only be connected to one port.
+    pub fn mem_ports(&self, shape: PEIdx) -> Vec<MemPort> {
+        assert!(
+            self.pes_per_dm == 1 || self.pes_per_dm == 2,
+            "A data memory has 2 ports, cannot be shared by {} PEs",
+            self.pes_per_dm
+        );
+        let edge_pes: Vec<Vec<PEIdx>> = self.edges.iter().map(|e| e.pes(shape)).collect();
+
+        let mut ports = Vec::new();
+        let mut dm_base = 0;
+        let mut agu_base = 0;
+        for (edge_idx, pes) in edge_pes.iter().enumerate() {
+            for (pos, pe) in pes.iter().enumerate() {
+                assert!(
+                    ports.iter().all(|p: &MemPort| p.pe != *pe),
+                    "PE (y={}, x={}) is on two memory edges",
+                    pe.y,
+                    pe.x
+                );
+                let agu = match self.agu_indexing {
+                    AguIndexing::EdgeMajor => agu_base + pos,
+                    AguIndexing::Interleaved => pos * self.edges.len() + edge_idx,
+                };
+                ports.push(MemPort {
+                    pe: *pe,
+                    dm: dm_base + pos / self.pes_per_dm,
+                    port: pos % self.pes_per_dm + 1,
+                    agu,
+                    last_of_dm: pos % self.pes_per_dm == self.pes_per_dm - 1
+                        || pos == pes.len() - 1,
+                });
+            }
+            dm_base += pes.len().div_ceil(self.pes_per_dm);
+            agu_base += pes.len();
+        }
+        ports
+    }
+
+    /// The number of data memories for a grid of the given shape
+    pub fn num_dms(&self, shape: PEIdx) -> usize {
+        self.edges
+            .iter()
+            .map(|e| e.pes(shape).len().div_ceil(self.pes_per_dm))
+            .sum()
+    }
+
+    /// The number of AGUs (one per memory PE) for a grid of the given shape
+    pub fn num_agus(&self, shape: PEIdx) -> usize {
+        self.edges.iter().map(|e| e.pes(shape).len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_sided_ports() {
+        let ports = MemoryTopology::double_sided().mem_ports(PEIdx { x: 4, y: 4 });
+        assert_eq!(ports.len(), 8);
+        // left edge
+        assert_eq!(ports[1].pe, PEIdx { x: 0, y: 1 });
+        assert_eq!((ports[1].dm, ports[1].port, ports[1].agu), (0, 2, 1));
+        assert!(ports[1].last_of_dm);
+        // right edge
+        assert_eq!(ports[6].pe, PEIdx { x: 3, y: 2 });
+        assert_eq!((ports[6].dm, ports[6].port, ports[6].agu), (3, 1, 6));
+        assert!(!ports[6].last_of_dm);
+    }
+
+    #[test]
+    fn test_single_sided_odd_rows() {
+        let topology = MemoryTopology::single_sided();
+        let shape = PEIdx { x: 2, y: 3 };
+        let ports = topology.mem_ports(shape);
+        assert_eq!(topology.num_dms(shape), 2);
+        assert_eq!((ports[2].dm, ports[2].port), (1, 1));
+        assert!(ports[2].last_of_dm);
+    }
+
+    #[test]
+    fn test_interleaved_agus() {
+        let topology = MemoryTopology {
+            agu_indexing: AguIndexing::Interleaved,
+            ..MemoryTopology::north_south()
+        };
+        let ports = topology.mem_ports(PEIdx { x: 2, y: 2 });
+        let agus: Vec<usize> = ports.iter().map(|p| p.agu).collect();
+        assert_eq!(agus, vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_overlapping_edges() {
+        let topology = MemoryTopology {
+            edges: vec![MemEdge::West, MemEdge::North],
+            pes_per_dm: 2,
+            agu_indexing: AguIndexing::EdgeMajor,
+        };
+        topology.mem_ports(PEIdx { x: 2, y: 2 });
+    }
+}
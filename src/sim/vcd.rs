@@ -0,0 +1,289 @@
+//! Export the simulation as a VCD waveform (e.g. for GTKWave), to compare against the RTL.
+//! The signals are scoped as grid.PE_Y{y}X{x}.*, grid.dm{i}.port{1,2}.* and grid.agu{i}.*,
+//! one time unit per cycle. The values are sampled at the end of every cycle (before next_cycle),
+//! an undriven wire (None) is dumped as x.
+
+use std::{io::Write, str::FromStr};
+
+use nom::{
+    IResult, Parser, bytes::complete::tag, character::complete::digit1, combinator::map_res,
+    sequence::preceded,
+};
+use strum::IntoEnumIterator;
+
+use crate::isa::pe::PERegister;
+
+use super::{
+    dmem::DMemInterface,
+    grid::{Grid, PEIdx},
+};
+
+/// A rectangle of PEs, from the top left corner to the bottom right corner (inclusive).
+/// Syntax: `PE-Y0X0:PE-Y3X7`, or a single PE `PE-Y1X2`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PERange {
+    pub min: PEIdx,
+    pub max: PEIdx,
+}
+
+impl PERange {
+    pub fn contains(&self, pe: PEIdx) -> bool {
+        (self.min.x..=self.max.x).contains(&pe.x) && (self.min.y..=self.max.y).contains(&pe.y)
+    }
+
+    fn parse_pe(s: &str) -> IResult<&str, PEIdx> {
+        let (input, y) = preceded(tag("PE-Y"), map_res(digit1, str::parse::<usize>)).parse(s)?;
+        let (input, x) = preceded(tag("X"), map_res(digit1, str::parse::<usize>)).parse(input)?;
+        Ok((input, PEIdx { x, y }))
+    }
+}
+
+impl FromStr for PERange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid PE range: {}, expecting e.g. PE-Y0X0:PE-Y3X7", s);
+        let (first, second) = match s.trim().split_once(':') {
+            Some((first, second)) => (first, Some(second)),
+            None => (s.trim(), None),
+        };
+        let parse = |s: &str| match Self::parse_pe(s) {
+            Ok(("", pe)) => Ok(pe),
+            _ => Err(invalid()),
+        };
+        let min = parse(first)?;
+        let max = second.map(parse).transpose()?.unwrap_or(min);
+        if min.x > max.x || min.y > max.y {
+            return Err(invalid());
+        }
+        Ok(PERange { min, max })
+    }
+}
+
+/// Read the value of a variable from the grid, None for x
+type Probe = Box<dyn Fn(&Grid) -> Option<u64>>;
+
+struct Var {
+    id: String,
+    name: String,
+    width: usize,
+    probe: Probe,
+}
+
+/// The VCD writer, create it after loading the grid and call sample() after every simulate_cycle()
+pub struct VcdWriter<W: Write> {
+    out: W,
+    /// The variables, grouped by scope under grid
+    scopes: Vec<(String, Vec<Var>)>,
+    /// The last dumped value of each variable, in the order of the scopes
+    last: Vec<Option<String>>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Declare the variables of the grid and write the header.
+    /// Only the PEs in the range are dumped, and the DM ports and AGUs of the memory PEs in the range.
+    pub fn new(mut out: W, grid: &Grid, range: Option<PERange>) -> std::io::Result<Self> {
+        let in_range = |pe: PEIdx| range.is_none_or(|range| range.contains(pe));
+        let mut scopes = Vec::new();
+        let mut num_vars = 0;
+        let mut var = |name: &str, width: usize, probe: Probe| {
+            num_vars += 1;
+            Var {
+                id: Self::identifier(num_vars - 1),
+                name: name.to_string(),
+                width,
+                probe,
+            }
+        };
+
+        for y in 0..grid.shape.y {
+            for x in 0..grid.shape.x {
+                if !in_range(PEIdx { x, y }) {
+                    continue;
+                }
+                let mut vars = vec![var(
+                    "pc",
+                    8,
+                    Box::new(move |grid: &Grid| Some(grid.pes[y][x].pc as u64)),
+                )];
+                for (i, (name, _)) in grid.pes[y][x].signals.wires().iter().enumerate() {
+                    vars.push(var(
+                        name,
+                        64,
+                        Box::new(move |grid: &Grid| grid.pes[y][x].signals.wires()[i].1),
+                    ));
+                }
+                for reg in PERegister::iter() {
+                    vars.push(var(
+                        &reg.to_string(),
+                        reg.width(),
+                        Box::new(move |grid: &Grid| Some(grid.pes[y][x].regs.get(reg))),
+                    ));
+                }
+                scopes.push((format!("PE_Y{}X{}", y, x), vars));
+            }
+        }
+
+        for mem_port in grid.mem_ports.iter().filter(|p| in_range(p.pe)) {
+            let (dm, port) = (mem_port.dm, mem_port.port);
+            let interface = move |grid: &Grid| grid.dmems[dm].port(port).clone();
+            let mut vars = vec![
+                var(
+                    "wire_dmem_addr",
+                    64,
+                    Box::new(move |grid: &Grid| interface(grid).wire_dmem_addr),
+                ),
+                var(
+                    "wire_dmem_data",
+                    64,
+                    Box::new(move |grid: &Grid| interface(grid).wire_dmem_data),
+                ),
+            ];
+            // one register per cycle of memory latency
+            for i in 0..grid.dmems[dm].port(port).latency() {
+                vars.push(var(
+                    &DMemInterface::reg_dmem_data_name(i),
+                    64,
+                    Box::new(move |grid: &Grid| interface(grid).reg_dmem_data[i]),
+                ));
+            }
+            // in the order of DMemMode: Read8, Read16, Read64, Write8, Write16, Write64, NOP
+            vars.push(var(
+                "mode",
+                3,
+                Box::new(move |grid: &Grid| Some(interface(grid).mode as u64)),
+            ));
+            scopes.push((format!("dm{}.port{}", dm, port), vars));
+        }
+
+        for mem_port in grid.mem_ports.iter().filter(|p| in_range(p.pe)) {
+            let agu = mem_port.agu;
+            if agu >= grid.agus.len() {
+                continue;
+            }
+            let vars = vec![
+                var(
+                    "pc",
+                    32,
+                    Box::new(move |grid: &Grid| Some(grid.agus[agu].pc as u64)),
+                ),
+                var(
+                    "count",
+                    32,
+                    Box::new(move |grid: &Grid| Some(grid.agus[agu].count as u64)),
+                ),
+            ];
+            scopes.push((format!("agu{}", agu), vars));
+        }
+
+        writeln!(out, "$version pace-sim {} $end", env!("CARGO_PKG_VERSION"))?;
+        writeln!(out, "$timescale 1ns $end")?;
+        writeln!(out, "$scope module grid $end")?;
+        // scopes like dm0.port1 are nested, consecutive scopes share their common levels
+        let mut open: Vec<&str> = Vec::new();
+        for (scope, vars) in scopes.iter() {
+            let levels: Vec<&str> = scope.split('.').collect();
+            let common = open
+                .iter()
+                .zip(levels.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            for _ in common..open.len() {
+                writeln!(out, "$upscope $end")?;
+            }
+            open.truncate(common);
+            for level in levels[common..].iter() {
+                writeln!(out, "$scope module {} $end", level)?;
+                open.push(level);
+            }
+            for var in vars {
+                writeln!(out, "$var wire {} {} {} $end", var.width, var.id, var.name)?;
+            }
+        }
+        for _ in open.iter() {
+            writeln!(out, "$upscope $end")?;
+        }
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$enddefinitions $end")?;
+
+        Ok(VcdWriter {
+            out,
+            scopes,
+            last: vec![None; num_vars],
+        })
+    }
+
+    /// Dump the values that changed since the last sample, at time `cycle`
+    pub fn sample(&mut self, grid: &Grid, cycle: usize) -> std::io::Result<()> {
+        writeln!(self.out, "#{}", cycle)?;
+        let vars = self.scopes.iter().flat_map(|(_, vars)| vars.iter());
+        for (var, last) in vars.zip(self.last.iter_mut()) {
+            let value = match ((var.probe)(grid), var.width) {
+                (None, 1) => "x".to_string(),
+                (None, _) => "bx ".to_string(),
+                (Some(v), 1) => (v & 1).to_string(),
+                (Some(v), _) => format!("b{:b} ", v),
+            };
+            if last.as_ref() != Some(&value) {
+                writeln!(self.out, "{}{}", value, var.id)?;
+                *last = Some(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush the output, e.g. at the end of the simulation
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Get back the output
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// The short identifier of the n-th variable, in printable ASCII characters
+    fn identifier(mut n: usize) -> String {
+        let mut id = String::new();
+        loop {
+            id.push((b'!' + (n % 94) as u8) as char);
+            n /= 94;
+            if n == 0 {
+                return id;
+            }
+            n -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pe_range() {
+        let range: PERange = "PE-Y0X1:PE-Y3X7".parse().unwrap();
+        assert_eq!(range.min, PEIdx { x: 1, y: 0 });
+        assert_eq!(range.max, PEIdx { x: 7, y: 3 });
+        assert!(range.contains(PEIdx { x: 7, y: 2 }));
+        assert!(!range.contains(PEIdx { x: 0, y: 2 }));
+        let single: PERange = "PE-Y2X2".parse().unwrap();
+        assert_eq!(single.min, single.max);
+        assert!("PE-Y3X3:PE-Y0X0".parse::<PERange>().is_err());
+        assert!("PE-Y0X0:".parse::<PERange>().is_err());
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let ids: Vec<String> = (0..200).map(VcdWriter::<Vec<u8>>::identifier).collect();
+        assert_eq!(ids[0], "!");
+        assert_eq!(ids[93], "~");
+        assert_eq!(ids[94], "!!");
+        let unique: std::collections::HashSet<&String> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+        assert!(
+            ids.iter()
+                .all(|id| id.chars().all(|c| c.is_ascii_graphic()))
+        );
+    }
+}
@@ -1939,7 +1939,7 @@ uint32_t agu0_arf_data[1] = {
 };
 
 uint32_t agu0_arf_addr[1] = {
-	0x00001000
+	0x00000200
 };
 
 uint32_t agu1_arf_data[1] = {
@@ -1947,7 +1947,7 @@ uint32_t agu1_arf_data[1] = {
 };
 
 uint32_t agu1_arf_addr[1] = {
-	0x00001400
+	0x00000600
 };
 
 uint32_t agu2_arf_data[1] = {
@@ -1955,7 +1955,7 @@ uint32_t agu2_arf_data[1] = {
 };
 
 uint32_t agu2_arf_addr[1] = {
-	0x00001800
+	0x00000a00
 };
 
 uint32_t agu3_arf_data[1] = {
@@ -1963,7 +1963,7 @@ uint32_t agu3_arf_data[1] = {
 };
 
 uint32_t agu3_arf_addr[1] = {
-	0x00001c00
+	0x00000e00
 };
 
 uint32_t agu4_arf_data[1] = {
@@ -1971,7 +1971,7 @@ uint32_t agu4_arf_data[1] = {
 };
 
 uint32_t agu4_arf_addr[1] = {
-	0x00001000
+	0x00001200
 };
 
 uint32_t agu5_arf_data[1] = {
@@ -1979,7 +1979,7 @@ uint32_t agu5_arf_data[1] = {
 };
 
 uint32_t agu5_arf_addr[1] = {
-	0x00001400
+	0x00001600
 };
 
 uint32_t agu6_arf_data[1] = {
@@ -1987,7 +1987,7 @@ uint32_t agu6_arf_data[1] = {
 };
 
 uint32_t agu6_arf_addr[1] = {
-	0x00001800
+	0x00001a00
 };
 
 uint32_t agu7_arf_data[1] = {
@@ -1995,7 +1995,7 @@ uint32_t agu7_arf_data[1] = {
 };
 
 uint32_t agu7_arf_addr[1] = {
-	0x00001c00
+	0x00001e00
 };
 
 uint32_t agu8_arf_data[1] = {
@@ -2003,7 +2003,7 @@ uint32_t agu8_arf_data[1] = {
 };
 
 uint32_t agu8_arf_addr[1] = {
-	0x00003000
+	0x00002200
 };
 
 uint32_t agu9_arf_data[1] = {
@@ -2011,7 +2011,7 @@ uint32_t agu9_arf_data[1] = {
 };
 
 uint32_t agu9_arf_addr[1] = {
-	0x00003400
+	0x00002600
 };
 
 uint32_t agu10_arf_data[1] = {
@@ -2019,7 +2019,7 @@ uint32_t agu10_arf_data[1] = {
 };
 
 uint32_t agu10_arf_addr[1] = {
-	0x00003800
+	0x00002a00
 };
 
 uint32_t agu11_arf_data[1] = {
@@ -2027,7 +2027,7 @@ uint32_t agu11_arf_data[1] = {
 };
 
 uint32_t agu11_arf_addr[1] = {
-	0x00003c00
+	0x00002e00
 };
 
 uint32_t agu12_arf_data[1] = {
@@ -2035,7 +2035,7 @@ uint32_t agu12_arf_data[1] = {
 };
 
 uint32_t agu12_arf_addr[1] = {
-	0x00003000
+	0x00003200
 };
 
 uint32_t agu13_arf_data[1] = {
@@ -2043,7 +2043,7 @@ uint32_t agu13_arf_data[1] = {
 };
 
 uint32_t agu13_arf_addr[1] = {
-	0x00003400
+	0x00003600
 };
 
 uint32_t agu14_arf_data[1] = {
@@ -2051,7 +2051,7 @@ uint32_t agu14_arf_data[1] = {
 };
 
 uint32_t agu14_arf_addr[1] = {
-	0x00003800
+	0x00003a00
 };
 
 uint32_t agu15_arf_data[1] = {
@@ -2059,44 +2059,44 @@ uint32_t agu15_arf_data[1] = {
 };
 
 uint32_t agu15_arf_addr[1] = {
-	0x00003c00
+	0x00003e00
 };
 
 uint32_t agu_max_count_data[16] = {
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010,
-	0x01000040,
-	0x01000010
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010,
+	0x00000040,
+	0x00000010
 };
 
 uint32_t agu_max_count_addr[16] = {
-	0x00001100,
-	0x00001500,
-	0x00001900,
-	0x00001d00,
-	0x00001100,
-	0x00001500,
-	0x00001900,
-	0x00001d00,
-	0x00003100,
-	0x00003500,
-	0x00003900,
-	0x00003d00,
-	0x00003100,
-	0x00003500,
-	0x00003900,
-	0x00003d00
+	0x00000300,
+	0x00000700,
+	0x00000b00,
+	0x00000f00,
+	0x00001300,
+	0x00001700,
+	0x00001b00,
+	0x00001f00,
+	0x00002300,
+	0x00002700,
+	0x00002b00,
+	0x00002f00,
+	0x00003300,
+	0x00003700,
+	0x00003b00,
+	0x00003f00
 };
 
@@ -3743,7 +3743,7 @@ uint32_t agu0_arf_data[1] = {
 };
 
 uint32_t agu0_arf_addr[1] = {
-	0x00001000
+	0x00000200
 };
 
 uint32_t agu1_arf_data[1] = {
@@ -3751,7 +3751,7 @@ uint32_t agu1_arf_data[1] = {
 };
 
 uint32_t agu1_arf_addr[1] = {
-	0x00001400
+	0x00000600
 };
 
 uint32_t agu2_arf_data[1] = {
@@ -3759,7 +3759,7 @@ uint32_t agu2_arf_data[1] = {
 };
 
 uint32_t agu2_arf_addr[1] = {
-	0x00001800
+	0x00000a00
 };
 
 uint32_t agu3_arf_data[1] = {
@@ -3767,7 +3767,7 @@ uint32_t agu3_arf_data[1] = {
 };
 
 uint32_t agu3_arf_addr[1] = {
-	0x00001c00
+	0x00000e00
 };
 
 uint32_t agu4_arf_data[1] = {
@@ -3775,7 +3775,7 @@ uint32_t agu4_arf_data[1] = {
 };
 
 uint32_t agu4_arf_addr[1] = {
-	0x00001000
+	0x00001200
 };
 
 uint32_t agu5_arf_data[1] = {
@@ -3783,7 +3783,7 @@ uint32_t agu5_arf_data[1] = {
 };
 
 uint32_t agu5_arf_addr[1] = {
-	0x00001400
+	0x00001600
 };
 
 uint32_t agu6_arf_data[1] = {
@@ -3791,7 +3791,7 @@ uint32_t agu6_arf_data[1] = {
 };
 
 uint32_t agu6_arf_addr[1] = {
-	0x00001800
+	0x00001a00
 };
 
 uint32_t agu7_arf_data[1] = {
@@ -3799,7 +3799,7 @@ uint32_t agu7_arf_data[1] = {
 };
 
 uint32_t agu7_arf_addr[1] = {
-	0x00001c00
+	0x00001e00
 };
 
 uint32_t agu8_arf_data[1] = {
@@ -3807,7 +3807,7 @@ uint32_t agu8_arf_data[1] = {
 };
 
 uint32_t agu8_arf_addr[1] = {
-	0x00003000
+	0x00002200
 };
 
 uint32_t agu9_arf_data[1] = {
@@ -3815,7 +3815,7 @@ uint32_t agu9_arf_data[1] = {
 };
 
 uint32_t agu9_arf_addr[1] = {
-	0x00003400
+	0x00002600
 };
 
 uint32_t agu10_arf_data[1] = {
@@ -3823,7 +3823,7 @@ uint32_t agu10_arf_data[1] = {
 };
 
 uint32_t agu10_arf_addr[1] = {
-	0x00003800
+	0x00002a00
 };
 
 uint32_t agu11_arf_data[1] = {
@@ -3831,7 +3831,7 @@ uint32_t agu11_arf_data[1] = {
 };
 
 uint32_t agu11_arf_addr[1] = {
-	0x00003c00
+	0x00002e00
 };
 
 uint32_t agu12_arf_data[1] = {
@@ -3839,7 +3839,7 @@ uint32_t agu12_arf_data[1] = {
 };
 
 uint32_t agu12_arf_addr[1] = {
-	0x00003000
+	0x00003200
 };
 
 uint32_t agu13_arf_data[1] = {
@@ -3847,7 +3847,7 @@ uint32_t agu13_arf_data[1] = {
 };
 
 uint32_t agu13_arf_addr[1] = {
-	0x00003400
+	0x00003600
 };
 
 uint32_t agu14_arf_data[1] = {
@@ -3855,7 +3855,7 @@ uint32_t agu14_arf_data[1] = {
 };
 
 uint32_t agu14_arf_addr[1] = {
-	0x00003800
+	0x00003a00
 };
 
 uint32_t agu15_arf_data[1] = {
@@ -3863,7 +3863,7 @@ uint32_t agu15_arf_data[1] = {
 };
 
 uint32_t agu15_arf_addr[1] = {
-	0x00003c00
+	0x00003e00
 };
 
 uint32_t agu_max_count_data[16] = {
@@ -3886,21 +3886,21 @@ uint32_t agu_max_count_data[16] = {
 };
 
 uint32_t agu_max_count_addr[16] = {
-	0x00001100,
-	0x00001500,
-	0x00001900,
-	0x00001d00,
-	0x00001100,
-	0x00001500,
-	0x00001900,
-	0x00001d00,
-	0x00003100,
-	0x00003500,
-	0x00003900,
-	0x00003d00,
-	0x00003100,
-	0x00003500,
-	0x00003900,
-	0x00003d00
+	0x00000300,
+	0x00000700,
+	0x00000b00,
+	0x00000f00,
+	0x00001300,
+	0x00001700,
+	0x00001b00,
+	0x00001f00,
+	0x00002300,
+	0x00002700,
+	0x00002b00,
+	0x00002f00,
+	0x00003300,
+	0x00003700,
+	0x00003b00,
+	0x00003f00
 };
 
@@ -1,6 +1,9 @@
 use pace_sim::{
     self,
-    isa::{configuration::Configuration, operation::*, pe::*, router::*},
+    isa::{
+        configuration::Configuration, fp8::FP8Format, fp16::FloatFormat, operation::*, pe::*,
+        router::*,
+    },
 };
 
 /// Test a single PE doing ALU operations (ADD, SUB, MULT)
@@ -32,6 +35,11 @@ fn test_single_pe_alu() {
             input_register_write: DirectionsOpt::default(),
         },
         agu_trigger: false,
+        predicated: false,
+        use_float: false,
+        alu_bypass: false,
+        fp8_format: FP8Format::E4M3,
+        float_format: FloatFormat::FP16,
     };
 
     // ADD with immediate: result = op1 + 10 (op1 starts at 0)
@@ -57,6 +65,11 @@ fn test_single_pe_alu() {
             input_register_write: DirectionsOpt::default(),
         },
         agu_trigger: false,
+        predicated: false,
+        use_float: false,
+        alu_bypass: false,
+        fp8_format: FP8Format::E4M3,
+        float_format: FloatFormat::FP16,
     };
 
     // MULT with immediate: result = op1 * 2
@@ -82,6 +95,11 @@ fn test_single_pe_alu() {
             input_register_write: DirectionsOpt::default(),
         },
         agu_trigger: false,
+        predicated: false,
+        use_float: false,
+        alu_bypass: false,
+        fp8_format: FP8Format::E4M3,
+        float_format: FloatFormat::FP16,
     };
 
     // SUB with immediate: result = op1 - 5
@@ -107,6 +125,11 @@ fn test_single_pe_alu() {
             input_register_write: DirectionsOpt::default(),
         },
         agu_trigger: false,
+        predicated: false,
+        use_float: false,
+        alu_bypass: false,
+        fp8_format: FP8Format::E4M3,
+        float_format: FloatFormat::FP16,
     };
 
     // NOP at end
@@ -132,6 +155,11 @@ fn test_single_pe_alu() {
             input_register_write: DirectionsOpt::default(),
         },
         agu_trigger: false,
+        predicated: false,
+        use_float: false,
+        alu_bypass: false,
+        fp8_format: FP8Format::E4M3,
+        float_format: FloatFormat::FP16,
     };
 
     let configurations = vec![init_loop, add_imm, mult_imm, sub_imm, nop];
@@ -142,8 +170,7 @@ fn test_single_pe_alu() {
         regs: PERegisters::default(),
         signals: PESignals::default(),
         is_mem_pe_flag: false, // Not a memory PE
-        agu_cm_s: None,
-        agu_cm_ss: None,
+        agu_cm_pipeline: vec![None; 2],
         previous_op: None,
     };
 
@@ -1,11 +1,12 @@
-use pace_sim::sim::grid::DoubleSidedMemoryGrid;
+use pace_sim::sim::{grid::Grid, topology::MemoryTopology};
 
 /// This test uses the old non-AGU model which is no longer supported.
 /// Memory operations now require AGU in the new design.
 #[test]
 #[ignore = "Test uses old non-AGU model - AGU is now required for memory operations"]
 fn test_add_2x2() {
-    let mut grid = DoubleSidedMemoryGrid::from_folder("tests/add_2x2");
+    let mut grid = Grid::from_folder("tests/add_2x2", MemoryTopology::double_sided())
+        .expect("Failed to load the grid");
     grid.simulate_cycle().expect("Simulation failed");
     grid.snapshot("tests/add_2x2/cycle_0");
     grid.next_cycle();
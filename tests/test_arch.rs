@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use pace_sim::sim::{
+    arch::Architecture,
+    dump_header::DumpHeader,
+    global_mem::GlobalMemory,
+    grid::{Grid, PEIdx},
+    loader::LoadError,
+    pace::PACESystem,
+    topology::MemoryTopology,
+};
+
+#[test]
+fn test_load_descriptors() {
+    let pace = Architecture::load(Path::new("tests/arch/pace2.toml")).unwrap();
+    assert_eq!(pace, Architecture::default());
+    let json = Architecture::load(Path::new("tests/arch/pace2.json")).unwrap();
+    assert_eq!(json, pace);
+
+    let small = Architecture::load(Path::new("tests/arch/single_sided_4x4.toml")).unwrap();
+    assert_eq!(small.shape(), PEIdx { x: 4, y: 4 });
+    assert_eq!(small.topology(), MemoryTopology::single_sided());
+
+    match Architecture::load(Path::new("tests/arch/deep_cm.toml")) {
+        Err(LoadError::InvalidArchitecture { message, .. }) => {
+            assert!(message.contains("cm_depth"), "{}", message)
+        }
+        other => panic!("Expected an invalid architecture, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_pace_system_with_arch() {
+    let arch = Architecture::load(Path::new("tests/arch/pace2.toml")).unwrap();
+    let pace = PACESystem::from_folder_with_arch("tests/gemm", arch.clone()).unwrap();
+    assert!(
+        pace.dmems
+            .iter()
+            .all(|dmem| dmem.capacity() == arch.dm_size)
+    );
+    let grid = pace.to_grid();
+    assert_eq!(grid.arch, arch);
+    assert_eq!(GlobalMemory::from_grid(&grid).content.len(), 1 << 19);
+
+    // the folder does not match the architecture
+    let small = Architecture::load(Path::new("tests/arch/single_sided_4x4.toml")).unwrap();
+    let e = Grid::from_folder_with_arch("tests/gemm", &small).unwrap_err();
+    assert!(
+        e.errors()
+            .iter()
+            .any(|e| matches!(e, LoadError::CountMismatch { what, .. } if what == "data memories")),
+        "{}",
+        e
+    );
+}
+
+#[test]
+fn test_small_global_memory() {
+    let small = Architecture::load(Path::new("tests/arch/single_sided_4x4.toml")).unwrap();
+    let grid = Grid::from_folder_with_arch("tests/single_sided_array_add_4x4", &small).unwrap();
+    assert!(grid.dmems.iter().all(|dmem| dmem.capacity() == 8192));
+    let address_map = grid.arch.address_map();
+    let global_mem = GlobalMemory::from_grid(&grid);
+    assert_eq!(global_mem.content.len(), address_map.size());
+    // PE-Y1X1 is the PE 5, its CM is not overwritten by the AGU regions of the PE 1
+    assert_eq!(
+        global_mem.content[address_map.pe_cm(5, 0) as usize],
+        grid.pes[1][1].configurations[0].to_u64()
+    );
+    let dm1 = grid.dmems[1].to_u64_vec();
+    let dm1_base = address_map.dm_base(1) as usize;
+    assert_eq!(
+        &global_mem.content[dm1_base..dm1_base + dm1.len()],
+        &dm1[..]
+    );
+
+    let header = std::env::temp_dir().join("pace_sim_test_small_header.h");
+    grid.dump_header(header.to_str().unwrap());
+    let content = std::fs::read_to_string(&header).unwrap();
+    assert!(content.contains(&format!("\t0x{:08x},\n", address_map.dm_base(1))));
+    std::fs::remove_file(header).unwrap();
+}
@@ -1,33 +1,48 @@
-use log::{error, info};
-use pace_sim::sim::grid::{DoubleSidedMemoryGrid, SimulationError};
+use std::path::Path;
 
-/// This test only has left-side AGU files (agu0-agu3), but DoubleSidedMemoryGrid
-/// requires AGU files for both sides in the new design.
+use log::info;
+use pace_sim::sim::{
+    dmem::DEFAULT_MEM_LATENCY,
+    golden::Golden,
+    grid::{Grid, SimulationError},
+    topology::MemoryTopology,
+};
+
+/// c = a + b over 4 elements: a and b are loaded from dm0, c is stored into dm1.
+/// The schedule leaves 4 cycles between a LOAD and the use of its data (see the README of the
+/// folder), the result is the same for every memory latency up to 4 cycles.
 #[test]
-#[ignore = "Test has incomplete AGU files - needs both left and right side AGUs"]
 fn test_add_array_2x2() {
-    env_logger::init();
-    let mut grid = DoubleSidedMemoryGrid::from_folder("tests/array_add_2x2");
+    let _ = env_logger::builder().is_test(true).try_init();
+    let golden = Golden::load(Path::new("tests/array_add_2x2/expected")).unwrap();
+    for latency in 1..=4 {
+        let mut grid = Grid::from_folder("tests/array_add_2x2", MemoryTopology::double_sided())
+            .expect("Failed to load the grid");
+        grid.set_mem_latency(latency);
+        // the snapshots of every cycle are kept for the latency of PACE 2.0
+        let snapshots = (latency == DEFAULT_MEM_LATENCY).then_some("tests/array_add_2x2");
+        let cycles = run(&mut grid, snapshots);
+        info!("Latency {}: finished after {} cycles", latency, cycles);
+        // the loads of the 5th iteration end the simulation
+        assert_eq!(cycles, 1 + 7 * 4, "latency {}", latency);
+        let comparison = golden.compare(&grid.dmems).unwrap();
+        assert!(comparison.is_match(), "latency {}\n{}", latency, comparison);
+    }
+}
+
+/// Simulate until the AGUs end, return the number of cycles.
+/// A snapshot of every cycle is saved to <snapshots>/cycle_N if given.
+fn run(grid: &mut Grid, snapshots: Option<&str>) -> usize {
     let mut cycle = 0;
     loop {
-        if let Err(e) = grid.simulate_cycle() {
-            match e {
-                SimulationError::PEUpdateError(pe_idx, e) => {
-                    error!("PEUpdateError at PE({},{}): {}", pe_idx.x, pe_idx.y, e);
-                    panic!("PEUpdateError at PE({},{}): {}", pe_idx.x, pe_idx.y, e);
-                }
-                SimulationError::SimulationEnd => {
-                    info!("Simulation finished by AGU signal");
-                    break;
-                }
-            }
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return cycle,
+            Err(e) => panic!("Simulation failed at cycle {}: {:?}", cycle, e),
+        }
+        if let Some(folder) = snapshots {
+            grid.snapshot(&format!("{}/cycle_{}", folder, cycle));
         }
-        let snapshot_folder = format!("tests/array_add_2x2/cycle_{}", cycle);
-        info!(
-            "Taking snapshot after cycle {}, saved to {}",
-            cycle, snapshot_folder
-        );
-        grid.snapshot(snapshot_folder.as_str());
         grid.next_cycle();
         cycle += 1;
     }
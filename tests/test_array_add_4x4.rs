@@ -1,57 +1,89 @@
-use log::{error, info};
-use pace_sim::sim::grid::{SimulationError, SingleSidedMemoryGrid};
+use std::path::Path;
 
-/// This test uses PE programs with LOAD/STORE opcodes which are now deprecated.
-/// Memory operations are now controlled by AGU instruction, not PE opcode.
+use log::info;
+use pace_sim::{
+    agu::instruction::InstType,
+    sim::{
+        arch::Architecture,
+        dmem::DEFAULT_MEM_LATENCY,
+        golden::Golden,
+        grid::{Grid, PEIdx, SimulationError},
+    },
+};
+
+const TEST_FOLDER: &str = "tests/single_sided_array_add_4x4";
+
+/// c = a + b over 4 elements in every DM: a and b are loaded from the DM, c is stored back.
+/// The schedule leaves 4 cycles between a LOAD and the use of its data (see the README of the
+/// folder), the result is the same for every memory latency up to 4 cycles.
 #[test]
-#[ignore = "Test uses deprecated LOAD/STORE PE opcodes - needs updated PE programs"]
 fn test_add_array_4x4() {
-    env_logger::init();
-    let mut grid = SingleSidedMemoryGrid::from_folder("tests/single_sided_array_add_4x4");
+    let _ = env_logger::builder().is_test(true).try_init();
+    let golden = Golden::load(Path::new(&format!("{}/expected", TEST_FOLDER))).unwrap();
+    let small = Architecture::load(Path::new("tests/arch/single_sided_4x4.toml")).unwrap();
+    for latency in 1..=4 {
+        let arch = Architecture {
+            mem_latency: latency,
+            ..small.clone()
+        };
+        let mut grid = Grid::from_folder_with_arch(TEST_FOLDER, &arch).unwrap();
+        assert!(
+            grid.dmems
+                .iter()
+                .all(|dmem| dmem.port1.latency() == latency)
+        );
+        // the snapshots of every cycle are kept for the latency of PACE 2.0
+        let snapshots = (latency == DEFAULT_MEM_LATENCY).then_some(TEST_FOLDER);
+        let cycles = run(&mut grid, snapshots);
+        info!("Latency {}: finished after {} cycles", latency, cycles);
+        // the loads of the 5th iteration end the simulation
+        assert_eq!(cycles, 1 + 7 * 4, "latency {}", latency);
+        let comparison = golden.compare(&grid.dmems).unwrap();
+        assert!(
+            comparison.is_match(),
+            "latency {}
+{}",
+            latency,
+            comparison
+        );
+    }
+}
+
+/// The AGU of Y1X0 loads b then stores c: the PE pipeline records the LOAD issued in the cycle,
+/// not the STORE the AGU has moved to.
+#[test]
+fn test_agu_cm_pipeline_records_issued_instruction() {
+    let arch = Architecture::load(Path::new("tests/arch/single_sided_4x4.toml")).unwrap();
+    let mut grid = Grid::from_folder_with_arch(TEST_FOLDER, &arch).unwrap();
+    let pe = PEIdx { x: 0, y: 1 };
+    let agu = grid.mem_ports.iter().find(|p| p.pe == pe).unwrap().agu;
+    // cycle 0 resets the PC, the LOAD is issued at cycle 1
+    for _ in 0..2 {
+        grid.simulate_cycle().unwrap();
+        grid.next_cycle();
+    }
+    let recorded = grid.pes[pe.y][pe.x].agu_cm_pipeline[0].unwrap();
+    assert_eq!(recorded.inst_type, InstType::LOAD);
+    assert_eq!(
+        grid.agus[agu].current_instruction().inst_type,
+        InstType::STORE
+    );
+}
+
+/// Simulate until the AGUs end, return the number of cycles.
+/// A snapshot of every cycle is saved to <snapshots>/cycle_N if given.
+fn run(grid: &mut Grid, snapshots: Option<&str>) -> usize {
     let mut cycle = 0;
     loop {
-        if let Err(e) = grid.simulate_cycle() {
-            match e {
-                SimulationError::PEUpdateError(pe_idx, e) => {
-                    error!("PEUpdateError at PE({},{}): {}", pe_idx.x, pe_idx.y, e);
-                    panic!("PEUpdateError at PE({},{}): {}", pe_idx.x, pe_idx.y, e);
-                }
-                SimulationError::SimulationEnd => {
-                    info!("Simulation finished by AGU signal");
-                    break;
-                }
-            }
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return cycle,
+            Err(e) => panic!("Simulation failed at cycle {}: {:?}", cycle, e),
+        }
+        if let Some(folder) = snapshots {
+            grid.snapshot(&format!("{}/cycle_{}", folder, cycle));
         }
-        let snapshot_folder = format!("tests/single_sided_array_add_4x4/cycle_{}", cycle);
-        info!(
-            "Taking snapshot after cycle {}, saved to {}",
-            cycle, snapshot_folder
-        );
-        grid.snapshot(snapshot_folder.as_str());
         grid.next_cycle();
         cycle += 1;
     }
-
-    // load the dm0 at cycle 16, compare with the expected dm0, remove spaces and newlines
-    let dm0 = std::fs::read_to_string("tests/single_sided_array_add_4x4/cycle_16/dm0")
-        .unwrap()
-        .replace(" ", "")
-        .replace("\n", "");
-
-    let dm0_expected = std::fs::read_to_string("tests/single_sided_array_add_4x4/dm0_expected")
-        .unwrap()
-        .replace(" ", "")
-        .replace("\n", "");
-    assert_eq!(dm0, dm0_expected);
-
-    // load the dm1 at cycle 16, compare with the expected dm1, remove spaces and newlines
-    let dm1 = std::fs::read_to_string("tests/single_sided_array_add_4x4/cycle_16/dm1")
-        .unwrap()
-        .replace(" ", "")
-        .replace("\n", "");
-    let dm1_expected = std::fs::read_to_string("tests/single_sided_array_add_4x4/dm1_expected")
-        .unwrap()
-        .replace(" ", "")
-        .replace("\n", "");
-    assert_eq!(dm1, dm1_expected);
 }
@@ -0,0 +1,55 @@
+use pace_sim::sim::{
+    checkpoint::Checkpoint,
+    grid::{Grid, SimulationError},
+    topology::MemoryTopology,
+};
+
+const TEST_FOLDER: &str = "tests/gemm";
+
+fn load_grid() -> Grid {
+    Grid::from_folder(TEST_FOLDER, MemoryTopology::double_sided()).expect("Failed to load the grid")
+}
+
+/// Simulate until the AGUs stop the simulation, return the number of simulated cycles
+fn run_to_end(grid: &mut Grid, mut cycle: usize) -> usize {
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return cycle,
+            Err(e) => panic!("Unexpected simulation error at cycle {}: {:?}", cycle, e),
+        }
+        grid.next_cycle();
+        cycle += 1;
+    }
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    let checkpoint_path = std::env::temp_dir().join("pace_sim_test_checkpoint.json");
+
+    // reference run, checkpointed after 10 cycles
+    let mut grid = load_grid();
+    for _ in 0..10 {
+        grid.simulate_cycle().unwrap();
+        grid.next_cycle();
+    }
+    grid.checkpoint().save(&checkpoint_path).unwrap();
+    let end_cycle = run_to_end(&mut grid, 10);
+    assert!(end_cycle > 10);
+
+    // resumed run
+    let mut resumed = load_grid();
+    let checkpoint = Checkpoint::load(&checkpoint_path).unwrap();
+    let cycle = resumed.restore(&checkpoint).unwrap();
+    assert_eq!(cycle, 10);
+    assert_eq!(resumed.stats.cycles, 10);
+    assert_eq!(run_to_end(&mut resumed, cycle), end_cycle);
+    assert_eq!(resumed.stats, grid.stats);
+    assert_eq!(resumed.checkpoint(), grid.checkpoint());
+
+    // a checkpoint does not fit a grid of another shape
+    let mut other = Grid::from_folder("tests/add_2x2", MemoryTopology::double_sided())
+        .expect("Failed to load the grid");
+    assert!(other.restore(&checkpoint).is_err());
+    std::fs::remove_file(&checkpoint_path).unwrap();
+}
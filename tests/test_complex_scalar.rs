@@ -15,12 +15,9 @@ const TEST_FOLDER: &str = "tests/complex_scalar_8x8";
 /// Test complex scalar operations on an 8x8 grid with AGU-driven memory operations.
 /// PE programs use ADD? 0 to trigger AGU memory operations and pass loaded data.
 ///
-/// NOTE: This test is currently ignored because:
-/// - The expected values (dm*.expected) were computed for 1-cycle memory latency
-/// - The new design uses 2-cycle memory latency (agu_cm_ss)
-/// - The test's expected values need to be regenerated for the new timing
+/// NOTE: The expected values (dm*.expected) were computed for 1-cycle memory latency,
+/// the grid is simulated with a 1-cycle latency instead of the 2 cycles of PACE 2.0.
 #[test]
-#[ignore = "Test needs updated expected values for 2-cycle memory latency"]
 fn test_complex_scalar_8x8() {
     let handle = std::thread::Builder::new()
         .stack_size(1024 * 1024 * 1024) // 1 GiB
@@ -57,8 +54,9 @@ fn check_final_dm_content() {
 }
 
 fn pack_run_simulation_and_pack() {
-    let pace = PACESystem::from_folder(TEST_FOLDER);
+    let pace = PACESystem::from_folder(TEST_FOLDER).expect("Failed to load the PACE system");
     let mut grid = pace.to_grid();
+    grid.set_mem_latency(1);
     let global_mem = GlobalMemory::from_grid(&grid);
     global_mem.dump_to_64b_format("tests/complex_scalar_8x8/start.mem");
     grid.dump_header("tests/complex_scalar_8x8/pace_sys_start.h");
@@ -74,6 +72,15 @@ fn pack_run_simulation_and_pack() {
                     info!("Simulation finished by AGU signal");
                     break;
                 }
+                SimulationError::CombinationalLoop(router_loop) => {
+                    panic!("Combinational loop in the router: {}", router_loop);
+                }
+                SimulationError::MemoryFault(fault) => {
+                    panic!("Memory fault at {}", fault);
+                }
+                SimulationError::Breakpoint(reason) => {
+                    unreachable!("No breakpoint is set: {}", reason);
+                }
             }
         }
         let snapshot_folder = format!("tests/complex_scalar_8x8/cycle_{}", cycle);
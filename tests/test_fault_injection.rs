@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use pace_sim::sim::{
+    fault_injection::{Campaign, Outcome},
+    golden::Golden,
+    grid::Grid,
+    topology::MemoryTopology,
+};
+
+const TEST_FOLDER: &str = "tests/array_add_2x2";
+
+#[test]
+fn test_campaign() {
+    let grid = Grid::from_folder(TEST_FOLDER, MemoryTopology::double_sided())
+        .expect("Failed to load the grid");
+    let campaign = Campaign::load(&Path::new(TEST_FOLDER).join("campaign.toml")).unwrap();
+    let golden = Golden::load(&Path::new(TEST_FOLDER).join("expected")).unwrap();
+    // the runs which panic are reported as crashed
+    std::panic::set_hook(Box::new(|_| {}));
+    let report = campaign.run(&grid, Some(&golden)).unwrap();
+    assert_eq!(report.golden_cycles, 29);
+    assert_eq!(report.runs.len(), 4 + 200);
+
+    let outcomes: Vec<String> = report.runs[..4]
+        .iter()
+        .map(|run| run.outcome.to_string())
+        .collect();
+    assert_eq!(outcomes[0], "masked");
+    assert_eq!(outcomes[1], "corrupted (1 mismatching words)");
+    assert_eq!(
+        outcomes[2],
+        "crashed (memory fault at cycle 1: agu0 (PE-Y0X0) Read16 of 2 bytes at address 0x4000 of dm0 port 1 is out of the 24 bytes of the data memory)"
+    );
+    assert!(
+        outcomes[3].contains("Invalid router direction"),
+        "{}",
+        outcomes[3]
+    );
+    match &report.runs[1].outcome {
+        Outcome::Corrupted(comparison) => assert_eq!(comparison.mismatches[0].addr, 0),
+        other => panic!("Expected a corruption, got {}", other),
+    }
+    let [masked, corrupted, crashed] = report.counts();
+    assert!(masked > 0 && corrupted > 0 && crashed > 0);
+    assert!(report.to_string().starts_with(&format!(
+        "Fault-free run: 29 cycles\nAll: 204 runs, {} masked",
+        masked
+    )));
+
+    // reproducible: the same flips against the fault-free DMs, where dm0 is compared too
+    let fault_free = campaign.run(&grid, None).unwrap();
+    for (run, run_fault_free) in report.runs.iter().zip(fault_free.runs.iter()) {
+        assert_eq!(run.injections, run_fault_free.injections);
+        if run_fault_free.outcome == Outcome::Masked {
+            assert_eq!(run.outcome, Outcome::Masked);
+        }
+    }
+    let _ = std::panic::take_hook();
+}
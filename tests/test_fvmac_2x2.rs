@@ -1,5 +1,8 @@
 use log::{error, info};
-use pace_sim::sim::grid::{SimulationError, SingleSidedMemoryGrid};
+use pace_sim::sim::{
+    grid::{Grid, SimulationError},
+    topology::MemoryTopology,
+};
 
 /// This test uses the old non-AGU model which is no longer supported.
 /// Memory operations now require AGU in the new design.
@@ -7,7 +10,11 @@ use pace_sim::sim::grid::{SimulationError, SingleSidedMemoryGrid};
 #[ignore = "Test uses old non-AGU model - AGU is now required for memory operations"]
 fn test_fvmac_2x2() {
     env_logger::init();
-    let mut grid = SingleSidedMemoryGrid::from_folder("tests/single_sided_fvmac_2x2");
+    let mut grid = Grid::from_folder(
+        "tests/single_sided_fvmac_2x2",
+        MemoryTopology::single_sided(),
+    )
+    .expect("Failed to load the grid");
     let mut cycle = 0;
     loop {
         if let Err(e) = grid.simulate_cycle() {
@@ -27,6 +34,15 @@ fn test_fvmac_2x2() {
                     info!("Simulation finished");
                     break;
                 }
+                SimulationError::CombinationalLoop(router_loop) => {
+                    panic!("Combinational loop in the router: {}", router_loop);
+                }
+                SimulationError::MemoryFault(fault) => {
+                    panic!("Memory fault at {}", fault);
+                }
+                SimulationError::Breakpoint(reason) => {
+                    unreachable!("No breakpoint is set: {}", reason);
+                }
             }
         }
         let snapshot_folder = format!("tests/single_sided_fvmac_2x2/cycle_{}", cycle);
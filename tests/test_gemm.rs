@@ -82,7 +82,8 @@ fn run_gemm_test() {
 
     // Step 2: Run simulation
     info!("Starting GEMM simulation...");
-    let pace = PACESystem::from_folder(config.test_folder);
+    let pace = PACESystem::from_folder(config.test_folder)
+        .expect("Failed to load the PACE system");
     let mut grid = pace.to_grid();
 
     // Dump initial state (packed memory format)
@@ -109,6 +110,15 @@ fn run_gemm_test() {
                     info!("Simulation finished successfully at cycle {}", cycle);
                     break;
                 }
+                SimulationError::CombinationalLoop(router_loop) => {
+                    panic!("Combinational loop in the router: {}", router_loop);
+                }
+                SimulationError::MemoryFault(fault) => {
+                    panic!("Memory fault at {}", fault);
+                }
+                SimulationError::Breakpoint(reason) => {
+                    unreachable!("No breakpoint is set: {}", reason);
+                }
             }
         }
         let snapshot_folder = format!("{}/cycle_{}", config.test_folder, cycle);
@@ -0,0 +1,79 @@
+use pace_sim::sim::{
+    dmem::{DMemMode, FaultKind, MemoryFaultPolicy},
+    golden::Golden,
+    grid::{Grid, PEIdx, SimulationError},
+    topology::MemoryTopology,
+};
+
+const TEST_FOLDER: &str = "tests/array_add_2x2";
+
+/// The array_add_2x2 grid, agu0 (loading a from dm0) starting at `addr`
+fn grid_loading_a_from(addr: u16, policy: MemoryFaultPolicy) -> Grid {
+    let mut grid = Grid::from_folder(TEST_FOLDER, MemoryTopology::double_sided())
+        .expect("Failed to load the grid");
+    grid.agus[0].arf[0] = addr;
+    grid.mem_fault_policy = policy;
+    grid
+}
+
+fn run(grid: &mut Grid) -> Result<(), SimulationError> {
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        grid.next_cycle();
+    }
+}
+
+fn golden() -> Golden {
+    Golden::load(std::path::Path::new(&format!("{}/expected", TEST_FOLDER))).unwrap()
+}
+
+#[test]
+fn test_fault() {
+    // dm0 is 24 bytes
+    let mut grid = grid_loading_a_from(30, MemoryFaultPolicy::Fault);
+    match run(&mut grid) {
+        Err(SimulationError::MemoryFault(fault)) => {
+            assert_eq!(fault.cycle, 1);
+            assert_eq!((fault.dm, fault.port, fault.agu), (0, 1, 0));
+            assert_eq!(fault.pe, PEIdx { x: 0, y: 0 });
+            assert_eq!((fault.addr, fault.width), (30, 2));
+            assert_eq!(fault.mode, DMemMode::Read16);
+            assert_eq!(fault.kind, FaultKind::OutOfRange { capacity: 24 });
+            assert_eq!(
+                fault.to_string(),
+                "cycle 1: agu0 (PE-Y0X0) Read16 of 2 bytes at address 0x1e of dm0 port 1 is out of the 24 bytes of the data memory"
+            );
+        }
+        other => panic!("Expected a memory fault, got {:?}", other),
+    }
+
+    let mut grid = grid_loading_a_from(1, MemoryFaultPolicy::Fault);
+    match run(&mut grid) {
+        Err(SimulationError::MemoryFault(fault)) => {
+            assert_eq!(fault.kind, FaultKind::Misaligned)
+        }
+        other => panic!("Expected a memory fault, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_wrap() {
+    // 24 wraps to 0, where a is
+    let mut grid = grid_loading_a_from(24, MemoryFaultPolicy::Wrap);
+    run(&mut grid).unwrap();
+    let comparison = golden().compare(&grid.dmems).unwrap();
+    assert!(comparison.is_match(), "{}", comparison);
+}
+
+#[test]
+fn test_log() {
+    // every load of a is skipped and gives 0, c = b
+    let mut grid = grid_loading_a_from(24, MemoryFaultPolicy::Log);
+    run(&mut grid).unwrap();
+    assert_eq!(grid.dmems[1].data[..8], grid.dmems[0].data[8..16]);
+    assert!(!golden().compare(&grid.dmems).unwrap().is_match());
+}
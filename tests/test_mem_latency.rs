@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use pace_sim::sim::{dmem::DEFAULT_MEM_LATENCY, grid::SimulationError, pace::PACESystem};
+
+const TEST_FOLDER: &str = "tests/gemm";
+
+/// The cycle_N snapshots of the gemm kernel were saved with the fixed 2-cycle memory pipeline
+/// (agu_cm_s/agu_cm_ss, reg_dmem_data_s) and kept unchanged when the latency became configurable.
+/// A grid set to the 2-cycle latency of PACE 2.0 must reproduce them byte for byte.
+#[test]
+fn test_latency_2_reproduces_snapshots() {
+    // PACESystem requires significant stack space
+    let handle = std::thread::Builder::new()
+        .stack_size(1024 * 1024 * 1024) // 1 GiB
+        .spawn(check_snapshots)
+        .unwrap();
+    handle.join().unwrap();
+}
+
+fn check_snapshots() {
+    let pace = PACESystem::from_folder(TEST_FOLDER).expect("Failed to load the PACE system");
+    let mut grid = pace.to_grid();
+    grid.set_mem_latency(DEFAULT_MEM_LATENCY);
+    let snapshots = std::env::temp_dir().join("pace_sim_test_mem_latency");
+    let mut cycle = 0;
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => break,
+            Err(e) => panic!("Simulation failed at cycle {}: {:?}", cycle, e),
+        }
+        let folder = snapshots.join(format!("cycle_{}", cycle));
+        grid.snapshot(folder.to_str().unwrap());
+        compare(
+            &folder,
+            &Path::new(TEST_FOLDER).join(format!("cycle_{}", cycle)),
+        );
+        grid.next_cycle();
+        cycle += 1;
+    }
+    assert!(
+        !Path::new(TEST_FOLDER)
+            .join(format!("cycle_{}", cycle))
+            .exists(),
+        "The simulation ended before cycle {}",
+        cycle
+    );
+    std::fs::remove_dir_all(&snapshots).unwrap();
+}
+
+/// Every file of the snapshot must be the same, byte for byte, in the expected folder
+fn compare(snapshot: &Path, expected: &Path) {
+    for entry in std::fs::read_dir(snapshot).unwrap() {
+        let path = entry.unwrap().path();
+        let expected_path = expected.join(path.file_name().unwrap());
+        let expected_content = std::fs::read(&expected_path)
+            .unwrap_or_else(|e| panic!("{}: {}", expected_path.display(), e));
+        assert!(
+            std::fs::read(&path).unwrap() == expected_content,
+            "{} differs from {}",
+            path.display(),
+            expected_path.display()
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use pace_sim::isa::{configuration::Configuration, pe::*};
+
+/// A configuration of a single PE, the result register is routed back to op1
+fn configuration(operation: &str, predicate: &str) -> Configuration {
+    Configuration::from_mnemonics(&format!(
+        "operation: {}
+switch_config: {{
+    {} -> predicate,
+    Open -> south_out,
+    Open -> west_out,
+    Open -> north_out,
+    Open -> east_out,
+    Open -> alu_op2,
+    ALURes -> alu_op1,
+}};
+input_register_used: {{}};
+input_register_write: {{}};",
+        operation, predicate
+    ))
+    .unwrap()
+}
+
+fn single_pe(operations: &[&str]) -> PE {
+    PE {
+        configurations: operations
+            .iter()
+            .map(|operation| configuration(operation, "Open"))
+            .collect(),
+        pc: 0,
+        regs: PERegisters::default(),
+        signals: PESignals::default(),
+        is_mem_pe_flag: false,
+        agu_cm_pipeline: vec![None; 2],
+        previous_op: None,
+    }
+}
+
+/// Run a cycle, return the PC of the executed configuration
+fn step(pe: &mut PE) -> usize {
+    let pc = pe.pc;
+    pe.update_alu_out();
+    pe.update_registers(None).expect("PEUpdateError");
+    pe.next_conf();
+    pc
+}
+
+#[test]
+fn test_sext() {
+    let mut pe = single_pe(&[
+        "JUMP 1 [1, 6]",
+        "MOVC! 250",
+        "SEXT! 8",
+        "MOVC! 122",
+        "SEXT! 8",
+        "MOVC! 2048",
+        "SEXT! 12",
+    ]);
+    step(&mut pe);
+    step(&mut pe);
+    step(&mut pe);
+    // 0xFA is negative on 8 bits
+    assert_eq!(pe.regs.reg_res, 0xFFFA);
+    step(&mut pe);
+    step(&mut pe);
+    // 0x7A is positive on 8 bits
+    assert_eq!(pe.regs.reg_res, 0x7A);
+    step(&mut pe);
+    step(&mut pe);
+    // 0x800 is negative on 12 bits
+    assert_eq!(pe.regs.reg_res, 0xF800);
+}
+
+#[test]
+fn test_movc_movcl() {
+    let mut pe = single_pe(&["JUMP 1 [1, 3]", "MOVC! 22136", "MOVCL! 4660", "MOVC! 1"]);
+    step(&mut pe);
+    step(&mut pe);
+    assert_eq!(pe.regs.reg_res, 0x5678);
+    step(&mut pe);
+    // the low half is kept
+    assert_eq!(pe.regs.reg_res, 0x1234_5678);
+    step(&mut pe);
+    // the high half is kept
+    assert_eq!(pe.regs.reg_res, 0x1234_0001);
+}
+
+#[test]
+fn test_br() {
+    let mut pe = single_pe(&["JUMP 1 [1, 4]", "CMP 0", "BR 4", "ADD! 1", "ADD! 16"]);
+    // the predicate of the BR is routed from the CMP
+    pe.configurations[1] = configuration("CMP 0", "ALUOut");
+
+    let pcs: Vec<usize> = (0..8).map(|_| step(&mut pe)).collect();
+    // taken while reg_res is 0, then not taken
+    assert_eq!(pcs, vec![0, 1, 2, 4, 1, 2, 3, 4]);
+    assert_eq!(pe.regs.reg_res, 33);
+}
+
+#[test]
+fn test_float_mode() {
+    // 1.5 = 0x3E00, 2.0 = 0x4000, 0.25 = 0x3400, -2.0 = 0xC000 as FP16
+    let mut pe = single_pe(&[
+        "JUMP 1 [1, 5]",
+        "ADD! 15872",
+        "(f) MULT! 16384",
+        "(f) SUB! 13312",
+        "(f) CGT 49152",
+        "(f) AND! 255",
+    ]);
+    pe.configurations[4] = configuration("(f) CGT 49152", "ALUOut");
+    step(&mut pe);
+    step(&mut pe);
+    step(&mut pe);
+    // 1.5 * 2.0 = 3.0
+    assert_eq!(pe.regs.reg_res, 0x4200);
+    step(&mut pe);
+    // 3.0 - 0.25 = 2.75
+    assert_eq!(pe.regs.reg_res, 0x4180);
+    step(&mut pe);
+    // 2.75 >= -2.0, unlike 0x4180 >= 0xC000
+    assert!(pe.regs.reg_predicate);
+    step(&mut pe);
+    // the bitwise operations are not affected
+    assert_eq!(pe.regs.reg_res, 0x80);
+}
+
+#[test]
+fn test_alu_bypass() {
+    let mut pe = single_pe(&["JUMP 1 [1, 3]", "ADD! 7", "(b) MULT! 3", "(b) NOP"]);
+    step(&mut pe);
+    step(&mut pe);
+    // op1 is forwarded instead of 7 * 3
+    step(&mut pe);
+    assert_eq!(pe.regs.reg_res, 7);
+    pe.update_alu_out();
+    assert_eq!(pe.signals.wire_alu_out, Some(7));
+}
+
+#[test]
+fn test_fp8_format() {
+    let mut pe = single_pe(&["VMUL!", "(e5m2) VMUL!"]);
+    // 0x38 is 1.0 as E4M3 and 0.5 as E5M2, 0x40 is 2.0 in both formats,
+    // 0x7E is 448 as E4M3 (the largest) and a NaN as E5M2, 0x7B is 57344 as E5M2 (the largest)
+    pe.regs.reg_op1 = u64::from_le_bytes([0x38, 0x40, 0x7E, 0x7B, 0, 0, 0, 0]);
+    pe.regs.reg_op2 = u64::from_le_bytes([0x38, 0x40, 0x40, 0x40, 0, 0, 0, 0]);
+
+    pe.update_alu_out();
+    let lanes = pe.signals.wire_alu_out.unwrap().to_le_bytes();
+    // 1 * 1, 2 * 2, 448 * 2 and 352 * 2 overflow to NaN
+    assert_eq!(lanes[..4], [0x38, 0x48, 0x7F, 0x7F]);
+    assert!(
+        pe.snapshot()
+            .contains("reg_op1: 0x4038|SIMD[00111000/1 01000000/2 ")
+    );
+
+    pe.pc = 1;
+    pe.update_alu_out();
+    let lanes = pe.signals.wire_alu_out.unwrap().to_le_bytes();
+    // 0.5 * 0.5, 2 * 2, NaN, 57344 * 2 overflows to the infinity
+    assert_eq!(lanes[..4], [0x34, 0x44, 0x7E, 0x7C]);
+    // the snapshot decodes the lanes in the format of the configuration
+    assert!(
+        pe.snapshot()
+            .contains("reg_op1: 0x4038|SIMD[00111000/0.5 01000000/2 ")
+    );
+}
+
+#[test]
+fn test_int_simd() {
+    let mut pe = single_pe(&["JUMP 1 [1, 3]", "VADDB! 100", "VADDSB! 100", "VSHLSH! 2"]);
+    pe.regs.reg_res = u64::from_le_bytes([1, 50, 0x80, 0, 0, 0, 0, 0xFF]);
+    step(&mut pe);
+    step(&mut pe);
+    // the immediate is added to each i8 lane, wrapping around
+    assert_eq!(
+        pe.regs.reg_res.to_le_bytes(),
+        [101, 150, 0xE4, 100, 100, 100, 100, 99]
+    );
+    assert!(pe.snapshot().contains("I8[01100101/101 10010110/-106 "));
+    step(&mut pe);
+    // 101 + 100 saturates to 127, -106 + 100 and -28 + 100 do not overflow
+    assert_eq!(
+        pe.regs.reg_res.to_le_bytes(),
+        [127, 0xFA, 0x48, 127, 127, 127, 127, 127]
+    );
+    step(&mut pe);
+    // -1409 << 2 fits in an i16 lane, the other lanes saturate
+    assert_eq!(pe.regs.reg_res, 0x7FFF_7FFF_7FFF_E9FC);
+    // the snapshot shows the lanes of the configuration at the PC
+    assert!(
+        pe.snapshot()
+            .contains("reg_res: 0xe9fc|I8[11111100/-4 11101001/-23 ")
+    );
+    pe.pc = 3;
+    assert!(
+        pe.snapshot()
+            .contains("reg_res: 0xe9fc|I16[0xe9fc/-5636 0x7fff/32767 ")
+    );
+}
+
+#[test]
+fn test_fixed_point() {
+    let mut pe = single_pe(&[
+        "JUMP 1 [1, 5]",
+        "MOVC! 24576",
+        "ADD.sat! 16384",
+        "MULT.Q15! 16384",
+        "MAC! 2",
+        "MAC.sat! 2",
+    ]);
+    step(&mut pe);
+    step(&mut pe);
+    step(&mut pe);
+    // 0.75 + 0.5 saturates to the largest Q15 value
+    assert_eq!(pe.regs.reg_res, 0x7FFF);
+    step(&mut pe);
+    // (1 - 2^-15) * 0.5 rounds to 0.5
+    assert_eq!(pe.regs.reg_res, 0x4000);
+    step(&mut pe);
+    // reg_res + op1 * 2 = 3 * 16384 wraps around
+    assert_eq!(pe.regs.reg_res, 0xC000);
+    step(&mut pe);
+    // 3 * -16384 saturates
+    assert_eq!(pe.regs.reg_res, 0x8000);
+}
+
+#[test]
+fn test_bf16_float_mode() {
+    // 1.5 = 0x3FC0, 2.0 = 0x4000, 0.25 = 0x3E80, -2.0 = 0xC000, 2^-7 = 0x3C00 as BF16
+    let mut pe = single_pe(&[
+        "JUMP 1 [1, 5]",
+        "ADD! 16320",
+        "(f) (bf16) MULT! 16384",
+        "(f) (bf16) SUB! 16000",
+        "(f) (bf16) CGT 49152",
+        "(f) (bf16) ADD! 15360",
+    ]);
+    pe.configurations[4] = configuration("(f) (bf16) CGT 49152", "ALUOut");
+    step(&mut pe);
+    step(&mut pe);
+    step(&mut pe);
+    // 1.5 * 2.0 = 3.0
+    assert_eq!(pe.regs.reg_res, 0x4040);
+    step(&mut pe);
+    // 3.0 - 0.25 = 2.75
+    assert_eq!(pe.regs.reg_res, 0x4030);
+    step(&mut pe);
+    // 2.75 >= -2.0
+    assert!(pe.regs.reg_predicate);
+    // the snapshot shows the low 16 bits in the float format of the configuration,
+    // the debug view as FP16
+    assert!(pe.snapshot().contains("reg_res: 0x4030|SIMD["));
+    assert!(pe.snapshot().contains("|BF16(2.75)\nreg_north_in"));
+    assert!(format!("{:?}", pe.regs).contains("|FP16(2.09375)\nreg_north_in"));
+    step(&mut pe);
+    // 2.75 + 2^-7 is a tie between 2.75 and 2.765625, rounded to the even mantissa
+    assert_eq!(pe.regs.reg_res, 0x4030);
+}
@@ -0,0 +1,99 @@
+use pace_sim::isa::{configuration::Configuration, pe::*};
+
+fn configuration(operation: &str, predicate: &str) -> Configuration {
+    Configuration::from_mnemonics(&format!(
+        "operation: {}
+switch_config: {{
+    {} -> predicate,
+    Open -> south_out,
+    Open -> west_out,
+    Open -> north_out,
+    Open -> east_out,
+    Open -> alu_op2,
+    ALURes -> alu_op1,
+}};
+input_register_used: {{}};
+input_register_write: {{}};",
+        operation, predicate
+    ))
+    .unwrap()
+}
+
+fn single_pe(configurations: Vec<Configuration>) -> PE {
+    PE {
+        configurations,
+        pc: 0,
+        regs: PERegisters::default(),
+        signals: PESignals::default(),
+        is_mem_pe_flag: false,
+        agu_cm_pipeline: vec![None; 2],
+        previous_op: None,
+    }
+}
+
+fn run(pe: &mut PE, cycles: usize) {
+    for _ in 0..cycles {
+        pe.update_alu_out();
+        pe.update_registers(None).expect("PEUpdateError");
+        pe.next_conf();
+    }
+}
+
+/// A predicated ADD! only updates reg_res when the predicate routed from a CMP is true
+#[test]
+fn test_predicated_execution() {
+    let configurations = vec![
+        configuration("JUMP 1 [1, 4]", "Open"),
+        // op1 is 0: the predicate is true
+        configuration("CMP 0", "ALUOut"),
+        configuration("(p) ADD! 5", "Open"),
+        // op1 is 5: the predicate is false
+        configuration("CMP 0", "ALUOut"),
+        configuration("(p) ADD! 7", "Open"),
+    ];
+    let mut pe = single_pe(configurations);
+
+    run(&mut pe, 3);
+    assert!(pe.regs.reg_predicate);
+    assert_eq!(pe.regs.reg_res, 5);
+
+    run(&mut pe, 2);
+    assert!(!pe.regs.reg_predicate);
+    assert_eq!(pe.regs.reg_res, 5, "the predicated ADD! must be suppressed");
+    // the loop goes on after the suppressed configuration
+    assert_eq!(pe.pc, 1);
+}
+
+/// The predicate is the LSB of the routed value, registered like the operands
+#[test]
+fn test_predicate_from_res() {
+    let configurations = vec![
+        configuration("JUMP 1 [1, 3]", "Open"),
+        configuration("ADD! 3", "Open"),
+        configuration("NOP", "ALURes"),
+        configuration("(p) ADD! 1", "Open"),
+    ];
+    let mut pe = single_pe(configurations);
+
+    run(&mut pe, 3);
+    assert!(pe.regs.reg_predicate);
+    run(&mut pe, 1);
+    assert_eq!(pe.regs.reg_res, 4);
+}
+
+/// JUMP resets the predicate, even if one is routed in the same configuration
+#[test]
+fn test_jump_resets_predicate() {
+    let configurations = vec![
+        configuration("JUMP 1 [1, 2]", "ALURes"),
+        configuration("(p) ADD! 1", "Open"),
+        configuration("NOP", "Open"),
+    ];
+    let mut pe = single_pe(configurations);
+    pe.regs.reg_predicate = true;
+    pe.regs.reg_res = 1;
+
+    run(&mut pe, 2);
+    assert!(!pe.regs.reg_predicate);
+    assert_eq!(pe.regs.reg_res, 1);
+}
@@ -0,0 +1,35 @@
+use pace_sim::sim::{grid::Grid, route_check::RouterLoopCheck, topology::MemoryTopology};
+
+#[test]
+fn test_route_check_kernels() {
+    let kernels = [
+        ("tests/add_2x2", MemoryTopology::double_sided()),
+        ("tests/array_add_2x2", MemoryTopology::double_sided()),
+        ("tests/complex_scalar_8x8", MemoryTopology::double_sided()),
+        ("tests/gemm", MemoryTopology::double_sided()),
+        (
+            "tests/single_sided_array_add_4x4",
+            MemoryTopology::single_sided(),
+        ),
+        (
+            "tests/single_sided_fvmac_2x2",
+            MemoryTopology::single_sided(),
+        ),
+    ];
+    for (folder, topology) in kernels {
+        let grid = Grid::from_folder(folder, topology).expect("Failed to load the grid");
+        let diagnostics: Vec<String> = grid.check_routing().iter().map(|d| d.to_string()).collect();
+        assert!(
+            diagnostics.is_empty(),
+            "{}:\n{}",
+            folder,
+            diagnostics.join("\n")
+        );
+        assert_eq!(
+            grid.check_router_loops(),
+            RouterLoopCheck::default(),
+            "{}",
+            folder
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use pace_sim::sim::{
+    grid::{Grid, PEIdx, SimulationError},
+    loader::LoadError,
+    rtl_trace::{Divergence, RtlTrace, TraceEvent},
+    topology::MemoryTopology,
+};
+
+const TEST_FOLDER: &str = "tests/array_add_2x2";
+
+fn load_grid() -> Grid {
+    Grid::from_folder(TEST_FOLDER, MemoryTopology::double_sided()).expect("Failed to load the grid")
+}
+
+/// The trace of the simulation itself, standing for the one of the RTL
+fn record() -> RtlTrace {
+    let mut grid = load_grid();
+    let mut trace = RtlTrace::default();
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return trace,
+            Err(e) => panic!("Simulation failed at cycle {}: {:?}", grid.cycle, e),
+        }
+        trace.record(&grid);
+        grid.next_cycle();
+    }
+}
+
+/// Run the grid in lock-step with the trace, return the number of cycles
+fn run_lockstep(trace: &RtlTrace) -> Result<usize, Divergence> {
+    let mut grid = load_grid();
+    trace.validate(&grid).unwrap();
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => return Ok(grid.cycle),
+            Err(e) => panic!("Simulation failed at cycle {}: {:?}", grid.cycle, e),
+        }
+        trace.check(&grid)?;
+        grid.next_cycle();
+    }
+}
+
+#[test]
+fn test_lockstep() {
+    let trace = record();
+    assert_eq!(trace.last_cycle(), Some(28));
+    // the trace format round-trips
+    let path = std::env::temp_dir().join("pace_sim_test_rtl_trace.txt");
+    std::fs::write(&path, format!("# array_add_2x2\n{}", trace)).unwrap();
+    let loaded = RtlTrace::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, trace);
+    assert_eq!(run_lockstep(&trace), Ok(29));
+}
+
+#[test]
+fn test_divergence() {
+    let pe = PEIdx { x: 1, y: 0 };
+    let mut trace = record();
+    for event in trace.cycles.get_mut(&10).unwrap().iter_mut() {
+        if let TraceEvent::Reg {
+            pe: event_pe,
+            value,
+            ..
+        } = event
+            && *event_pe == pe
+        {
+            *value ^= 1;
+        }
+    }
+    let divergence = run_lockstep(&trace).unwrap_err();
+    assert_eq!(divergence.cycle, 10);
+    assert_eq!(divergence.signal, "PE-Y0X1 reg_op1");
+    let (conf_pe, conf) = divergence.conf.as_ref().unwrap();
+    assert_eq!(*conf_pe, pe);
+    assert!(conf.starts_with("operation:"), "{}", conf);
+    assert!(divergence.to_string().starts_with(&format!(
+        "cycle 10: PE-Y0X1 reg_op1 differs, RTL {}, simulation {}\nPE-Y0X1 executes:\n",
+        divergence.rtl, divergence.sim
+    )));
+
+    // a store missing from the RTL
+    let mut trace = record();
+    let (cycle, events) = trace
+        .cycles
+        .iter_mut()
+        .find(|(_, events)| {
+            events
+                .iter()
+                .any(|e| matches!(e, TraceEvent::DmWrite { .. }))
+        })
+        .unwrap();
+    let cycle = *cycle;
+    events.retain(|e| !matches!(e, TraceEvent::DmWrite { .. }));
+    let divergence = run_lockstep(&trace).unwrap_err();
+    assert_eq!(divergence.cycle, cycle);
+    assert_eq!(divergence.signal, "dm1 writes");
+    assert_eq!(divergence.rtl, "no write");
+    assert_eq!(divergence.conf, None);
+}
+
+#[test]
+fn test_invalid_trace() {
+    let path = std::env::temp_dir().join("pace_sim_test_invalid_rtl_trace.txt");
+    std::fs::write(&path, "0 PE-Y0X0 pc 0\n\n  1 PE-Y0X0 reg_foo 0x1\n").unwrap();
+    let e = RtlTrace::load(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    match e {
+        LoadError::Parse {
+            line,
+            column,
+            message,
+            ..
+        } => {
+            assert_eq!((line, column), (3, 3));
+            assert_eq!(message, "Unknown register `reg_foo`");
+        }
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+
+    let mut trace = RtlTrace::default();
+    trace.push(3, "dm4 write 0 0".parse().unwrap());
+    assert_eq!(
+        trace.validate(&load_grid()),
+        Err("cycle 3: `dm4 write 0x0 0x0` is not in the grid".to_string())
+    );
+}
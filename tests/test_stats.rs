@@ -0,0 +1,107 @@
+use pace_sim::{
+    agu::agu::AGU,
+    isa::{configuration::Program, pe::PE},
+    sim::{
+        dmem::DataMemory,
+        grid::{Grid, PEIdx, SimulationError},
+        topology::MemoryTopology,
+    },
+};
+
+#[test]
+fn test_stats_gemm() {
+    let mut grid = Grid::from_folder("tests/gemm", MemoryTopology::double_sided())
+        .expect("Failed to load the grid");
+    let mut cycle = 0;
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => break,
+            Err(e) => panic!("Unexpected simulation error at cycle {}: {:?}", cycle, e),
+        }
+        grid.next_cycle();
+        cycle += 1;
+    }
+
+    let stats = &grid.stats;
+    assert_eq!(stats.cycles, cycle);
+    assert!(stats.router_hops > 0);
+    assert!(stats.max_router_hops * stats.cycles >= stats.router_hops);
+    let reads: u64 = stats.ports.iter().map(|p| p.reads()).sum();
+    let writes: u64 = stats.ports.iter().map(|p| p.writes()).sum();
+    assert!(reads > 0);
+    assert!(writes > 0);
+    assert_eq!(stats.agu_iterations.len(), grid.agus.len());
+    for (iterations, agu) in stats.agu_iterations.iter().zip(grid.agus.iter()) {
+        assert_eq!(*iterations, agu.count as u64);
+    }
+    let mut alu_ops = 0;
+    for y in 0..grid.shape.y {
+        for x in 0..grid.shape.x {
+            let pe = &stats.pes[y][x];
+            assert_eq!(pe.ops.values().sum::<u64>(), pe.alu_ops());
+            assert!(stats.utilization(PEIdx { x, y }) <= 100.0);
+            alu_ops += pe.alu_ops();
+        }
+    }
+    assert!(alu_ops > 0);
+    let report = stats.to_string();
+    assert!(report.starts_with(&format!("Cycles: {}\n", cycle)));
+}
+
+/// A PE executing the operation every cycle after the first one
+fn program(operation: &str) -> Program {
+    let mut program = String::new();
+    for operation in ["JUMP [1, 1]", operation] {
+        program.push_str(&format!(
+            "operation: {}\nswitch_config: {{ Open -> predicate, }};\ninput_register_used: {{}};\ninput_register_write: {{}};\n",
+            operation
+        ));
+    }
+    Program::from_mnemonics(&program).unwrap()
+}
+
+#[test]
+fn test_stats_ended_by_agu() {
+    // the PEs add and the memory PEs load every cycle until their AGUs end the simulation,
+    // Y1X1 is predicated off
+    let agu = || AGU::from_mnemonics("CM: LOAD,STRIDED,B16,2 ARF: 0 MAX COUNT: 4").unwrap();
+    let mut grid = Grid::new(
+        PEIdx { x: 2, y: 2 },
+        vec![
+            vec![
+                PE::new_mem_pe(program("ADD!? 1")),
+                PE::new(program("ADD! 1")),
+            ],
+            vec![
+                PE::new_mem_pe(program("ADD!? 1")),
+                PE::new(program("(p) ADD! 1")),
+            ],
+        ],
+        vec![DataMemory::new(64)],
+        vec![agu(), agu()],
+        MemoryTopology::single_sided(),
+    );
+    loop {
+        match grid.simulate_cycle() {
+            Ok(()) => {}
+            Err(SimulationError::SimulationEnd) => break,
+            Err(e) => panic!("Unexpected simulation error: {:?}", e),
+        }
+        grid.next_cycle();
+    }
+
+    let stats = &grid.stats;
+    assert!(stats.cycles > 0);
+    for y in 0..grid.shape.y {
+        for x in 0..grid.shape.x {
+            assert!(stats.utilization(PEIdx { x, y }) <= 100.0);
+        }
+    }
+    assert_eq!(stats.pes[0][0].alu_ops(), stats.cycles - 1);
+    assert_eq!(stats.pes[1][1].alu_ops(), 0);
+    assert_eq!(stats.pes[1][1].res_writes, 0);
+    for port in stats.ports.iter() {
+        assert_eq!(port.reads(), stats.cycles - 1);
+    }
+}
@@ -0,0 +1,32 @@
+use pace_sim::sim::{grid::Grid, topology::MemoryTopology, vcd::VcdWriter};
+
+#[test]
+fn test_vcd_gemm() {
+    let mut grid = Grid::from_folder("tests/gemm", MemoryTopology::double_sided())
+        .expect("Failed to load the grid");
+    let range = "PE-Y0X0:PE-Y1X1".parse().unwrap();
+    let mut vcd = VcdWriter::new(Vec::new(), &grid, Some(range)).unwrap();
+    for cycle in 0..4 {
+        grid.simulate_cycle().unwrap();
+        vcd.sample(&grid, cycle).unwrap();
+        grid.next_cycle();
+    }
+    vcd.flush().unwrap();
+    let vcd = String::from_utf8(vcd.into_inner()).unwrap();
+
+    // only the PEs in the range, and the ports and AGUs of the memory PEs Y0X0 and Y1X0
+    assert!(vcd.contains("$scope module PE_Y1X1 $end"));
+    assert!(!vcd.contains("PE_Y2X0"));
+    assert!(!vcd.contains("PE_Y0X2"));
+    assert!(vcd.contains("$scope module dm0 $end\n$scope module port1 $end"));
+    assert!(vcd.contains("$scope module agu1 $end"));
+    assert!(!vcd.contains("agu2"));
+    assert_eq!(
+        vcd.matches("$scope").count(),
+        vcd.matches("$upscope").count()
+    );
+    assert_eq!(vcd.matches("$var wire").count(), 4 * 20 + 2 * 5 + 2 * 2);
+    assert!(vcd.contains("\n#3\n"));
+    // the wires are not all driven: x values
+    assert!(vcd.contains("\nbx "));
+}
@@ -0,0 +1,123 @@
+use pace_sim::{
+    isa::{configuration::Configuration, fp8::FP8Format, pe::*},
+    sim::dmem::DataMemory,
+};
+
+fn configuration(operation: &str) -> Configuration {
+    Configuration::from_mnemonics(&format!(
+        "operation: {}
+switch_config: {{
+    Open -> predicate,
+    Open -> south_out,
+    Open -> west_out,
+    Open -> north_out,
+    Open -> east_out,
+    Open -> alu_op2,
+    Open -> alu_op1,
+}};
+input_register_used: {{}};
+input_register_write: {{}};",
+        operation
+    ))
+    .unwrap()
+}
+
+/// The ALU output of a single configuration with the given registers
+fn alu_out(operation: &str, op1: u64, op2: u64, res: u64) -> u64 {
+    let mut pe = PE {
+        configurations: vec![configuration(operation)],
+        pc: 0,
+        regs: PERegisters::default(),
+        signals: PESignals::default(),
+        is_mem_pe_flag: false,
+        agu_cm_pipeline: vec![None; 2],
+        previous_op: None,
+    };
+    pe.regs.reg_op1 = op1;
+    pe.regs.reg_op2 = op2;
+    pe.regs.reg_res = res;
+    pe.update_alu_out();
+    pe.signals
+        .wire_alu_out
+        .expect("The ALU output is not driven")
+}
+
+/// a, b and c of the FVMAC test
+fn fvmac_operands() -> (u64, u64, u64) {
+    let dm0 = DataMemory::from_binary_str(
+        &std::fs::read_to_string("tests/single_sided_fvmac_2x2/dm0").unwrap(),
+    );
+    (dm0.read64(0), dm0.read64(8), dm0.read64(16))
+}
+
+/// VMAC gives the result of VMUL then VADD when the products are exact,
+/// the exact a * b + c rounded once otherwise
+#[test]
+fn test_vmac_against_vmul_vadd() {
+    let (a, b, c) = fvmac_operands();
+    for (prefix, format) in [("", FP8Format::E4M3), ("(e5m2) ", FP8Format::E5M2)] {
+        let product = alu_out(&format!("{}VMUL", prefix), a, b, 0);
+        let sequence = alu_out(&format!("{}VADD", prefix), product, c, 0);
+        let fused = alu_out(&format!("{}VMAC", prefix), a, b, c);
+        let lanes = |v: u64| v.to_le_bytes().map(|lane| format.decode(lane));
+        let (a, b, c, product) = (lanes(a), lanes(b), lanes(c), lanes(product));
+        for i in 0..8 {
+            let exact = a[i] * b[i] + c[i];
+            let (fused, sequence) = (fused.to_le_bytes()[i], sequence.to_le_bytes()[i]);
+            assert_eq!(fused, format.encode(exact, false), "{} lane {}", format, i);
+            if product[i] == a[i] * b[i] || product[i].is_nan() {
+                assert_eq!(fused, sequence, "{} lane {}", format, i);
+            }
+        }
+    }
+}
+
+/// The inner loop of a GEMM: VMAC accumulates in one step what VMUL and VADD accumulate in two,
+/// with the same results on exact products
+#[test]
+fn test_vmac_accumulation() {
+    // 1.0, 2.0, 0.5, -1.5 and 0 as E4M3
+    let a = u64::from_le_bytes([0x38, 0x40, 0x30, 0xBC, 0x00, 0x38, 0x40, 0x30]);
+    let b = u64::from_le_bytes([0x40, 0x30, 0xBC, 0x38, 0x38, 0x00, 0x40, 0x40]);
+    let (mut fused, mut sequence) = (0, 0);
+    for _ in 0..4 {
+        fused = alu_out("VMAC!", a, b, fused);
+        let product = alu_out("VMUL", a, b, 0);
+        sequence = alu_out("VADD!", product, sequence, 0);
+    }
+    assert_eq!(fused, sequence);
+    // 4 * (1 * 2), 4 * (2 * 0.5), 4 * (0.5 * -1.5), 4 * (-1.5 * 1), 0, 0, 4 * (2 * 2), 4 * (0.5 * 2)
+    assert_eq!(
+        fused.to_le_bytes(),
+        [0x50, 0x48, 0xC4, 0xCC, 0x00, 0x00, 0x58, 0x48]
+    );
+}
+
+/// VDOT reduces the products into the f32 of the low 32 bits of reg_res
+#[test]
+fn test_vdot() {
+    let (a, b, _) = fvmac_operands();
+    for (prefix, format) in [("", FP8Format::E4M3), ("(e5m2) ", FP8Format::E5M2)] {
+        let acc = 1.5f32;
+        let result = alu_out(
+            &format!("{}VDOT", prefix),
+            a,
+            b,
+            0xFFFF_FFFF_0000_0000 | acc.to_bits() as u64,
+        );
+        // the high bits are cleared
+        assert_eq!(result >> 32, 0);
+        let (a, b) = (a.to_le_bytes(), b.to_le_bytes());
+        let exact: f64 = (0..8)
+            .map(|i| format.decode(a[i]) * format.decode(b[i]))
+            .sum::<f64>()
+            + acc as f64;
+        assert_eq!(f32::from_bits(result as u32), exact as f32, "{}", format);
+    }
+
+    // 1 * 2 + 2 * 0.5 + 0.5 * -1.5 + -1.5 * 1 + 2 * 2 + 0.5 * 2 accumulated on 0.25
+    let a = u64::from_le_bytes([0x38, 0x40, 0x30, 0xBC, 0x00, 0x38, 0x40, 0x30]);
+    let b = u64::from_le_bytes([0x40, 0x30, 0xBC, 0x38, 0x38, 0x00, 0x40, 0x40]);
+    let result = alu_out("VDOT!", a, b, 0.25f32.to_bits() as u64);
+    assert_eq!(f32::from_bits(result as u32), 6.0);
+}